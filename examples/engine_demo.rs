@@ -14,6 +14,7 @@ fn main() {
         exploration_constant: 1.414,
         gpu_batch_size: 2048,
         use_gpu_simulation: true,
+        ..EngineConfig::default()
     };
 
     println!("Creating MCTS engine with following difficulty...");
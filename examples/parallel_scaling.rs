@@ -0,0 +1,69 @@
+use arx_engine::{engine::{parallel::Parallelism, EngineConfig, MctsEngine}, Game};
+use std::time::Instant;
+
+/// Compare root-parallel and tree-parallel search throughput for growing thread counts.
+/// Usage: cargo run --release --example parallel_scaling [simulations_per_move]
+fn main() {
+    let simulations_per_move: u32 = std::env::args()
+        .nth(1)
+        .and_then(|arg| arg.parse().ok())
+        .unwrap_or(200);
+    let board = Game::new().to_binary();
+    let cpus = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+
+    let mut thread_counts = vec![1];
+    while thread_counts.last().unwrap() * 2 <= cpus * 2 {
+        thread_counts.push(thread_counts.last().unwrap() * 2);
+    }
+
+    println!("Arx Engine - Parallel Search Scaling");
+    println!("====================================\n");
+    println!("  CPUs: {}", cpus);
+    println!("  Simulations per move: {}\n", simulations_per_move);
+    println!("{:<6} {:>8} {:>12} {:>14} {:>9}", "Mode", "Threads", "Time (ms)", "Simulations/s", "Speedup");
+
+    for parallelism in [Parallelism::Root, Parallelism::Tree] {
+        let mut baseline = None;
+        for &threads in &thread_counts {
+            let config = EngineConfig {
+                max_depth: 8,
+                simulations_per_move,
+                use_gpu_simulation: false,
+                reuse_tree: false,
+                parallelism,
+                search_threads: threads,
+                ..EngineConfig::default()
+            };
+            let mut engine = match MctsEngine::with_config(config) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    eprintln!("✗ Failed to create engine: {}", e);
+                    return;
+                }
+            };
+
+            // Root-parallel searches run on the rayon pool, so size it to the thread count
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .expect("Failed to build thread pool");
+            let start = Instant::now();
+            if let Err(e) = pool.install(|| engine.search(&board)) {
+                eprintln!("✗ Search failed: {}", e);
+                return;
+            }
+            let elapsed = start.elapsed().as_secs_f64();
+
+            let rate = engine.get_statistics().simulations_run as f64 / elapsed;
+            let speedup = rate / *baseline.get_or_insert(rate);
+            println!(
+                "{:<6} {:>8} {:>12.1} {:>14.0} {:>8.2}x",
+                format!("{:?}", parallelism),
+                threads,
+                elapsed * 1000.0,
+                rate,
+                speedup
+            );
+        }
+    }
+}
@@ -8,6 +8,7 @@ const moveHistoryTextarea = document.getElementById('move-history');
 const loadGameBtn = document.getElementById('load-game-btn');
 const undoBtn = document.getElementById('undo-btn');
 const askEngineBtn = document.getElementById('ask-engine-btn');
+const engineLevelSelect = document.getElementById('engine-level');
 
 let config = null;
 let boardData = null;
@@ -365,7 +366,9 @@ askEngineBtn.addEventListener('click', async () => {
         askEngineBtn.innerText = 'Thinking...';
 
         // Request engine move
-        const response = await fetch(`${config.backendUrl}/engine-move`, {
+        const level = engineLevelSelect.value;
+        const query = level ? `?level=${encodeURIComponent(level)}` : '';
+        const response = await fetch(`${config.backendUrl}/engine-move${query}`, {
             method: 'POST',
             headers: { 'Content-Type': 'application/octet-stream' },
             body: boardData,
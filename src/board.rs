@@ -1,12 +1,24 @@
+use serde::{Deserialize, Serialize};
+
 pub const BOARD_DIMENSION: usize = 9; // 9x9 board
 pub const BOARD_SIZE: usize = BOARD_DIMENSION * BOARD_DIMENSION; // Total number of squares
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Color {
     White,
     Black,
 }
 
+impl Color {
+    pub fn opposite(&self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Position {
     pub x: usize, // 0-8 for columns
@@ -24,8 +36,8 @@ impl Position {
     pub const DIAGONAL_MOVES: [(isize, isize); 4] = [
         (1, 1),   // Down-Right
         (1, -1),  // Up-Right
-        (-1, -1), // Up-Left
         (-1, 1),  // Down-Left
+        (-1, -1), // Up-Left
     ];
 
     pub const ALL_MOVES: [(isize, isize); 8] = [
@@ -35,8 +47,8 @@ impl Position {
         (0, -1),  // Up
         (1, 1),   // Down-Right
         (1, -1),  // Up-Right
-        (-1, -1), // Up-Left
         (-1, 1),  // Down-Left
+        (-1, -1), // Up-Left
     ];
 
     pub fn new(x: usize, y: usize) -> Self {
@@ -77,12 +89,34 @@ impl Position {
         Some(Position::new(new_x as usize, new_y as usize))
     }
 
-    pub fn to_string(&self) -> String {
-        format!("{}{}", (b'A' + self.x as u8) as char, 9 - self.y)
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", (b'A' + self.x as u8) as char, 9 - self.y)
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+impl std::str::FromStr for Position {
+    type Err = String;
+
+    /// Parse a square name such as `E2` (case-insensitive); A1 is (0,8), I9 is (8,0)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[column, row] => {
+                let column = column.to_ascii_uppercase();
+                if !(b'A'..=b'I').contains(&column) || !(b'1'..=b'9').contains(&row) {
+                    return Err(format!("Invalid square '{}'. Use e.g. 'E2'.", s));
+                }
+                Ok(Position::new((column - b'A') as usize, (b'9' - row) as usize))
+            }
+            _ => Err(format!("Invalid square '{}'. Use e.g. 'E2'.", s)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum PieceType {
     Soldier = 0b001,   // 1
     Jester = 0b010,    // 2
@@ -94,6 +128,66 @@ pub enum PieceType {
     King,              // Handled specially, its discriminant (8) is not used in 3-bit piece codes
 }
 
+impl PieceType {
+    const KNIGHT_MOVES: [(isize, isize); 8] = [
+        (2, 1), (2, -1), (-2, 1), (-2, -1),
+        (1, 2), (1, -2), (-1, 2), (-1, -2),
+    ];
+
+    /// Directions this piece type moves (and captures) along; "forward" depends on its color.
+    /// They are listed in the order the GPU move generator visits them.
+    pub fn directions(&self, color: Color) -> &'static [(isize, isize)] {
+        match (self, color) {
+            (PieceType::Soldier, Color::White) => &[(1, -1), (-1, -1)],
+            (PieceType::Soldier, Color::Black) => &[(1, 1), (-1, 1)],
+            (PieceType::Ballista, Color::White) => &[(0, -1)],
+            (PieceType::Ballista, Color::Black) => &[(0, 1)],
+            (PieceType::Jester, _) | (PieceType::Guard, _) => &Position::DIAGONAL_MOVES,
+            (PieceType::Commander, _) | (PieceType::Paladin, _) => &Position::ORTHOGONAL_MOVES,
+            (PieceType::Dragon, _) => &Self::KNIGHT_MOVES,
+            (PieceType::King, _) => &Position::ALL_MOVES,
+        }
+    }
+
+    /// Maximum number of steps along each direction
+    pub fn range(&self) -> isize {
+        match self {
+            PieceType::Jester | PieceType::Commander | PieceType::Ballista => BOARD_DIMENSION as isize,
+            PieceType::Paladin | PieceType::Guard => 2,
+            PieceType::Soldier | PieceType::Dragon | PieceType::King => 1,
+        }
+    }
+
+    /// Letter of the piece in board notation (uppercase)
+    pub fn symbol(&self) -> char {
+        match self {
+            PieceType::Soldier => 'S',
+            PieceType::Jester => 'J',
+            PieceType::Commander => 'C',
+            PieceType::Paladin => 'P',
+            PieceType::Guard => 'G',
+            PieceType::Dragon => 'D',
+            PieceType::Ballista => 'B',
+            PieceType::King => 'K',
+        }
+    }
+
+    /// Piece of a board notation letter, in either case
+    pub fn from_symbol(symbol: char) -> Option<PieceType> {
+        match symbol.to_ascii_uppercase() {
+            'S' => Some(PieceType::Soldier),
+            'J' => Some(PieceType::Jester),
+            'C' => Some(PieceType::Commander),
+            'P' => Some(PieceType::Paladin),
+            'G' => Some(PieceType::Guard),
+            'D' => Some(PieceType::Dragon),
+            'B' => Some(PieceType::Ballista),
+            'K' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct Piece {
     pub color: Color,
@@ -222,8 +316,34 @@ impl Piece {
         }
     }
 
+    /// Build the stack obtained by moving `moving_piece` on top of this piece
+    /// Returns an error if stacking is not allowed
+    pub fn stacked_with(&self, moving_piece: Piece) -> Result<Piece, String> {
+        // Check if stacking is allowed
+        if !self.is_stackable() {
+            return Err("Cannot stack onto this piece (King or already stacked)".to_string());
+        }
+
+        // Check if pieces are same color
+        if self.color != moving_piece.color {
+            return Err("Cannot stack pieces of different colors".to_string());
+        }
+
+        // Check if moving piece is a single piece (not already stacked)
+        if moving_piece.top.is_some() {
+            return Err("Cannot stack an already stacked piece".to_string());
+        }
+
+        // Create new stacked piece: moving piece goes on top, existing piece becomes bottom
+        Ok(Piece {
+            color: self.color,
+            bottom: self.bottom,
+            top: Some(moving_piece.bottom),
+        })
+    }
+
     // Helper to convert 3-bit code to PieceType (excluding King)
-    fn code_to_piece_type(code: u8) -> Option<PieceType> {
+    pub(crate) fn code_to_piece_type(code: u8) -> Option<PieceType> {
         match code {
             0b001 => Some(PieceType::Soldier),
             0b010 => Some(PieceType::Jester),
@@ -243,6 +363,12 @@ pub struct Board {
     white_to_move: bool,               // true if it's white's turn to move
 }
 
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Board {
     pub fn new() -> Self {
         let mut data = [None; BOARD_SIZE]; // Initialize all to empty
@@ -323,14 +449,12 @@ impl Board {
     pub fn is_game_over(&self) -> bool {
         // Scan the board for both kings
         let mut king_found = false;
-        for piece_opt in self.data.iter() {
-            if let Some(piece) = piece_opt {
-                if piece.is_king() {
-                    if king_found {
-                        return false; // Both kings found, game is not over
-                    }
-                    king_found = true;
+        for piece in self.data.iter().flatten() {
+            if piece.is_king() {
+                if king_found {
+                    return false; // Both kings found, game is not over
                 }
+                king_found = true;
             }
         }
         // If either king is missing, the game is over
@@ -345,6 +469,90 @@ impl Board {
         self.data[position.to_absolute()] = piece;
     }
 
+    /// Squares attacked by the piece or stack standing on `position`.
+    /// A stack attacks with the movement of both its top and bottom pieces.
+    pub fn attacks_from(&self, position: &Position) -> Vec<Position> {
+        let mut attacks = Vec::new();
+        let Some(piece) = self.get_piece(position) else {
+            return attacks;
+        };
+
+        for piece_type in [Some(piece.bottom), piece.top].into_iter().flatten() {
+            for &(dx, dy) in piece_type.directions(piece.color) {
+                for mult in 1..=piece_type.range() {
+                    let Some(target) = position.get_new(dx * mult, dy * mult) else {
+                        break;
+                    };
+                    if !attacks.contains(&target) {
+                        attacks.push(target);
+                    }
+                    // Sliding pieces are stopped by the first piece they reach
+                    if self.get_piece(&target).is_some() {
+                        break;
+                    }
+                }
+            }
+        }
+
+        attacks
+    }
+
+    /// All squares attacked by at least one piece of `color`, in board order
+    pub fn attacked_squares(&self, color: Color) -> Vec<Position> {
+        let attacked = self.attack_map(color);
+        (0..BOARD_SIZE)
+            .filter(|&index| attacked[index])
+            .map(|index| Position::from_u8(index as u8))
+            .collect()
+    }
+
+    /// Whether each square, by absolute index, is attacked by a piece of `color`
+    fn attack_map(&self, color: Color) -> [bool; BOARD_SIZE] {
+        let mut attacked = [false; BOARD_SIZE];
+        for (index, piece) in self.data.iter().enumerate() {
+            if piece.is_some_and(|p| p.color == color) {
+                for target in self.attacks_from(&Position::from_u8(index as u8)) {
+                    attacked[target.to_absolute()] = true;
+                }
+            }
+        }
+        attacked
+    }
+
+    /// Positions of every piece (of either color) attacking `position`.
+    /// Friendly attackers of an occupied square are its defenders.
+    pub fn attackers_of(&self, position: &Position) -> Vec<Position> {
+        (0..BOARD_SIZE)
+            .map(|index| Position::from_u8(index as u8))
+            .filter(|from| self.attacks_from(from).contains(position))
+            .collect()
+    }
+
+    /// Whether the king of `color` could be captured by the opponent's next move
+    pub fn king_in_danger(&self, color: Color) -> bool {
+        self.find_king(color)
+            .is_some_and(|king| self.attack_map(color.opposite())[king.to_absolute()])
+    }
+
+    /// Pieces of `color` (king excluded) attacked by the opponent and not defended
+    pub fn hanging_pieces(&self, color: Color) -> Vec<Position> {
+        let attacked = self.attack_map(color.opposite());
+        let defended = self.attack_map(color);
+        (0..BOARD_SIZE)
+            .filter(|&index| attacked[index] && !defended[index])
+            .filter(|&index| self.data[index].is_some_and(|p| p.color == color && !p.is_king()))
+            .map(|index| Position::from_u8(index as u8))
+            .collect()
+    }
+
+    /// Position of the king of `color`, if it is still on the board
+    pub fn find_king(&self, color: Color) -> Option<Position> {
+        self.data
+            .iter()
+            .position(|piece| piece.is_some_and(|p| p.is_king() && p.color == color))
+            .map(|index| Position::from_u8(index as u8))
+    }
+
     pub fn unstack_piece(&mut self, position: &Position) -> Result<Piece, String> {
         let piece = self.get_piece(position);
         if piece.is_none() {
@@ -378,29 +586,7 @@ impl Board {
         if existing_piece.is_none() {
             return Err("No piece at position to stack onto".to_string());
         }
-        let existing_piece = existing_piece.unwrap();
-
-        // Check if stacking is allowed
-        if !existing_piece.is_stackable() {
-            return Err("Cannot stack onto this piece (King or already stacked)".to_string());
-        }
-
-        // Check if pieces are same color
-        if existing_piece.color != moving_piece.color {
-            return Err("Cannot stack pieces of different colors".to_string());
-        }
-
-        // Check if moving piece is a single piece (not already stacked)
-        if moving_piece.top.is_some() {
-            return Err("Cannot stack an already stacked piece".to_string());
-        }
-
-        // Create new stacked piece: moving piece goes on top, existing piece becomes bottom
-        let stacked_piece = Piece {
-            color: existing_piece.color,
-            bottom: existing_piece.bottom,
-            top: Some(moving_piece.bottom),
-        };
+        let stacked_piece = existing_piece.unwrap().stacked_with(moving_piece)?;
 
         self.set_piece(position, Some(stacked_piece));
         Ok(())
@@ -419,6 +605,102 @@ impl Board {
         binary
     }
 
+    /// Zobrist hash of the position (pieces and side to move)
+    pub fn hash(&self) -> u64 {
+        crate::zobrist::hash_binary(&self.to_binary())
+    }
+
+    /// Text notation of the position: the rows from rank 9 down to rank 1 separated by
+    /// `/`, then the side to move (`w` or `b`). Within a row, digits count empty squares
+    /// and letters are pieces, uppercase for white and lowercase for black; a stack is
+    /// written `top+bottom`. The initial position is
+    /// `bdpgkgpdb/2c3j2/sssssssss/9/9/9/SSSSSSSSS/2J3C2/BDPGKGPDB w`.
+    pub fn to_notation(&self) -> String {
+        let mut notation = String::new();
+        for (y, row) in self.data.chunks(BOARD_DIMENSION).enumerate() {
+            if y > 0 {
+                notation.push('/');
+            }
+            let mut empty = 0;
+            for square in row {
+                let Some(piece) = square else {
+                    empty += 1;
+                    continue;
+                };
+                if empty > 0 {
+                    notation.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                let letter = |piece_type: PieceType| match piece.color {
+                    Color::White => piece_type.symbol(),
+                    Color::Black => piece_type.symbol().to_ascii_lowercase(),
+                };
+                if let Some(top) = piece.top {
+                    notation.push(letter(top));
+                    notation.push('+');
+                }
+                notation.push(letter(piece.bottom));
+            }
+            if empty > 0 {
+                notation.push_str(&empty.to_string());
+            }
+        }
+        notation.push_str(if self.white_to_move { " w" } else { " b" });
+        notation
+    }
+
+    /// Parse the text notation written by [`Board::to_notation`]
+    pub fn from_notation(notation: &str) -> Result<Self, String> {
+        let (rows, side) = notation
+            .trim()
+            .split_once(' ')
+            .ok_or("Missing side to move in board notation")?;
+        let white_to_move = match side.trim() {
+            "w" => true,
+            "b" => false,
+            other => return Err(format!("Invalid side to move '{}'", other)),
+        };
+
+        let mut data = [None; BOARD_SIZE];
+        let rows: Vec<&str> = rows.split('/').collect();
+        if rows.len() != BOARD_DIMENSION {
+            return Err(format!("Expected {} rows, found {}", BOARD_DIMENSION, rows.len()));
+        }
+        for (y, row) in rows.iter().enumerate() {
+            let mut x = 0;
+            let mut chars = row.chars().peekable();
+            while let Some(c) = chars.next() {
+                if let Some(empty) = c.to_digit(10) {
+                    x += empty as usize;
+                    continue;
+                }
+                let piece_type = |c: char| PieceType::from_symbol(c).ok_or(format!("Invalid piece '{}'", c));
+                let color = if c.is_ascii_uppercase() { Color::White } else { Color::Black };
+                let mut piece = Piece { color, bottom: piece_type(c)?, top: None };
+                if chars.next_if_eq(&'+').is_some() {
+                    let bottom = chars.next().ok_or("Missing bottom piece of a stack")?;
+                    if bottom.is_ascii_uppercase() != c.is_ascii_uppercase() {
+                        return Err(format!("Stack '{}+{}' mixes colors", c, bottom));
+                    }
+                    piece = Piece { color, bottom: piece_type(bottom)?, top: Some(piece.bottom) };
+                    if piece.bottom == PieceType::King || piece.top == Some(PieceType::King) {
+                        return Err("The King cannot be stacked".to_string());
+                    }
+                }
+                if x >= BOARD_DIMENSION {
+                    return Err(format!("Row {} is too long", BOARD_DIMENSION - y));
+                }
+                data[y * BOARD_DIMENSION + x] = Some(piece);
+                x += 1;
+            }
+            if x != BOARD_DIMENSION {
+                return Err(format!("Row {} has {} squares", BOARD_DIMENSION - y, x));
+            }
+        }
+
+        Ok(Board { data, white_to_move })
+    }
+
     pub fn from_binary(binary: [u8; BOARD_SIZE + 1]) -> Result<Self, String> {
         let mut data = [None; BOARD_SIZE];
 
@@ -436,3 +718,90 @@ impl Board {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn empty_board() -> Board {
+        Board {
+            data: [None; BOARD_SIZE],
+            white_to_move: true,
+        }
+    }
+
+    #[test]
+    fn test_initial_position_has_no_threats() {
+        let board = Board::new();
+        assert!(!board.king_in_danger(Color::White));
+        assert!(!board.king_in_danger(Color::Black));
+        assert!(board.hanging_pieces(Color::White).is_empty());
+        assert!(board.hanging_pieces(Color::Black).is_empty());
+        // White soldiers attack the whole row in front of them
+        let attacked = board.attacked_squares(Color::White);
+        assert!((0..BOARD_DIMENSION).all(|x| attacked.contains(&Position::new(x, 5))));
+    }
+
+    #[test]
+    fn test_stack_attacks_with_both_pieces() {
+        let mut board = empty_board();
+        let stack = Position::new(4, 4);
+        // Guard on top of a Paladin: diagonal and orthogonal steps of up to 2
+        board.set_piece(&stack, Some(Piece::new(Color::White, PieceType::Paladin, Some(PieceType::Guard))));
+        let attacks = board.attacks_from(&stack);
+        assert_eq!(attacks.len(), 16);
+        assert!(attacks.contains(&Position::new(6, 6)));
+        assert!(attacks.contains(&Position::new(4, 2)));
+    }
+
+    #[test]
+    fn test_sliding_attacks_are_blocked() {
+        let mut board = empty_board();
+        board.set_piece(&Position::new(0, 4), Some(Piece::new(Color::White, PieceType::Commander, None)));
+        board.set_piece(&Position::new(3, 4), Some(Piece::new(Color::Black, PieceType::Soldier, None)));
+        let attacks = board.attacks_from(&Position::new(0, 4));
+        assert!(attacks.contains(&Position::new(3, 4)));
+        assert!(!attacks.contains(&Position::new(4, 4)));
+    }
+
+    #[test]
+    fn test_king_in_danger_and_hanging_pieces() {
+        let mut board = empty_board();
+        board.set_piece(&Position::new(4, 0), Some(Piece::new(Color::Black, PieceType::King, None)));
+        board.set_piece(&Position::new(4, 8), Some(Piece::new(Color::White, PieceType::King, None)));
+        // Dragon jumps to the black king
+        board.set_piece(&Position::new(3, 2), Some(Piece::new(Color::White, PieceType::Dragon, None)));
+        assert!(board.king_in_danger(Color::Black));
+        assert!(!board.king_in_danger(Color::White));
+        assert_eq!(board.attackers_of(&Position::new(4, 0)), vec![Position::new(3, 2)]);
+
+        // The dragon is attacked by the king's neighbour guard and left undefended
+        board.set_piece(&Position::new(5, 0), Some(Piece::new(Color::Black, PieceType::Guard, None)));
+        assert_eq!(board.hanging_pieces(Color::White), vec![Position::new(3, 2)]);
+
+        // Defending it with a soldier removes it from the hanging list
+        board.set_piece(&Position::new(2, 3), Some(Piece::new(Color::White, PieceType::Soldier, None)));
+        assert!(board.hanging_pieces(Color::White).is_empty());
+    }
+
+    #[test]
+    fn test_notation_round_trip() {
+        let board = Board::new();
+        let notation = board.to_notation();
+        assert_eq!(notation, "bdpgkgpdb/2c3j2/sssssssss/9/9/9/SSSSSSSSS/2J3C2/BDPGKGPDB w");
+        assert_eq!(Board::from_notation(&notation), Ok(board));
+
+        let mut board = empty_board();
+        board.set_white_to_move(false);
+        board.set_piece(&"E5".parse().unwrap(), Some(Piece::new(Color::White, PieceType::Paladin, Some(PieceType::Guard))));
+        board.set_piece(&"I1".parse().unwrap(), Some(Piece::new(Color::Black, PieceType::King, None)));
+        assert_eq!(board.to_notation(), "9/9/9/9/4G+P4/9/9/9/8k b");
+        assert_eq!(Board::from_notation("9/9/9/9/4G+P4/9/9/9/8k b"), Ok(board));
+
+        assert!(Board::from_notation("9/9/9/9/9/9/9/9/9").is_err());
+        assert!(Board::from_notation("9/9/9/9/9/9/9/9/8 w").is_err());
+        assert!(Board::from_notation("9/9/9/9/4K+P4/9/9/9/9 w").is_err());
+        assert!(Board::from_notation("9/9/9/9/4g+P4/9/9/9/9 w").is_err());
+        assert!("J0".parse::<Position>().is_err());
+    }
+}
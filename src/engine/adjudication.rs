@@ -0,0 +1,199 @@
+//! Resignation and draw adjudication
+//!
+//! An [`Adjudicator`] follows the scores of one engine's searches during a game and
+//! ends hopeless or dead-drawn games early:
+//!
+//! - **Resignation**: the engine resigns once its score stayed below
+//!   `resign_threshold` for `resign_moves` consecutive moves.
+//! - **Draw offers**: once the engine has played `draw_min_moves` moves, it offers a
+//!   draw when its score stayed within `draw_margin` of zero for `draw_moves`
+//!   consecutive moves.
+//! - **Draw acceptance**: past `draw_min_moves`, the engine accepts a draw offer
+//!   unless its last score is better than `draw_margin`.
+//!
+//! Scores are from the engine's perspective, in evaluation units. Moves without a
+//! search score (book moves, forced moves) are not counted. Adjudication is disabled
+//! by default; [`AdjudicationConfig::standard`] enables both rules.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::adjudication::{AdjudicationConfig, Adjudicator, Verdict};
+//! use arx_engine::engine::MctsEngine;
+//!
+//! let mut engine = MctsEngine::new().expect("Failed to create engine");
+//! let mut adjudicator = Adjudicator::new(AdjudicationConfig::standard());
+//! let board = arx_engine::Game::new().to_binary();
+//! let (mv, score) = engine.find_best_move_scored(&board).expect("No legal moves");
+//! match score.map_or(Verdict::Continue, |score| adjudicator.record(score)) {
+//!     Verdict::Resign => println!("The engine resigns"),
+//!     Verdict::OfferDraw => println!("The engine plays {} and offers a draw", mv),
+//!     Verdict::Continue => println!("The engine plays {}", mv),
+//! }
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// Resignation and draw rules of one game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AdjudicationConfig {
+    /// Score below which a move counts towards resigning (None never resigns)
+    pub resign_threshold: Option<i32>,
+    /// Consecutive moves below the threshold before resigning
+    pub resign_moves: u32,
+    /// Largest absolute score counted as drawish (None never offers nor accepts draws)
+    pub draw_margin: Option<i32>,
+    /// Consecutive drawish moves before offering a draw
+    pub draw_moves: u32,
+    /// Moves played by the engine before it offers or accepts draws
+    pub draw_min_moves: u32,
+}
+
+impl Default for AdjudicationConfig {
+    fn default() -> Self {
+        Self {
+            resign_threshold: None,
+            resign_moves: 5,
+            draw_margin: None,
+            draw_moves: 10,
+            draw_min_moves: 40,
+        }
+    }
+}
+
+impl AdjudicationConfig {
+    /// Resign after 5 moves below -900 (nearly two Commanders down), offer draws after
+    /// 10 moves within 25 of zero once 40 moves were played
+    pub fn standard() -> Self {
+        Self {
+            resign_threshold: Some(-900),
+            draw_margin: Some(25),
+            ..Self::default()
+        }
+    }
+}
+
+/// Decision taken after a move's score
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Play the move
+    Continue,
+    /// Give up the game instead of playing the move
+    Resign,
+    /// Play the move and offer a draw
+    OfferDraw,
+}
+
+/// Score history of one engine in one game
+#[derive(Clone, Debug)]
+pub struct Adjudicator {
+    config: AdjudicationConfig,
+    moves: u32,
+    losing_streak: u32,
+    drawish_streak: u32,
+    last_score: Option<i32>,
+}
+
+impl Adjudicator {
+    pub fn new(config: AdjudicationConfig) -> Self {
+        Self {
+            config,
+            moves: 0,
+            losing_streak: 0,
+            drawish_streak: 0,
+            last_score: None,
+        }
+    }
+
+    pub fn config(&self) -> &AdjudicationConfig {
+        &self.config
+    }
+
+    /// Forget the game's scores, keeping the rules
+    pub fn reset(&mut self) {
+        *self = Self::new(self.config);
+    }
+
+    /// Count the search score of the engine's next move and decide what to do with it
+    pub fn record(&mut self, score: i32) -> Verdict {
+        self.moves += 1;
+        self.last_score = Some(score);
+        self.losing_streak = match self.config.resign_threshold {
+            Some(threshold) if score < threshold => self.losing_streak + 1,
+            _ => 0,
+        };
+        self.drawish_streak = match self.config.draw_margin {
+            Some(margin) if score.abs() <= margin => self.drawish_streak + 1,
+            _ => 0,
+        };
+
+        if self.config.resign_threshold.is_some() && self.losing_streak >= self.config.resign_moves.max(1) {
+            Verdict::Resign
+        } else if self.config.draw_margin.is_some()
+            && self.moves >= self.config.draw_min_moves
+            && self.drawish_streak >= self.config.draw_moves.max(1)
+        {
+            Verdict::OfferDraw
+        } else {
+            Verdict::Continue
+        }
+    }
+
+    /// Whether the engine accepts the opponent's draw offer
+    pub fn accepts_draw(&self) -> bool {
+        let Some(margin) = self.config.draw_margin else {
+            return false;
+        };
+        self.moves >= self.config.draw_min_moves && self.last_score.is_some_and(|score| score <= margin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resigns_after_consecutive_losing_moves() {
+        let config = AdjudicationConfig { resign_threshold: Some(-500), resign_moves: 3, ..AdjudicationConfig::default() };
+        let mut adjudicator = Adjudicator::new(config);
+        assert_eq!(adjudicator.record(-600), Verdict::Continue);
+        assert_eq!(adjudicator.record(-600), Verdict::Continue);
+        assert_eq!(adjudicator.record(-100), Verdict::Continue); // The streak is broken
+        assert_eq!(adjudicator.record(-600), Verdict::Continue);
+        assert_eq!(adjudicator.record(-700), Verdict::Continue);
+        assert_eq!(adjudicator.record(-800), Verdict::Resign);
+        assert!(!adjudicator.accepts_draw()); // Draws are disabled
+
+        // Disabled rules never trigger
+        let mut adjudicator = Adjudicator::new(AdjudicationConfig::default());
+        assert!((0..100).all(|_| adjudicator.record(-100_000) == Verdict::Continue));
+    }
+
+    #[test]
+    fn test_draw_offers_and_acceptance() {
+        let config = AdjudicationConfig {
+            draw_margin: Some(20),
+            draw_moves: 2,
+            draw_min_moves: 4,
+            ..AdjudicationConfig::default()
+        };
+        let mut adjudicator = Adjudicator::new(config);
+        assert_eq!(adjudicator.record(0), Verdict::Continue);
+        assert_eq!(adjudicator.record(10), Verdict::Continue);
+        assert!(!adjudicator.accepts_draw()); // Too early
+        assert_eq!(adjudicator.record(-15), Verdict::Continue);
+        assert_eq!(adjudicator.record(5), Verdict::OfferDraw);
+        assert!(adjudicator.accepts_draw());
+
+        // Winning: no offer, and offers are declined
+        assert_eq!(adjudicator.record(300), Verdict::Continue);
+        assert!(!adjudicator.accepts_draw());
+        // Losing: offers are welcome
+        assert_eq!(adjudicator.record(-300), Verdict::Continue);
+        assert!(adjudicator.accepts_draw());
+
+        adjudicator.reset();
+        assert!(!adjudicator.accepts_draw());
+    }
+}
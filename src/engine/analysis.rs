@@ -0,0 +1,296 @@
+//! Batch position evaluation
+//!
+//! [`MctsEngine::evaluate_batch`] scores many positions at once, for game reviews and
+//! dataset labelling. Every position gets its static evaluation; with a simulation
+//! budget, each of its root moves is also simulated as in a root-parallel search.
+//!
+//! Instead of searching the positions one after the other, the root moves of all
+//! positions are flattened into a single work list. On the GPU, rollouts are packed
+//! into full batches whatever position they come from, and the batches are submitted
+//! from the rayon pool; on the CPU (no GPU, a network evaluator or quiescence), each
+//! root move is a rayon task. Positions covered by the tablebases get their exact
+//! value and best move without simulation.
+//!
+//! Batch evaluations neither probe the opening book nor read or update the search
+//! tree, so they do not disturb the games the engine is playing. Seeded engines give
+//! reproducible results.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{MctsEngine, SearchLimits};
+//!
+//! let engine = MctsEngine::new().expect("Failed to create engine");
+//! let boards = vec![arx_engine::Game::new().to_binary(); 100];
+//! let limits = SearchLimits { max_depth: 3, simulations_per_move: 50 };
+//! for evaluation in engine.evaluate_batch(&boards, limits) {
+//!     println!("{} {} {:?}", evaluation.static_eval, evaluation.score, evaluation.best_move);
+//! }
+//! ```
+
+use super::{cpu_move_gen, make_move, BatchSimulationEngine, MctsEngine, RootMoveStats};
+use crate::zobrist::{self, mix64};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::sync::atomic::Ordering;
+
+/// Search budget of each position of a batch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchLimits {
+    /// Plies played from each position, the root move included
+    pub max_depth: u32,
+    /// Simulations per root move (0 only computes static evaluations)
+    pub simulations_per_move: u32,
+}
+
+impl Default for SearchLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            simulations_per_move: 100,
+        }
+    }
+}
+
+impl SearchLimits {
+    /// Static evaluations only
+    pub fn static_only() -> Self {
+        Self {
+            max_depth: 1,
+            simulations_per_move: 0,
+        }
+    }
+}
+
+/// Scores of one position, from the perspective of its side to move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Static evaluation (the network when one is configured)
+    pub static_eval: i32,
+    /// Average score of the best move, the exact tablebase score, or the static
+    /// evaluation when the position was not searched or has no legal move
+    pub score: i32,
+    /// Move with the best average score (or from the tablebases), if any was searched
+    pub best_move: Option<u16>,
+}
+
+/// One root move of one position of the batch
+struct RootJob {
+    position: usize,
+    hash: u64,
+    mv: u16,
+}
+
+impl MctsEngine {
+    /// Static evaluation, search score and best move of every position, in order.
+    /// `limits` replace the configured depth and simulations; everything else
+    /// (evaluator, playouts, tablebases, seed) follows the engine configuration.
+    pub fn evaluate_batch(&self, boards: &[[u8; 82]], limits: SearchLimits) -> Vec<Evaluation> {
+        let mut engine = self.worker();
+        engine.config.max_depth = limits.max_depth.max(1);
+        engine.config.simulations_per_move = limits.simulations_per_move;
+        engine.evaluate_positions(boards)
+    }
+
+    fn evaluate_positions(&self, boards: &[[u8; 82]]) -> Vec<Evaluation> {
+        let king_value = self.config.eval_weights.king_value;
+        let mut evaluations: Vec<Evaluation> = boards
+            .par_iter()
+            .map(|board| {
+                let static_eval = self.evaluate_board(board);
+                match self.probe_tablebases(board) {
+                    Some((mv, value)) => Evaluation { static_eval, score: value.score(king_value), best_move: Some(mv) },
+                    None => Evaluation { static_eval, score: static_eval, best_move: None },
+                }
+            })
+            .collect();
+        if self.config.simulations_per_move == 0 {
+            return evaluations;
+        }
+
+        // Root moves of every position left to search, in a stable order
+        let jobs: Vec<RootJob> = boards
+            .iter()
+            .enumerate()
+            .filter(|&(position, _)| evaluations[position].best_move.is_none())
+            .flat_map(|(position, board)| {
+                let hash = zobrist::hash_binary(board);
+                let mut moves = cpu_move_gen::generate_moves(board);
+                moves.sort_unstable();
+                moves.into_iter().map(move |mv| RootJob { position, hash, mv })
+            })
+            .collect();
+
+        let gpu_sim = self
+            .batch_sim
+            .as_ref()
+            .filter(|_| self.config.nnue.is_none() && self.config.quiescence.is_none());
+        let results = match gpu_sim {
+            Some(batch_sim) => self.simulate_jobs_gpu(boards, &jobs, batch_sim),
+            None => self.simulate_jobs_cpu(boards, &jobs),
+        };
+
+        // Best average score per position
+        let mut best: Vec<Option<RootMoveStats>> = vec![None; boards.len()];
+        for (job, stats) in jobs.iter().zip(results) {
+            let current = &mut best[job.position];
+            if stats.visits > 0 && current.is_none_or(|current| stats.mean_score() > current.mean_score()) {
+                *current = Some(stats);
+            }
+        }
+        for (evaluation, best) in evaluations.iter_mut().zip(best) {
+            if let Some(best) = best {
+                evaluation.score = best.mean_score().round() as i32;
+                evaluation.best_move = Some(best.mv);
+            }
+        }
+        evaluations
+    }
+
+    /// Random generator of a job; seeded engines derive it from the job alone
+    fn job_rng(&self, parts: &[u64]) -> StdRng {
+        match self.config.seed {
+            Some(seed) => StdRng::seed_from_u64(parts.iter().fold(!seed, |state, &part| mix64(state ^ part))),
+            None => StdRng::from_rng(rand::thread_rng()).expect("thread_rng never fails"),
+        }
+    }
+
+    /// CPU rollouts, one rayon task per root move
+    fn simulate_jobs_cpu(&self, boards: &[[u8; 82]], jobs: &[RootJob]) -> Vec<RootMoveStats> {
+        jobs.par_iter()
+            .map(|job| {
+                let mut stats = RootMoveStats { mv: job.mv, visits: 0, total_score: 0 };
+                let mut child = boards[job.position];
+                if make_move(&mut child, job.mv).is_err() {
+                    return stats;
+                }
+                let mut rng = self.job_rng(&[job.hash, job.mv as u64]);
+                let mut prefix = Vec::new();
+                for _ in 0..self.config.simulations_per_move {
+                    stats.total_score -= self.rollout(&child, 1, &mut rng, &mut prefix) as i64;
+                    stats.visits += 1;
+                }
+
+                let simulations = stats.visits as u64;
+                self.stats.simulations.fetch_add(simulations, Ordering::Relaxed);
+                self.stats.cpu_sims.fetch_add(simulations, Ordering::Relaxed);
+                self.stats.total_moves.fetch_add(simulations, Ordering::Relaxed);
+                stats
+            })
+            .collect()
+    }
+
+    /// GPU rollouts: simulation `i` belongs to job `i / simulations_per_move`, and
+    /// consecutive simulations fill `gpu_batch_size` batches across positions
+    fn simulate_jobs_gpu(
+        &self,
+        boards: &[[u8; 82]],
+        jobs: &[RootJob],
+        batch_sim: &BatchSimulationEngine,
+    ) -> Vec<RootMoveStats> {
+        let per_job = self.config.simulations_per_move as usize;
+        let total = jobs.len() * per_job;
+        let batch_size = self.config.gpu_batch_size.max(1);
+        let rollout_plies = self.config.max_depth.saturating_sub(1);
+
+        // (job, score from the root mover's perspective) of every valid simulation
+        let scores: Vec<(usize, i32)> = (0..total.div_ceil(batch_size))
+            .into_par_iter()
+            .flat_map_iter(|batch| {
+                let range = batch * batch_size..total.min((batch + 1) * batch_size);
+                let mut rng = self.job_rng(&[jobs.len() as u64, batch as u64]);
+                let job_of = |simulation: usize| &jobs[simulation / per_job];
+                let batch_boards: Vec<[u8; 82]> = range.clone().map(|i| boards[job_of(i).position]).collect();
+                let batch_moves: Vec<u16> = range.clone().map(|i| job_of(i).mv).collect();
+                let seeds: Vec<u32> = range.clone().map(|_| rng.gen()).collect();
+
+                let mut scores = Vec::with_capacity(range.len());
+                match batch_sim.process_rollouts(&batch_boards, &batch_moves, &seeds, rollout_plies) {
+                    Ok(results) => {
+                        self.stats.gpu_batches.fetch_add(1, Ordering::Relaxed);
+                        for (i, result) in range.zip(results) {
+                            if !result.valid {
+                                continue;
+                            }
+                            // GPU scores are for the side to move right after the root move
+                            let opponent = 1 - boards[job_of(i).position][81];
+                            let score = self
+                                .tablebase_score(&result.board)
+                                .map(|exact| if result.board[81] == opponent { exact } else { -exact })
+                                .unwrap_or(result.score);
+                            scores.push((i / per_job, -score));
+                        }
+                    }
+                    Err(_) => {
+                        // Fall back to CPU for this batch
+                        self.stats.cpu_sims.fetch_add(range.len() as u64, Ordering::Relaxed);
+                        let mut prefix = Vec::new();
+                        for i in range {
+                            let mut child = boards[job_of(i).position];
+                            if make_move(&mut child, job_of(i).mv).is_ok() {
+                                scores.push((i / per_job, -self.rollout(&child, 1, &mut rng, &mut prefix)));
+                            }
+                        }
+                    }
+                }
+                scores
+            })
+            .collect();
+
+        let mut results: Vec<RootMoveStats> =
+            jobs.iter().map(|job| RootMoveStats { mv: job.mv, visits: 0, total_score: 0 }).collect();
+        for (job, score) in scores {
+            results[job].visits += 1;
+            results[job].total_score += score as i64;
+        }
+        self.stats.simulations.fetch_add(total as u64, Ordering::Relaxed);
+        self.stats.total_moves.fetch_add(total as u64, Ordering::Relaxed);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::EngineConfig;
+
+    #[test]
+    fn test_evaluate_batch() {
+        let start = crate::Game::new().to_binary();
+        let mut capture = [0u8; 82];
+        capture[81] = 1;
+        capture[40] = 0x43; // White Commander
+        capture[4] = 0x03; // Black Commander in its line
+        capture[72] = 0x78;
+        capture[8] = 0x38;
+        let mut stuck = [0u8; 82];
+        stuck[81] = 1;
+        let boards = [start, capture, stuck];
+
+        for use_gpu_simulation in [false, true] {
+            let config = EngineConfig { use_gpu_simulation, seed: Some(5), ..EngineConfig::default() };
+            let engine = MctsEngine::with_config(config);
+            if let Err(e) = &engine {
+                println!("Skipping test: GPU not available - {}", e);
+                return;
+            }
+            let engine = engine.unwrap();
+
+            let statics = engine.evaluate_batch(&boards, SearchLimits::static_only());
+            assert_eq!(statics[0].static_eval, engine.evaluate_board(&start));
+            assert!(statics.iter().all(|evaluation| evaluation.best_move.is_none()));
+            assert_eq!(engine.get_statistics().simulations_run, 0);
+
+            let limits = SearchLimits { max_depth: 2, simulations_per_move: 4 };
+            let searched = engine.evaluate_batch(&boards, limits);
+            assert!(cpu_move_gen::generate_moves(&start).contains(&searched[0].best_move.unwrap()));
+            assert_eq!(searched[1].best_move, Some(40 | (4 << 7)));
+            assert!(searched[1].score > searched[1].static_eval);
+            assert_eq!(searched[2], statics[2]);
+
+            // Seeded batches are reproducible
+            assert_eq!(engine.evaluate_batch(&boards, limits), searched);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Opening book keyed by Zobrist hash
+//!
+//! The book maps positions (by [`zobrist::hash_binary`]) to the moves played from them,
+//! each with win/draw/loss counts from the perspective of the player who made the
+//! move. It is built from game records with [`BookBuilder`] and probed by
+//! [`MctsEngine::find_best_move`](super::MctsEngine::find_best_move) before searching.
+//!
+//! # File format (version 1)
+//!
+//! Little-endian binary: the magic bytes `ARXB`, a `u32` version and a `u32` row count,
+//! followed by one 22-byte row per (position, move) pair sorted by hash then move:
+//! `u64` position hash, `u16` move, `u32` wins, `u32` draws, `u32` losses.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::book::OpeningBook;
+//!
+//! let book = OpeningBook::load("openings.arxb").expect("Failed to load book");
+//! let board = arx_engine::Game::new().to_binary();
+//! if let Some(mv) = book.pick_move(&board, &mut rand::thread_rng()) {
+//!     println!("Book move: {}", mv);
+//! }
+//! ```
+
+use super::cpu_move_gen::generate_moves;
+use super::make_move;
+use super::selfplay::PositionRecord;
+use crate::zobrist;
+use rand::Rng;
+use std::collections::HashMap;
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"ARXB";
+const FORMAT_VERSION: u32 = 1;
+const ROW_SIZE: usize = 22;
+
+/// A book move with its results
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BookMove {
+    /// Encoded move
+    pub mv: u16,
+    /// Games won by the player who made the move
+    pub wins: u32,
+    /// Drawn games
+    pub draws: u32,
+    /// Games lost by the player who made the move
+    pub losses: u32,
+}
+
+impl BookMove {
+    /// Number of games in which the move was played
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Average result for the player who made the move (1 = always won)
+    pub fn score(&self) -> f64 {
+        if self.games() == 0 {
+            return 0.5;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.games() as f64
+    }
+
+    fn record(&mut self, result: f64) {
+        if result > 0.5 {
+            self.wins += 1;
+        } else if result < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    fn merge(&mut self, other: &BookMove) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+/// Most played moves first, ties broken by move encoding
+fn sort_moves(moves: &mut [BookMove]) {
+    moves.sort_by_key(|m| (std::cmp::Reverse(m.games()), m.mv));
+}
+
+/// Opening book: weighted moves per position hash
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OpeningBook {
+    entries: HashMap<u64, Vec<BookMove>>,
+}
+
+impl OpeningBook {
+    /// Number of positions in the book
+    pub fn positions(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the book holds no position
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Book moves of a position, most played first
+    pub fn moves(&self, board: &[u8; 82]) -> &[BookMove] {
+        self.entries
+            .get(&zobrist::hash_binary(board))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Pick a legal book move at random, weighted by the number of games it was played in
+    pub fn pick_move(&self, board: &[u8; 82], rng: &mut impl Rng) -> Option<u16> {
+        // Guard against hash collisions: only consider moves legal in this position
+        let legal = generate_moves(board);
+        let candidates: Vec<&BookMove> = self
+            .moves(board)
+            .iter()
+            .filter(|m| legal.contains(&m.mv))
+            .collect();
+
+        let total: u64 = candidates.iter().map(|m| m.games() as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for candidate in candidates {
+            if pick < candidate.games() as u64 {
+                return Some(candidate.mv);
+            }
+            pick -= candidate.games() as u64;
+        }
+        None
+    }
+
+    /// Add all the results of another book to this one
+    pub fn merge(&mut self, other: &OpeningBook) {
+        for (&hash, moves) in &other.entries {
+            let entry = self.entries.entry(hash).or_default();
+            for book_move in moves {
+                match entry.iter_mut().find(|m| m.mv == book_move.mv) {
+                    Some(existing) => existing.merge(book_move),
+                    None => entry.push(*book_move),
+                }
+            }
+            sort_moves(entry);
+        }
+    }
+
+    /// Drop moves played in fewer than `min_games` games (and positions left empty)
+    pub fn prune(&mut self, min_games: u32) {
+        self.entries.retain(|_, moves| {
+            moves.retain(|m| m.games() >= min_games);
+            !moves.is_empty()
+        });
+    }
+
+    /// Serialize the book (see the module documentation for the format)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut rows: Vec<(u64, &BookMove)> = self
+            .entries
+            .iter()
+            .flat_map(|(&hash, moves)| moves.iter().map(move |m| (hash, m)))
+            .collect();
+        rows.sort_by_key(|(hash, m)| (*hash, m.mv));
+
+        let mut bytes = Vec::with_capacity(12 + rows.len() * ROW_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(rows.len() as u32).to_le_bytes());
+        for (hash, m) in rows {
+            bytes.extend_from_slice(&hash.to_le_bytes());
+            bytes.extend_from_slice(&m.mv.to_le_bytes());
+            bytes.extend_from_slice(&m.wins.to_le_bytes());
+            bytes.extend_from_slice(&m.draws.to_le_bytes());
+            bytes.extend_from_slice(&m.losses.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a book, checking the magic bytes, version and size
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 12 || &bytes[0..4] != MAGIC {
+            return Err("Not an Arx opening book".to_string());
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported book version {} (expected {})",
+                version, FORMAT_VERSION
+            ));
+        }
+        let rows = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+        let data = &bytes[12..];
+        if data.len() != rows * ROW_SIZE {
+            return Err(format!(
+                "Invalid book size: expected {} rows, got {} bytes",
+                rows,
+                data.len()
+            ));
+        }
+
+        let mut book = Self::default();
+        for row in data.chunks_exact(ROW_SIZE) {
+            let u32_at = |offset: usize| u32::from_le_bytes(row[offset..offset + 4].try_into().unwrap());
+            let hash = u64::from_le_bytes(row[0..8].try_into().unwrap());
+            book.entries.entry(hash).or_default().push(BookMove {
+                mv: u16::from_le_bytes(row[8..10].try_into().unwrap()),
+                wins: u32_at(10),
+                draws: u32_at(14),
+                losses: u32_at(18),
+            });
+        }
+        book.entries.values_mut().for_each(|moves| sort_moves(moves));
+        Ok(book)
+    }
+
+    /// Load a book from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read book file {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Save the book to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|e| format!("Failed to write book file {}: {}", path.display(), e))
+    }
+}
+
+/// Accumulates game records into an [`OpeningBook`]
+#[derive(Clone, Debug)]
+pub struct BookBuilder {
+    max_ply: u32,
+    book: OpeningBook,
+}
+
+impl BookBuilder {
+    /// Create a builder recording the first `max_ply` plies of each game
+    pub fn new(max_ply: u32) -> Self {
+        Self {
+            max_ply,
+            book: OpeningBook::default(),
+        }
+    }
+
+    /// Record a move played from a position, with the game result from white's perspective
+    pub fn add_position(&mut self, board: &[u8; 82], mv: u16, white_result: f64) {
+        let result = if board[81] == 1 { white_result } else { 1.0 - white_result };
+        let moves = self.book.entries.entry(zobrist::hash_binary(board)).or_default();
+        match moves.iter_mut().find(|m| m.mv == mv) {
+            Some(existing) => existing.record(result),
+            None => {
+                let mut book_move = BookMove { mv, ..BookMove::default() };
+                book_move.record(result);
+                moves.push(book_move);
+            }
+        }
+    }
+
+    /// Record a game played from the initial position
+    pub fn add_game(&mut self, moves: &[u16], white_result: f64) -> Result<(), String> {
+        let mut board = crate::Game::new().to_binary();
+        for &mv in moves.iter().take(self.max_ply as usize) {
+            self.add_position(&board, mv, white_result);
+            make_move(&mut board, mv)?;
+        }
+        Ok(())
+    }
+
+    /// Record a self-play position if it lies within the book depth and its move was
+    /// searched rather than a random opening ply
+    pub fn add_record(&mut self, record: &PositionRecord) -> Result<(), String> {
+        if record.ply < self.max_ply && !record.random {
+            self.add_position(&record.decode_board()?, record.best_move, record.result);
+        }
+        Ok(())
+    }
+
+    /// Record every position of a self-play dataset file
+    pub fn add_dataset(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read dataset {}: {}", path.display(), e))?;
+        for (number, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let record: PositionRecord = serde_json::from_str(line)
+                .map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))?;
+            self.add_record(&record)?;
+        }
+        Ok(())
+    }
+
+    /// Finish building, sorting moves by popularity
+    pub fn build(mut self) -> OpeningBook {
+        self.book.entries.values_mut().for_each(|moves| sort_moves(moves));
+        self.book
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+
+    // White Soldier A3 -> B4, then black replies
+    const WHITE_FIRST: u16 = 54 | (46 << 7);
+    const WHITE_OTHER: u16 = 56 | (46 << 7);
+
+    fn reply(board_after: &[u8; 82]) -> u16 {
+        generate_moves(board_after)[0]
+    }
+
+    fn sample_book() -> OpeningBook {
+        let mut after = Game::new().to_binary();
+        make_move(&mut after, WHITE_FIRST).unwrap();
+
+        let mut builder = BookBuilder::new(2);
+        builder.add_game(&[WHITE_FIRST, reply(&after)], 1.0).unwrap();
+        builder.add_game(&[WHITE_FIRST, reply(&after)], 0.5).unwrap();
+        builder.add_game(&[WHITE_OTHER], 0.0).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn test_build_and_probe() {
+        let book = sample_book();
+        let board = Game::new().to_binary();
+        let moves = book.moves(&board);
+        assert_eq!(moves.len(), 2);
+        assert_eq!(moves[0], BookMove { mv: WHITE_FIRST, wins: 1, draws: 1, losses: 0 });
+        assert_eq!(moves[1].losses, 1);
+
+        // Black's reply is stored from black's point of view
+        let mut after = board;
+        make_move(&mut after, WHITE_FIRST).unwrap();
+        assert_eq!(book.moves(&after)[0].losses, 1);
+
+        let picked = book.pick_move(&board, &mut rand::thread_rng()).unwrap();
+        assert!(picked == WHITE_FIRST || picked == WHITE_OTHER);
+        assert_eq!(book.pick_move(&[0u8; 82], &mut rand::thread_rng()), None);
+    }
+
+    #[test]
+    fn test_file_round_trip_merge_and_prune() {
+        let book = sample_book();
+        assert_eq!(OpeningBook::from_bytes(&book.to_bytes()).unwrap(), book);
+        assert!(OpeningBook::from_bytes(b"ARXN\x01\x00\x00\x00\x00\x00\x00\x00").is_err());
+
+        let mut merged = book.clone();
+        merged.merge(&book);
+        let board = Game::new().to_binary();
+        assert_eq!(merged.moves(&board)[0].games(), 4);
+
+        // The rarely played alternative first move is dropped
+        merged.prune(3);
+        assert_eq!(merged.positions(), 2);
+        assert_eq!(merged.moves(&board).len(), 1);
+    }
+}
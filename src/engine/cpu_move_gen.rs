@@ -0,0 +1,171 @@
+//! CPU move generation on raw engine boards
+//!
+//! This mirrors `shaders/move_generation.wgsl` square for square so CPU-side code
+//! (evaluation features, tuning, exhaustive searches) produces exactly the same
+//! move encodings as the GPU without a round-trip to the device. Piece movement comes
+//! from [`PieceType::directions`] and [`PieceType::range`], whose directions are listed
+//! in the shader's order.
+
+use crate::board::{Color, Piece, PieceType};
+
+const BOARD_SIZE: usize = 81;
+const BOARD_DIM: isize = 9;
+const KING_PAYLOAD: u8 = 0x38;
+
+/// Generate all moves for the side to move (same 16-bit encoding as the GPU)
+pub fn generate_moves(board: &[u8; 82]) -> Vec<u16> {
+    let mut moves = Vec::with_capacity(64);
+    generate_moves_for(board, board[81] == 1, &mut moves);
+    moves
+}
+
+/// Append all moves available to one side, whoever is to move
+pub fn generate_moves_for(board: &[u8; 82], white: bool, moves: &mut Vec<u16>) {
+    for_each_move(board, white, |mv| moves.push(mv));
+}
+
+/// Count the moves available to one side without storing them
+pub fn count_moves(board: &[u8; 82], white: bool) -> u32 {
+    let mut count = 0;
+    for_each_move(board, white, |_| count += 1);
+    count
+}
+
+/// Call `visit` with every move available to one side
+pub fn for_each_move(board: &[u8; 82], white: bool, mut visit: impl FnMut(u16)) {
+    let color = white as u8;
+    for from in 0..BOARD_SIZE {
+        let piece = board[from];
+        if piece == 0 || (piece >> 6) != color {
+            continue;
+        }
+
+        let payload = piece & 0x3F;
+        if payload == KING_PAYLOAD {
+            // King can never be stacked: has_top=true forbids moving onto friendly pieces
+            piece_moves(board, from, color, 0, false, true, &mut visit);
+            continue;
+        }
+
+        let top_code = (payload >> 3) & 0x07;
+        let bottom_code = payload & 0x07;
+        if top_code != 0 {
+            piece_moves(board, from, color, top_code, true, true, &mut visit);
+        }
+        piece_moves(board, from, color, bottom_code, false, top_code != 0, &mut visit);
+    }
+}
+
+/// Directions and range of a piece code (0 is used for the king), as [`PieceType`] defines them
+pub(crate) fn movement(piece_code: u8, color: u8) -> (&'static [(isize, isize)], isize) {
+    let piece_type = Piece::code_to_piece_type(piece_code).unwrap_or(PieceType::King);
+    let color = if color == 1 { Color::White } else { Color::Black };
+    (piece_type.directions(color), piece_type.range())
+}
+
+fn piece_moves(
+    board: &[u8; 82],
+    from: usize,
+    color: u8,
+    piece_code: u8,
+    is_top: bool,
+    has_top: bool,
+    visit: &mut impl FnMut(u16),
+) {
+    let x = (from % 9) as isize;
+    let y = (from / 9) as isize;
+    let (directions, range) = movement(piece_code, color);
+
+    for &(dx, dy) in directions {
+        for dist in 1..=range {
+            let tx = x + dx * dist;
+            let ty = y + dy * dist;
+            if !(0..BOARD_DIM).contains(&tx) || !(0..BOARD_DIM).contains(&ty) {
+                break;
+            }
+            if !explore_target(board, from, (ty * BOARD_DIM + tx) as usize, color, is_top, has_top, visit) {
+                break;
+            }
+        }
+    }
+}
+
+/// Emit the move to `to` if allowed; returns true if sliding may continue past it
+fn explore_target(
+    board: &[u8; 82],
+    from: usize,
+    to: usize,
+    color: u8,
+    is_top: bool,
+    has_top: bool,
+    visit: &mut impl FnMut(u16),
+) -> bool {
+    let encode = |unstackable: bool, force_unstack: bool| {
+        ((force_unstack as u16) << 15) | ((unstackable as u16) << 14) | ((to as u16) << 7) | from as u16
+    };
+
+    let target = board[to];
+    // Empty square
+    if target == 0 {
+        visit(encode(is_top, false));
+        return true;
+    }
+
+    // Enemy piece - can capture
+    if (target >> 6) != color {
+        visit(encode(is_top, false));
+        return false;
+    }
+
+    // Friendly piece: the bottom of a stack cannot carry its top onto another piece
+    if !is_top && has_top {
+        return false;
+    }
+
+    // Can only stack onto a single, non-king piece
+    let payload = target & 0x3F;
+    if payload == KING_PAYLOAD || (payload >> 3) != 0 {
+        return false;
+    }
+
+    visit(encode(is_top, is_top));
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::make_move;
+    use crate::Game;
+
+    fn sorted(mut moves: Vec<u16>) -> Vec<u16> {
+        moves.sort_unstable();
+        moves
+    }
+
+    #[test]
+    fn test_matches_game_move_generation() {
+        let mut game = Game::new();
+        let mut board = game.to_binary();
+
+        // Walk a few plies of a deterministic game, comparing at each step
+        for ply in 0..12 {
+            let expected: Vec<u16> = game.get_all_moves().iter().map(|m| m.to_u16()).collect();
+            let moves = generate_moves(&board);
+            assert_eq!(sorted(moves.clone()), sorted(expected), "ply {}", ply);
+
+            let mv = moves[(ply * 7) % moves.len()];
+            make_move(&mut board, mv).unwrap();
+            game = Game::from_binary(board).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_count_moves_for_both_sides() {
+        let board = Game::new().to_binary();
+        let white = count_moves(&board, true);
+        let black = count_moves(&board, false);
+        assert_eq!(white, black);
+        assert_eq!(white as usize, generate_moves(&board).len());
+    }
+}
@@ -0,0 +1,317 @@
+//! Positional evaluation shared by the CPU and GPU paths
+//!
+//! All evaluation terms are weighted by an [`EvalWeights`] value. The same struct is
+//! used by [`evaluate`] on the CPU and uploaded as a uniform buffer to
+//! `shaders/batch_simulation.wgsl`, so both sides always score positions identically.
+//! Scores are expressed in hundredths of a Soldier.
+//!
+//! Evaluation terms (each computed per side, white minus black):
+//! - Material: value of every piece, both pieces of a stack counting
+//! - Mobility: number of available moves, whoever is to move
+//! - Stack bonus: per stack, since it combines two movement sets
+//! - Soldier advancement: per row a Soldier has advanced from its starting row
+//! - King safety: penalty per enemy piece within two squares of the king
+//! - Centre control: per piece or stack standing on the central 3x3 squares
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{evaluate, EvalWeights};
+//!
+//! let weights = EvalWeights::load("weights.json").unwrap_or_default();
+//! let board = arx_engine::Game::new().to_binary();
+//! println!("Score: {}", evaluate(&board, &weights));
+//! ```
+
+use super::cpu_move_gen::count_moves;
+use bytemuck::{Pod, Zeroable};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const BOARD_SIZE: usize = 81;
+const KING_PAYLOAD: u8 = 0x38;
+const SOLDIER_CODE: u8 = 1;
+
+/// Weights of the evaluation terms, in hundredths of a Soldier
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvalWeights {
+    /// Material value per piece code (index 0 unused, then Soldier to Ballista)
+    pub piece_values: [i32; 8],
+    /// Material value of the King
+    pub king_value: i32,
+    /// Bonus per available move
+    pub mobility: i32,
+    /// Bonus per stack
+    pub stack_bonus: i32,
+    /// Bonus per row advanced by each Soldier
+    pub soldier_advancement: i32,
+    /// Penalty per enemy piece within two squares of the King
+    pub king_attacker_penalty: i32,
+    /// Bonus per piece or stack on the central 3x3 squares
+    pub center_control: i32,
+}
+
+impl Default for EvalWeights {
+    fn default() -> Self {
+        Self {
+            piece_values: [
+                0,   // Index 0: unused
+                100, // Soldier
+                300, // Jester (like Bishop)
+                500, // Commander (like Rook)
+                300, // Paladin (like Bishop-lite)
+                300, // Guard (like Bishop-lite)
+                300, // Dragon (like Knight)
+                500, // Ballista (like Rook-lite)
+            ],
+            king_value: 100_000, // King is invaluable
+            mobility: 5,
+            stack_bonus: 30,
+            soldier_advancement: 10,
+            king_attacker_penalty: 25,
+            center_control: 15,
+        }
+    }
+}
+
+impl EvalWeights {
+    /// Weights counting material only (all positional terms disabled)
+    pub fn material_only() -> Self {
+        Self {
+            mobility: 0,
+            stack_bonus: 0,
+            soldier_advancement: 0,
+            king_attacker_penalty: 0,
+            center_control: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Load weights from a JSON file (missing fields keep their default value)
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read weights file {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| format!("Failed to parse weights file {}: {}", path.display(), e))
+    }
+
+    /// Save weights to a JSON file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize weights: {}", e))?;
+        std::fs::write(path, contents + "\n")
+            .map_err(|e| format!("Failed to write weights file {}: {}", path.display(), e))
+    }
+
+    /// Uniform buffer layout used by the batch simulation shader
+    pub(crate) fn to_gpu(self) -> GpuEvalWeights {
+        let mut piece_values = [[0; 4]; 2];
+        for (code, &value) in self.piece_values.iter().enumerate() {
+            piece_values[code / 4][code % 4] = value;
+        }
+        GpuEvalWeights {
+            piece_values,
+            king_value: self.king_value,
+            mobility: self.mobility,
+            stack_bonus: self.stack_bonus,
+            soldier_advancement: self.soldier_advancement,
+            king_attacker_penalty: self.king_attacker_penalty,
+            center_control: self.center_control,
+            _padding: [0; 2],
+        }
+    }
+}
+
+/// `EvalWeights` as laid out in the shader's uniform buffer (16-byte aligned arrays)
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct GpuEvalWeights {
+    piece_values: [[i32; 4]; 2],
+    king_value: i32,
+    mobility: i32,
+    stack_bonus: i32,
+    soldier_advancement: i32,
+    king_attacker_penalty: i32,
+    center_control: i32,
+    _padding: [i32; 2],
+}
+
+/// Raw evaluation terms of a position, each as white minus black
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EvalFeatures {
+    /// Piece count difference per piece code (stacks count both pieces)
+    pub piece_counts: [i32; 8],
+    /// King count difference
+    pub kings: i32,
+    /// Move count difference
+    pub mobility: i32,
+    /// Stack count difference
+    pub stacks: i32,
+    /// Soldier advancement difference (in rows)
+    pub soldier_advancement: i32,
+    /// Enemy pieces near the white King minus enemy pieces near the black King
+    pub king_attackers: i32,
+    /// Centre occupation difference
+    pub center: i32,
+}
+
+impl EvalFeatures {
+    /// Extract the evaluation terms of a board
+    pub fn extract(board: &[u8; 82]) -> Self {
+        let mut features = Self {
+            mobility: count_moves(board, true) as i32 - count_moves(board, false) as i32,
+            ..Self::default()
+        };
+        let mut kings: [Option<(i32, i32)>; 2] = [None, None];
+
+        for (square, &piece) in board.iter().take(BOARD_SIZE).enumerate() {
+            if piece == 0 {
+                continue;
+            }
+
+            let is_white = (piece >> 6) == 1;
+            let sign = if is_white { 1 } else { -1 };
+            let payload = piece & 0x3F;
+            let x = (square % 9) as i32;
+            let y = (square / 9) as i32;
+
+            if (3..=5).contains(&x) && (3..=5).contains(&y) {
+                features.center += sign;
+            }
+
+            // Check for King
+            if payload == KING_PAYLOAD {
+                features.kings += sign;
+                kings[is_white as usize] = Some((x, y));
+                continue;
+            }
+
+            let top_code = (payload >> 3) & 0x07;
+            let bottom_code = payload & 0x07;
+            if top_code != 0 {
+                features.stacks += sign;
+            }
+
+            // White soldiers start on row 6 and advance upwards, black ones on row 2
+            let advancement = if is_white { 6 - y } else { y - 2 }.max(0);
+            for code in [top_code, bottom_code] {
+                if code == 0 {
+                    continue;
+                }
+                features.piece_counts[code as usize] += sign;
+                if code == SOLDIER_CODE {
+                    features.soldier_advancement += sign * advancement;
+                }
+            }
+        }
+
+        // Count enemy pieces within two squares (Chebyshev distance) of each King
+        for (square, &piece) in board.iter().take(BOARD_SIZE).enumerate() {
+            if piece == 0 {
+                continue;
+            }
+            let is_white = (piece >> 6) == 1;
+            let x = (square % 9) as i32;
+            let y = (square / 9) as i32;
+            if let Some((kx, ky)) = kings[!is_white as usize] {
+                if (x - kx).abs() <= 2 && (y - ky).abs() <= 2 {
+                    // An enemy of the white King counts positively
+                    features.king_attackers += if is_white { -1 } else { 1 };
+                }
+            }
+        }
+
+        features
+    }
+
+    /// Weighted score of the features from white's perspective
+    pub fn score(&self, weights: &EvalWeights) -> i32 {
+        let material: i32 = self
+            .piece_counts
+            .iter()
+            .zip(weights.piece_values.iter())
+            .map(|(count, value)| count * value)
+            .sum();
+
+        material
+            + self.kings * weights.king_value
+            + self.mobility * weights.mobility
+            + self.stacks * weights.stack_bonus
+            + self.soldier_advancement * weights.soldier_advancement
+            - self.king_attackers * weights.king_attacker_penalty
+            + self.center * weights.center_control
+    }
+}
+
+/// Evaluate a board position
+/// Positive values favor the player to move
+pub fn evaluate(board: &[u8; 82], weights: &EvalWeights) -> i32 {
+    let score = EvalFeatures::extract(board).score(weights);
+    if board[81] == 1 {
+        score
+    } else {
+        -score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Game;
+
+    #[test]
+    fn test_initial_position_is_balanced() {
+        let board = Game::new().to_binary();
+        assert_eq!(evaluate(&board, &EvalWeights::default()), 0);
+        assert_eq!(EvalFeatures::extract(&board), EvalFeatures::default());
+    }
+
+    #[test]
+    fn test_material_counts_both_stack_pieces() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[0] = 0b1010100; // White Jester+Paladin
+        let weights = EvalWeights::material_only();
+        assert_eq!(evaluate(&board, &weights), 600);
+
+        board[81] = 0;
+        assert_eq!(evaluate(&board, &weights), -600);
+    }
+
+    #[test]
+    fn test_positional_terms() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[4] = 0b0111000; // Black King on E9
+        board[22] = 0b1000001; // White Soldier on E7, next to the King and advanced 4 rows
+
+        let features = EvalFeatures::extract(&board);
+        assert_eq!(features.soldier_advancement, 4);
+        assert_eq!(features.king_attackers, -1);
+        assert_eq!(features.kings, -1);
+        assert_eq!(features.mobility, 2 - 5);
+
+        board[40] = 0b1000100; // White Paladin in the centre
+        assert_eq!(EvalFeatures::extract(&board).center, 1);
+    }
+
+    #[test]
+    fn test_weights_round_trip_through_json() {
+        let path = std::env::temp_dir().join(format!("arx_weights_{}.json", std::process::id()));
+        let weights = EvalWeights {
+            mobility: 7,
+            ..EvalWeights::default()
+        };
+        weights.save(&path).unwrap();
+        assert_eq!(EvalWeights::load(&path).unwrap(), weights);
+        std::fs::remove_file(&path).ok();
+
+        // Missing fields fall back to defaults
+        let partial: EvalWeights = serde_json::from_str(r#"{"mobility": 9}"#).unwrap();
+        assert_eq!(partial.mobility, 9);
+        assert_eq!(partial.king_value, EvalWeights::default().king_value);
+    }
+}
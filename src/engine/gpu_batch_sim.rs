@@ -1,9 +1,13 @@
 //! GPU-accelerated batch simulation engine for MCTS
 //!
-//! This module provides GPU-based move application and board evaluation,
-//! allowing multiple simulations to be processed in parallel on the GPU.
+//! This module provides GPU-based move application, light or heavy rollouts and board
+//! evaluation, allowing multiple simulations to be processed in parallel on the GPU.
+//! Each invocation draws its rollout moves from its own seed, so a batch is fully
+//! reproducible from its inputs.
 
+use super::eval::EvalWeights;
 use super::gpu_context::GpuContext;
+use super::playout::{PlayoutWeights, Playouts};
 use bytemuck::{Pod, Zeroable};
 use std::borrow::Cow;
 use wgpu::util::DeviceExt;
@@ -31,7 +35,10 @@ pub struct GpuMoveApplication {
     pub move_encoding: u32,
     pub result_score: i32,
     pub valid: u32,
-    _padding: [u32; 3], // Padding for alignment
+    pub seed: u32,
+    pub rollout_plies: u32,
+    /// First rollout moves, `u32::MAX` when not played
+    pub first_moves: [u32; 2],
 }
 
 unsafe impl Pod for GpuMoveApplication {}
@@ -43,6 +50,8 @@ pub struct BatchSimulationResult {
     pub score: i32,
     pub valid: bool,
     pub board: [u8; 82],
+    /// First rollout moves (at most two), in the order played
+    pub first_moves: Vec<u16>,
 }
 
 /// GPU-accelerated batch simulation engine
@@ -50,6 +59,8 @@ pub struct BatchSimulationEngine {
     gpu_context: GpuContext,
     pipeline: wgpu::ComputePipeline,
     bind_group_layout: wgpu::BindGroupLayout,
+    weights_buffer: wgpu::Buffer,
+    playout_buffer: wgpu::Buffer,
 }
 
 impl BatchSimulationEngine {
@@ -68,16 +79,38 @@ impl BatchSimulationEngine {
         // Create bind group layout
         let bind_group_layout = gpu_context.device().create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("Batch Simulation Bind Group Layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::COMPUTE,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Storage { read_only: false },
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
                 },
-                count: None,
-            }],
+            ],
         });
 
         // Create pipeline layout
@@ -97,13 +130,47 @@ impl BatchSimulationEngine {
             cache: None,
         });
 
+        // Evaluation weights uniform, updated with set_eval_weights
+        let weights_buffer = gpu_context
+            .device()
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Evaluation Weights Buffer"),
+                contents: bytemuck::cast_slice(&[EvalWeights::default().to_gpu()]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
+        // Playout policy uniform, updated with set_playouts
+        let playout_buffer = gpu_context
+            .device()
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Playout Weights Buffer"),
+                contents: bytemuck::cast_slice(&[PlayoutWeights::default().to_gpu(Playouts::Light)]),
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            });
+
         Ok(Self {
             gpu_context,
             pipeline,
             bind_group_layout,
+            weights_buffer,
+            playout_buffer,
         })
     }
 
+    /// Upload the evaluation weights used by the shader
+    pub fn set_eval_weights(&self, weights: &EvalWeights) {
+        self.gpu_context
+            .queue()
+            .write_buffer(&self.weights_buffer, 0, bytemuck::cast_slice(&[weights.to_gpu()]));
+    }
+
+    /// Select light or heavy rollouts and upload the heavy playout weights
+    pub fn set_playouts(&self, playouts: Playouts, weights: &PlayoutWeights) {
+        self.gpu_context
+            .queue()
+            .write_buffer(&self.playout_buffer, 0, bytemuck::cast_slice(&[weights.to_gpu(playouts)]));
+    }
+
     /// Convert board binary to GPU format
     fn board_to_gpu(&self, board_binary: &[u8; 82]) -> GpuBoardState {
         let mut gpu_board = GpuBoardState {
@@ -112,8 +179,8 @@ impl BatchSimulationEngine {
             _padding: [0; 3],
         };
 
-        for i in 0..BOARD_SIZE {
-            gpu_board.squares[i] = board_binary[i] as u32;
+        for (square, &byte) in gpu_board.squares.iter_mut().zip(board_binary.iter()) {
+            *square = byte as u32;
         }
 
         gpu_board
@@ -122,8 +189,8 @@ impl BatchSimulationEngine {
     /// Convert GPU board back to binary format
     fn gpu_to_board(&self, gpu_board: &GpuBoardState) -> [u8; 82] {
         let mut board = [0u8; 82];
-        for i in 0..BOARD_SIZE {
-            board[i] = gpu_board.squares[i] as u8;
+        for (byte, &square) in board.iter_mut().zip(gpu_board.squares.iter()) {
+            *byte = square as u8;
         }
         board[81] = gpu_board.white_to_move as u8;
         board
@@ -135,8 +202,22 @@ impl BatchSimulationEngine {
         boards: &[[u8; 82]],
         moves: &[u16],
     ) -> Result<Vec<BatchSimulationResult>, String> {
-        if boards.len() != moves.len() {
-            return Err("boards and moves must have the same length".to_string());
+        self.process_rollouts(boards, moves, &vec![0; boards.len()], 0)
+    }
+
+    /// Apply each move, play `rollout_plies` random plies seeded by `seeds` (light or
+    /// heavy, see `set_playouts`) and evaluate
+    /// the final position. Scores are from the perspective of the player to move right
+    /// after the applied move; the returned board is the final one.
+    pub fn process_rollouts(
+        &self,
+        boards: &[[u8; 82]],
+        moves: &[u16],
+        seeds: &[u32],
+        rollout_plies: u32,
+    ) -> Result<Vec<BatchSimulationResult>, String> {
+        if boards.len() != moves.len() || boards.len() != seeds.len() {
+            return Err("boards, moves and seeds must have the same length".to_string());
         }
 
         if boards.is_empty() {
@@ -154,7 +235,9 @@ impl BatchSimulationEngine {
                 move_encoding: moves[i] as u32,
                 result_score: 0,
                 valid: 0,
-                _padding: [0; 3],
+                seed: seeds[i],
+                rollout_plies,
+                first_moves: [u32::MAX; 2],
             });
         }
 
@@ -181,10 +264,20 @@ impl BatchSimulationEngine {
         let bind_group = self.gpu_context.device().create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Batch Simulation Bind Group"),
             layout: &self.bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.weights_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: self.playout_buffer.as_entire_binding(),
+                },
+            ],
         });
 
         // Create command encoder
@@ -204,7 +297,7 @@ impl BatchSimulationEngine {
             compute_pass.set_bind_group(0, &bind_group, &[]);
             
             // Calculate workgroups needed (workgroup size is 64)
-            let workgroups = ((batch_size + 63) / 64) as u32;
+            let workgroups = batch_size.div_ceil(64) as u32;
             compute_pass.dispatch_workgroups(workgroups, 1, 1);
         }
 
@@ -242,6 +335,7 @@ impl BatchSimulationEngine {
                 score: app.result_score,
                 valid: app.valid != 0,
                 board: self.gpu_to_board(&app.board),
+                first_moves: app.first_moves.iter().take_while(|&&mv| mv != u32::MAX).map(|&mv| mv as u16).collect(),
             });
         }
 
@@ -296,4 +390,102 @@ mod tests {
         let result = engine.process_batch(&boards, &moves);
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_gpu_evaluation_matches_cpu() {
+        let engine = BatchSimulationEngine::new_sync();
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let engine = engine.unwrap();
+
+        let weights = EvalWeights {
+            mobility: 3,
+            king_attacker_penalty: 40,
+            ..EvalWeights::default()
+        };
+        engine.set_eval_weights(&weights);
+
+        // Play every first move of the initial position and compare evaluations
+        let board = crate::Game::new().to_binary();
+        let moves = super::super::cpu_move_gen::generate_moves(&board);
+        let boards = vec![board; moves.len()];
+        let results = engine.process_batch(&boards, &moves).unwrap();
+
+        for (mv, result) in moves.iter().zip(results) {
+            let mut expected_board = board;
+            super::super::make_move(&mut expected_board, *mv).unwrap();
+            assert!(result.valid, "move {:#06x}", mv);
+            assert_eq!(result.board, expected_board, "move {:#06x}", mv);
+            assert_eq!(result.score, super::super::evaluate(&expected_board, &weights), "move {:#06x}", mv);
+        }
+    }
+
+    /// CPU mirror of the shader's `next_random`
+    fn rollout_random(state: &mut u32) -> u32 {
+        let s = state.wrapping_mul(747796405).wrapping_add(2891336453);
+        *state = s;
+        let word = ((s >> ((s >> 28) + 4)) ^ s).wrapping_mul(277803737);
+        (word >> 22) ^ word
+    }
+
+    #[test]
+    fn test_rollouts_match_cpu() {
+        let engine = BatchSimulationEngine::new_sync();
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let engine = engine.unwrap();
+        let weights = EvalWeights::default();
+        engine.set_eval_weights(&weights);
+
+        let board = crate::Game::new().to_binary();
+        let moves = super::super::cpu_move_gen::generate_moves(&board);
+        let boards = vec![board; moves.len()];
+        let seeds: Vec<u32> = (0..moves.len() as u32).map(|i| i * 7919 + 1).collect();
+        let plies = 10;
+        let playout_weights = PlayoutWeights::default();
+
+        for playouts in [Playouts::Light, Playouts::Heavy] {
+            engine.set_playouts(playouts, &playout_weights);
+            let results = engine.process_rollouts(&boards, &moves, &seeds, plies).unwrap();
+            assert_eq!(results.len(), moves.len());
+
+            for ((&mv, &seed), result) in moves.iter().zip(&seeds).zip(&results) {
+                // Replay the rollout on the CPU: the shader picks moves in generation order
+                let mut expected = board;
+                super::super::make_move(&mut expected, mv).unwrap();
+                let mut state = seed;
+                let mut sign = 1;
+                let mut played = Vec::new();
+                for _ in 0..plies {
+                    let replies = super::super::cpu_move_gen::generate_moves(&expected);
+                    let pick = match playouts {
+                        Playouts::Light if !replies.is_empty() => {
+                            Some(replies[(rollout_random(&mut state) % replies.len() as u32) as usize])
+                        }
+                        Playouts::Light => None,
+                        Playouts::Heavy => playout_weights
+                            .choose(&expected, &replies, &weights, |bound| rollout_random(&mut state) % bound),
+                    };
+                    let Some(pick) = pick else {
+                        break;
+                    };
+                    super::super::make_move(&mut expected, pick).unwrap();
+                    played.push(pick);
+                    sign = -sign;
+                }
+                assert!(result.valid);
+                assert_eq!(result.first_moves, played[..played.len().min(2)]);
+                assert_eq!(result.board, expected, "{:?} move {:#06x}", playouts, mv);
+                assert_eq!(result.score, sign * super::super::evaluate(&expected, &weights));
+            }
+
+            // Same seeds, same rollouts
+            let again = engine.process_rollouts(&boards, &moves, &seeds, plies).unwrap();
+            assert!(again.iter().zip(&results).all(|(a, b)| a.board == b.board && a.score == b.score));
+        }
+    }
 }
@@ -129,8 +129,8 @@ impl MoveGenerationEngine {
             _padding: [0; 3],
         };
 
-        for i in 0..BOARD_SIZE {
-            gpu_board.squares[i] = board_binary[i] as u32;
+        for (square, &byte) in gpu_board.squares.iter_mut().zip(board_binary.iter()) {
+            *square = byte as u32;
         }
 
         // Create buffers
@@ -290,6 +290,6 @@ mod tests {
         let moves = result.unwrap();
         // A white soldier at position 72 can move forward diagonally
         // This test just checks that we get some moves
-        assert!(moves.len() > 0, "Expected at least one move for a soldier");
+        assert!(!moves.is_empty(), "Expected at least one move for a soldier");
     }
 }
@@ -11,8 +11,20 @@
 //! - GPU-accelerated batch simulation for move application and evaluation
 //! - Multi-threaded CPU processing with Rayon
 //! - Configurable search depth and simulation count
-//! - Piece value-based position evaluation
-//! - Adjustable engine strength
+//! - Positional evaluation with configurable weights shared by CPU and GPU
+//! - Optional neural network evaluator with incremental updates (CPU)
+//! - Opening book probed before searching
+//! - Endgame tablebases probed at the root and at rollout leaves
+//! - Forced King-capture solver and puzzle finder
+//! - Search statistics reused between moves, with optional pondering
+//! - Difficulty levels with human-like move selection
+//! - Root-parallel or shared-tree parallel search with virtual loss
+//! - Light (uniform) or heavy (capture and King-aware) playouts on CPU and GPU
+//! - Move priors with PUCT selection and progressive widening
+//! - Capture-only quiescence search, optionally scoring rollout leaves
+//! - Batch evaluation of many positions, packing their rollouts into shared GPU batches
+//! - Resignation and draw adjudication from the search scores
+//! - Open-ended analysis reporting its progress after every round
 //! - Statistics tracking (moves evaluated, simulations run)
 //!
 //! # Example
@@ -27,6 +39,7 @@
 //!     exploration_constant: 1.414,
 //!     gpu_batch_size: 256,
 //!     use_gpu_simulation: true,
+//!     ..EngineConfig::default()
 //! };
 //! let mut engine = MctsEngine::with_config(config).expect("Failed to create engine");
 //!
@@ -40,10 +53,14 @@
 //! println!("Simulations run: {}", stats.simulations_run);
 //! ```
 
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
-use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use crate::zobrist;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 mod gpu_context;
 pub use gpu_context::{GpuContext, get_shared_context};
@@ -54,21 +71,64 @@ pub use gpu_move_gen::MoveGenerationEngine;
 mod gpu_batch_sim;
 pub use gpu_batch_sim::BatchSimulationEngine;
 
-const BOARD_SIZE: usize = 81;
+mod move_ops;
+pub use move_ops::{make_move, unmake_move, MoveUndo};
 
-/// Piece values for evaluation (based on chess piece values, scaled with Soldier=1)
-const PIECE_VALUES: [i32; 8] = [
-    0,  // Index 0: unused
-    1,  // Soldier
-    3,  // Jester (like Bishop)
-    5,  // Commander (like Rook)
-    3,  // Paladin (like Bishop-lite)
-    3,  // Guard (like Bishop-lite)
-    3,  // Dragon (like Knight)
-    5,  // Ballista (like Rook-lite)
-];
+pub mod cpu_move_gen;
 
-const KING_VALUE: i32 = 1000; // King is invaluable
+mod eval;
+pub use eval::{evaluate, EvalFeatures, EvalWeights};
+
+pub mod tuning;
+
+pub mod selfplay;
+
+pub mod nnue;
+use nnue::NnueNetwork;
+
+pub mod book;
+use book::OpeningBook;
+
+pub mod tablebase;
+use tablebase::{TbValue, Tablebases};
+
+pub mod solver;
+
+pub mod tree;
+use tree::SearchTree;
+
+pub mod strength;
+use strength::MoveSelection;
+
+pub mod parallel;
+use parallel::{Parallelism, SharedTree};
+
+pub mod playout;
+use playout::{PlayoutWeights, Playouts};
+
+pub mod priors;
+use priors::{Priors, Widening};
+
+pub mod quiescence;
+use quiescence::{Quiescence, QuiescenceConfig};
+
+pub mod analysis;
+pub use analysis::{Evaluation, SearchLimits};
+
+pub mod adjudication;
+
+/// Rollout plies recorded in the search tree below each root move
+const TREE_RECORD_PLIES: usize = 2;
+
+/// Waves a guided root-parallel search splits its budget into
+const ROOT_WAVES: u32 = 8;
+
+/// Longest principal variation reported by an analysis
+const MAX_PV_LENGTH: usize = 8;
+
+/// Simulations each root move runs per round of an even CPU search (GPU rounds are
+/// one batch), so that a stopped search sampled every move alike
+const CPU_ROUND_SIMULATIONS: u32 = 64;
 
 /// Engine configuration
 #[derive(Clone, Debug)]
@@ -83,6 +143,44 @@ pub struct EngineConfig {
     pub gpu_batch_size: usize,
     /// Enable GPU-accelerated batch simulation (if false, uses CPU fallback)
     pub use_gpu_simulation: bool,
+    /// Evaluation weights, shared by the CPU and GPU evaluation
+    pub eval_weights: EvalWeights,
+    /// Neural network evaluator used instead of `eval_weights` when set.
+    /// Network evaluation runs on the CPU, so GPU batch simulation is bypassed.
+    pub nnue: Option<Arc<NnueNetwork>>,
+    /// Opening book consulted by `find_best_move` before searching
+    pub book: Option<Arc<OpeningBook>>,
+    /// Endgame tablebases probed at the root and at the end of every rollout
+    pub tablebases: Option<Arc<Tablebases>>,
+    /// Keep move statistics between searches (see [`tree`]); required for pondering
+    pub reuse_tree: bool,
+    /// Most positions kept in the search tree
+    pub tree_capacity: usize,
+    /// Seed of the rollouts; the same seed, configuration and position (on a fresh
+    /// tree) always give the same move and statistics. Unseeded searches vary.
+    pub seed: Option<u64>,
+    /// How `find_best_move` picks among the searched moves (the best one by default);
+    /// weaker [`strength::Strength`] levels use it to play human-like mistakes
+    pub move_selection: MoveSelection,
+    /// Whether threads split the root moves or share one tree (see [`parallel`])
+    pub parallelism: Parallelism,
+    /// Worker threads of tree-parallel searches (0 uses one per CPU)
+    pub search_threads: usize,
+    /// Score counted for each simulation still in flight below a tree-parallel edge
+    pub virtual_loss: i32,
+    /// Uniformly random (light) or policy-driven (heavy) rollouts, see [`playout`]
+    pub playouts: Playouts,
+    /// Move weights of heavy playouts
+    pub playout_weights: PlayoutWeights,
+    /// Source of the move priors used by PUCT selection, see [`priors`]
+    pub priors: Priors,
+    /// Softmax temperature of `Priors::Evaluator`, in evaluation units
+    pub prior_temperature: f32,
+    /// Progressive widening of the moves considered at each node
+    pub widening: Option<Widening>,
+    /// Resolve pending captures before scoring rollout leaves (see [`quiescence`]).
+    /// The quiescence search runs on the CPU, so GPU batch simulation is bypassed.
+    pub quiescence: Option<QuiescenceConfig>,
 }
 
 impl Default for EngineConfig {
@@ -93,6 +191,23 @@ impl Default for EngineConfig {
             exploration_constant: 1.414,
             gpu_batch_size: 256,
             use_gpu_simulation: true,
+            eval_weights: EvalWeights::default(),
+            nnue: None,
+            book: None,
+            tablebases: None,
+            reuse_tree: true,
+            tree_capacity: 50_000,
+            seed: None,
+            move_selection: MoveSelection::default(),
+            parallelism: Parallelism::Root,
+            search_threads: 0,
+            virtual_loss: 1000,
+            playouts: Playouts::Light,
+            playout_weights: PlayoutWeights::default(),
+            priors: Priors::Uniform,
+            prior_temperature: 100.0,
+            widening: None,
+            quiescence: None,
         }
     }
 }
@@ -132,12 +247,81 @@ impl SearchStatistics {
     }
 }
 
+/// Search statistics of a single root move
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RootMoveStats {
+    /// Encoded move
+    pub mv: u16,
+    /// Number of simulations run for the move
+    pub visits: u32,
+    /// Sum of the simulation scores, from the perspective of the player to move
+    pub total_score: i64,
+}
+
+impl RootMoveStats {
+    /// Average simulation score (0 if the move was never simulated)
+    pub fn mean_score(&self) -> f64 {
+        if self.visits == 0 {
+            0.0
+        } else {
+            self.total_score as f64 / self.visits as f64
+        }
+    }
+}
+
+/// Outcome of a search
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// Move with the best average score (the most visited one when the search is
+    /// guided by priors or widening)
+    pub best_move: u16,
+    /// Average score of the best move, from the perspective of the player to move
+    pub score: i32,
+    /// Statistics of every legal root move
+    pub root_moves: Vec<RootMoveStats>,
+}
+
+/// Move played by [`MctsEngine::find_best_move_until`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MoveChoice {
+    pub mv: u16,
+    /// Score of the position for the side to move (None for book and forced moves)
+    pub score: Option<i32>,
+    /// Statistics of every root move (empty when answered by the book or the tablebases)
+    pub root_moves: Vec<RootMoveStats>,
+}
+
+/// Progress of [`MctsEngine::analyze`], reported after every round
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchInfo {
+    /// Rounds completed
+    pub depth: u32,
+    /// Simulations run by the analysis so far
+    pub nodes: u64,
+    pub best_move: u16,
+    /// Average score of the best move, from the perspective of the player to move
+    pub score: i32,
+    /// Best move followed by the most visited replies known to the search tree
+    pub pv: Vec<u16>,
+    pub elapsed: Duration,
+}
+
 /// Monte Carlo Tree Search Engine
 pub struct MctsEngine {
     config: EngineConfig,
-    move_gen: MoveGenerationEngine,
-    batch_sim: Option<BatchSimulationEngine>,
+    move_gen: Arc<MoveGenerationEngine>,
+    batch_sim: Option<Arc<BatchSimulationEngine>>,
     stats: Arc<AtomicStats>,
+    tree: Arc<Mutex<SearchTree>>,
+    ponder: Option<PonderJob>,
+}
+
+/// Background search of the position expected after the opponent's reply
+struct PonderJob {
+    board: [u8; 82],
+    reply: u16,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
 }
 
 /// Atomic statistics for thread-safe updates
@@ -191,7 +375,9 @@ impl MctsEngine {
             match BatchSimulationEngine::new_sync() {
                 Ok(engine) => {
                     eprintln!("✓ GPU batch simulation engine initialized");
-                    Some(engine)
+                    engine.set_eval_weights(&config.eval_weights);
+                    engine.set_playouts(config.playouts, &config.playout_weights);
+                    Some(Arc::new(engine))
                 }
                 Err(e) => {
                     eprintln!("⚠ GPU batch simulation unavailable: {}", e);
@@ -203,217 +389,621 @@ impl MctsEngine {
             None
         };
         
+        let tree = Arc::new(Mutex::new(SearchTree::new(config.tree_capacity)));
         Ok(Self {
             config,
-            move_gen,
+            move_gen: Arc::new(move_gen),
             batch_sim,
             stats: Arc::new(AtomicStats::new()),
+            tree,
+            ponder: None,
         })
     }
 
+    /// Engine sharing this one's GPU resources, statistics and tree, for background work
+    fn worker(&self) -> MctsEngine {
+        MctsEngine {
+            config: self.config.clone(),
+            move_gen: Arc::clone(&self.move_gen),
+            batch_sim: self.batch_sim.clone(),
+            stats: Arc::clone(&self.stats),
+            tree: Arc::clone(&self.tree),
+            ponder: None,
+        }
+    }
+
     /// Evaluate a board position and return the value
     /// Positive values favor the current player
     fn evaluate_board(&self, board: &[u8; 82]) -> i32 {
-        let white_to_move = board[81] == 1;
-        let mut white_value = 0;
-        let mut black_value = 0;
-
-        for i in 0..BOARD_SIZE {
-            let piece = board[i];
-            if piece == 0 {
-                continue;
+        match &self.config.nnue {
+            Some(network) => network.evaluate(board),
+            None => evaluate(board, &self.config.eval_weights),
+        }
+    }
+
+    /// Score of a rollout leaf: the static evaluation, or the quiescence search over
+    /// pending captures when configured
+    fn leaf_score(&self, board: &[u8; 82]) -> i32 {
+        match self.config.quiescence {
+            Some(config) => {
+                let evaluate = |board: &[u8; 82]| self.evaluate_board(board);
+                Quiescence::new(config, &self.config.eval_weights, evaluate).evaluate(board)
             }
+            None => self.evaluate_board(board),
+        }
+    }
+
+    /// Exact score of a position from the tablebases, from the side to move's perspective
+    fn tablebase_score(&self, board: &[u8; 82]) -> Option<i32> {
+        let value = self.config.tablebases.as_ref()?.probe(board)?;
+        Some(value.score(self.config.eval_weights.king_value))
+    }
 
-            let is_white = (piece >> 6) == 1;
-            let payload = piece & 0x3F;
+    /// Apply a move to a copy of a board state
+    /// This is a GPU-independent implementation for the engine
+    fn apply_move_simple(&self, board: &[u8; 82], move_encoding: u16) -> Result<[u8; 82], String> {
+        let mut new_board = *board;
+        make_move(&mut new_board, move_encoding)?;
+        Ok(new_board)
+    }
 
-            // Check for King
-            if payload == 0x38 {
-                if is_white {
-                    white_value += KING_VALUE;
-                } else {
-                    black_value += KING_VALUE;
-                }
-                continue;
-            }
+    /// Random generator for the simulations of one root move. Seeded searches derive it
+    /// from the position, the move and the number of simulations the move already has
+    /// (`done` of them in the current search), so results do not depend on how rayon
+    /// schedules the root moves and repeated searches of a position keep drawing new
+    /// rollouts.
+    fn move_rng(&self, hash: u64, mv: u16, done: u32) -> StdRng {
+        let Some(seed) = self.config.seed else {
+            return StdRng::from_rng(rand::thread_rng()).expect("thread_rng never fails");
+        };
+        let prior_visits = if self.config.reuse_tree {
+            let tree = self.tree.lock().unwrap();
+            tree.moves(hash)
+                .and_then(|known| known.iter().find(|stats| stats.mv == mv))
+                .map_or(0, |stats| stats.visits)
+        } else {
+            0
+        };
+        let mixed = [hash, mv as u64, (prior_visits + done) as u64]
+            .iter()
+            .fold(seed, |state, &part| zobrist::mix64(state ^ part));
+        StdRng::seed_from_u64(mixed)
+    }
 
-            let top_code = (payload >> 3) & 0x07;
-            let bottom_code = payload & 0x07;
+    /// Whether priors or widening steer the simulations instead of an even split
+    fn guided(&self) -> bool {
+        self.config.priors != Priors::Uniform || self.config.widening.is_some()
+    }
 
-            // Add value for bottom piece
-            if bottom_code > 0 && (bottom_code as usize) < PIECE_VALUES.len() {
-                let value = PIECE_VALUES[bottom_code as usize];
-                if is_white {
-                    white_value += value;
-                } else {
-                    black_value += value;
-                }
+    /// Priors of the moves of a position, None for uniform priors
+    fn move_priors(&self, board: &[u8; 82], moves: &[u16]) -> Option<Vec<f64>> {
+        match self.config.priors {
+            Priors::Uniform => None,
+            Priors::Heuristic => Some(priors::heuristic(
+                board,
+                moves,
+                &self.config.playout_weights,
+                &self.config.eval_weights,
+            )),
+            Priors::Evaluator => Some(priors::from_evaluation(
+                board,
+                moves,
+                self.config.prior_temperature as f64,
+                |child| self.evaluate_board(child),
+            )),
+        }
+    }
+
+    /// Random rollout that also reports its first moves (with their hash deltas)
+    /// so they can be recorded in the search tree
+    fn rollout(&self, board: &[u8; 82], depth: u32, rng: &mut impl Rng, prefix: &mut Vec<(u16, u64)>) -> i32 {
+        prefix.clear();
+        // The rollout is played in place on a single working copy
+        let mut board = *board;
+        // Scores are negated at each ply to stay in the starting player's perspective
+        let mut sign = 1;
+
+        // Terminal condition: max depth reached or game over
+        for _ in depth..self.config.max_depth {
+            // Positions covered by the tablebases have an exact value
+            if let Some(score) = self.tablebase_score(&board) {
+                return sign * score;
             }
 
-            // Add value for top piece if stacked
-            if top_code > 0 && (top_code as usize) < PIECE_VALUES.len() {
-                let value = PIECE_VALUES[top_code as usize];
-                if is_white {
-                    white_value += value;
-                } else {
-                    black_value += value;
+            // Generate legal moves (on the CPU: same moves, in a stable order)
+            let moves = cpu_move_gen::generate_moves(&board);
+            if moves.is_empty() {
+                break;
+            }
+
+            let random_move = match self.config.playouts {
+                Playouts::Light => moves[rng.gen_range(0..moves.len())],
+                Playouts::Heavy => {
+                    let weights = &self.config.playout_weights;
+                    match weights.choose(&board, &moves, &self.config.eval_weights, |bound| rng.gen_range(0..bound)) {
+                        Some(mv) => mv,
+                        None => break,
+                    }
                 }
+            };
+            let Ok(undo) = make_move(&mut board, random_move) else {
+                break; // Invalid move, evaluate current position
+            };
+            if prefix.len() < TREE_RECORD_PLIES {
+                prefix.push((random_move, undo.hash_delta));
             }
+            sign = -sign;
         }
 
-        // Return value from perspective of current player
-        if white_to_move {
-            white_value - black_value
-        } else {
-            black_value - white_value
-        }
+        sign * self
+            .tablebase_score(&board)
+            .unwrap_or_else(|| self.leaf_score(&board))
     }
 
-    /// Apply a move to a board state (simplified version without full game logic)
-    /// This is a GPU-independent implementation for the engine
-    fn apply_move_simple(&self, board: &[u8; 82], move_encoding: u16) -> Result<[u8; 82], String> {
-        let mut new_board = board.clone();
-        
-        let from = (move_encoding & 0x7F) as usize;
-        let to = ((move_encoding >> 7) & 0x7F) as usize;
-        let unstack = (move_encoding & 0x4000) != 0;
+    /// Find the best move using MCTS with GPU acceleration and multi-threading.
+    /// Positions found in the opening book or the tablebases are answered without searching.
+    pub fn find_best_move(&mut self, board: &[u8; 82]) -> Result<u16, String> {
+        self.find_best_move_scored(board).map(|(mv, _)| mv)
+    }
 
-        if from >= BOARD_SIZE || to >= BOARD_SIZE {
-            return Err("Invalid move: position out of bounds".to_string());
-        }
+    /// Same as [`MctsEngine::find_best_move`], along with the score of the position for
+    /// the side to move: the search score, or the exact tablebase score. Book moves and
+    /// forced moves are not searched and have no score.
+    pub fn find_best_move_scored(&mut self, board: &[u8; 82]) -> Result<(u16, Option<i32>), String> {
+        self.find_best_move_until(board, &AtomicBool::new(false))
+            .map(|choice| (choice.mv, choice.score))
+    }
 
-        let piece = board[from];
-        if piece == 0 {
-            return Err("No piece at source position".to_string());
+    /// Same as [`MctsEngine::find_best_move_scored`], with the search cut short once
+    /// `stop` is set, and the statistics of the root moves. Fails when stopped before
+    /// any simulation finished.
+    pub fn find_best_move_until(&mut self, board: &[u8; 82], stop: &AtomicBool) -> Result<MoveChoice, String> {
+        if let Some(mv) = self.probe_book(board) {
+            return Ok(MoveChoice { mv, score: None, root_moves: Vec::new() });
+        }
+        if let Some((mv, value)) = self.probe_tablebases(board) {
+            let score = value.score(self.config.eval_weights.king_value);
+            return Ok(MoveChoice { mv, score: Some(score), root_moves: Vec::new() });
         }
+        let result = self.search_until(board, stop)?;
+        let searched = result.root_moves.iter().any(|stats| stats.visits > 0);
+        Ok(MoveChoice {
+            mv: self.select_move(board, &result),
+            score: searched.then_some(result.score),
+            root_moves: result.root_moves,
+        })
+    }
 
-        if unstack {
-            // Unstack top piece
-            let payload = piece & 0x3F;
-            let top_code = (payload >> 3) & 0x07;
-            let bottom_code = payload & 0x07;
-            let color_bit = piece & 0x40;
+    /// Apply the configured move selection to a search result
+    fn select_move(&self, board: &[u8; 82], result: &SearchResult) -> u16 {
+        let selection = &self.config.move_selection;
+        if selection.is_greedy() {
+            return result.best_move;
+        }
+        // Half a King is only reachable by winning (or losing) it
+        let decisive = self.config.eval_weights.king_value as f64 / 2.0;
+        let chosen = match self.config.seed {
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(zobrist::mix64(!seed ^ zobrist::hash_binary(board)));
+                selection.choose(&result.root_moves, decisive, &mut rng)
+            }
+            None => selection.choose(&result.root_moves, decisive, &mut rand::thread_rng()),
+        };
+        chosen.unwrap_or(result.best_move)
+    }
 
-            if top_code == 0 {
-                return Err("Cannot unstack: no top piece".to_string());
+    /// Pick a move from the opening book, if one is configured and knows the position
+    pub fn probe_book(&self, board: &[u8; 82]) -> Option<u16> {
+        let book = self.config.book.as_ref()?;
+        match self.config.seed {
+            Some(seed) => {
+                let mut rng = StdRng::seed_from_u64(zobrist::mix64(seed ^ zobrist::hash_binary(board)));
+                book.pick_move(board, &mut rng)
             }
+            None => book.pick_move(board, &mut rand::thread_rng()),
+        }
+    }
+
+    /// Best move and exact value from the tablebases, if they cover the position
+    pub fn probe_tablebases(&self, board: &[u8; 82]) -> Option<(u16, TbValue)> {
+        self.config.tablebases.as_ref()?.best_move(board)
+    }
 
-            // Create new bottom piece (remove top)
-            new_board[from] = color_bit | bottom_code;
+    /// Number of positions in the search tree
+    pub fn tree_size(&self) -> usize {
+        self.tree.lock().unwrap().len()
+    }
 
-            // Create moving piece (top becomes new bottom)
-            let moving_piece = color_bit | top_code;
+    /// Forget the statistics gathered by earlier searches
+    pub fn clear_tree(&mut self) {
+        self.ponder_miss();
+        self.tree.lock().unwrap().clear();
+    }
 
-            // Place at destination (simple: just replace, no stacking logic)
-            new_board[to] = moving_piece;
-        } else {
-            // Move entire piece/stack
-            new_board[from] = 0;
-            new_board[to] = piece; // Simplified: just capture/replace
+    /// Start pondering after our own move: `board` is the position the opponent now
+    /// faces. The expected reply (most visited in the tree, or else the best by static
+    /// evaluation) is returned and the position after it is searched in a background
+    /// thread until [`MctsEngine::ponder_hit`] or [`MctsEngine::ponder_miss`].
+    pub fn start_pondering(&mut self, board: &[u8; 82]) -> Option<u16> {
+        self.ponder_miss();
+        if !self.config.reuse_tree {
+            return None;
         }
 
-        // Switch turn
-        new_board[81] = if new_board[81] == 1 { 0 } else { 1 };
+        let reply = self.expected_reply(board)?;
+        let mut pondered = *board;
+        make_move(&mut pondered, reply).ok()?;
+        if cpu_move_gen::generate_moves(&pondered).is_empty() {
+            return None;
+        }
 
-        Ok(new_board)
+        self.tree.lock().unwrap().advance();
+        let stop = Arc::new(AtomicBool::new(false));
+        let worker = self.worker();
+        let worker_stop = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            while !worker_stop.load(Ordering::Relaxed) {
+                // Nothing to learn from a forced move
+                match worker.search_round(&pondered, &worker_stop) {
+                    Ok(result) if result.root_moves.len() > 1 => {}
+                    _ => break,
+                }
+            }
+        });
+
+        self.ponder = Some(PonderJob { board: pondered, reply, stop, handle });
+        Some(reply)
     }
 
-    /// Run simulations from a given board state
-    fn simulate(&self, board: &[u8; 82], depth: u32) -> i32 {
-        // Terminal condition: max depth reached or game over
-        if depth >= self.config.max_depth {
-            return self.evaluate_board(board);
-        }
+    /// Reply the engine is pondering on, if any
+    pub fn pondered_reply(&self) -> Option<u16> {
+        self.ponder.as_ref().map(|job| job.reply)
+    }
 
-        // Generate legal moves
-        let moves = match self.move_gen.generate_moves(board) {
-            Ok(m) => m,
-            Err(_) => return self.evaluate_board(board), // No moves, evaluate position
-        };
+    /// The opponent played the expected reply: stop pondering and answer the pondered
+    /// position, building on the statistics gathered meanwhile
+    pub fn ponder_hit(&mut self) -> Result<u16, String> {
+        let board = self.stop_pondering().ok_or("Not pondering")?;
+        self.find_best_move(&board)
+    }
 
-        if moves.is_empty() {
-            return self.evaluate_board(board);
-        }
+    /// The opponent played something else (or the game ended): stop pondering
+    pub fn ponder_miss(&mut self) {
+        self.stop_pondering();
+    }
 
-        // Simple rollout: pick random move and continue
-        let mut rng = rand::thread_rng();
-        let random_move = moves[rng.gen_range(0..moves.len())];
+    /// Stop the pondering thread and return the pondered position
+    fn stop_pondering(&mut self) -> Option<[u8; 82]> {
+        let job = self.ponder.take()?;
+        job.stop.store(true, Ordering::Relaxed);
+        job.handle.join().ok();
+        Some(job.board)
+    }
 
-        match self.apply_move_simple(board, random_move) {
-            Ok(new_board) => -self.simulate(&new_board, depth + 1), // Negate for opponent's perspective
-            Err(_) => self.evaluate_board(board), // Invalid move, evaluate current position
+    /// Most likely opponent reply in a position
+    fn expected_reply(&self, board: &[u8; 82]) -> Option<u16> {
+        let hash = zobrist::hash_binary(board);
+        if let Some(mv) = self.tree.lock().unwrap().most_visited(hash) {
+            return Some(mv);
         }
+        cpu_move_gen::generate_moves(board).into_iter().max_by_key(|&mv| {
+            let mut child = *board;
+            match make_move(&mut child, mv) {
+                Ok(undo) if undo.captured_king() => i32::MAX,
+                Ok(_) => -self.evaluate_board(&child),
+                Err(_) => i32::MIN,
+            }
+        })
     }
 
-    /// Find the best move using MCTS with GPU acceleration and multi-threading
-    pub fn find_best_move(&mut self, board: &[u8; 82]) -> Result<u16, String> {
-        // Reset search-specific stats
-        let search_start_moves = self.stats.total_moves.load(Ordering::Relaxed);
-        
-        // Generate all legal moves
-        let moves = self.move_gen.generate_moves(board)?;
+    /// Search a position and return the best move along with the statistics of every root move.
+    /// With `reuse_tree`, the statistics include those gathered by earlier searches and pondering.
+    pub fn search(&mut self, board: &[u8; 82]) -> Result<SearchResult, String> {
+        self.search_until(board, &AtomicBool::new(false))
+    }
+
+    /// Same as [`MctsEngine::search`], cut short once `stop` is set (from another
+    /// thread): the result covers the simulations finished by then
+    pub fn search_until(&mut self, board: &[u8; 82], stop: &AtomicBool) -> Result<SearchResult, String> {
+        self.ponder_miss();
+        self.tree.lock().unwrap().advance();
+        self.search_round(board, stop)
+    }
+
+    /// One round of `simulations_per_move` simulations per root move, cut short by `stop`
+    fn search_round(&self, board: &[u8; 82], stop: &AtomicBool) -> Result<SearchResult, String> {
+        // Generate all legal moves, in a stable order for reproducible tie-breaks
+        let mut moves = self.move_gen.generate_moves(board)?;
+        moves.sort_unstable();
 
         if moves.is_empty() {
             return Err("No legal moves available".to_string());
         }
 
         if moves.len() == 1 {
-            return Ok(moves[0]);
+            return Ok(SearchResult {
+                best_move: moves[0],
+                score: 0,
+                root_moves: vec![RootMoveStats { mv: moves[0], visits: 0, total_score: 0 }],
+            });
         }
 
         // Use GPU batch processing if available
-        if let Some(ref batch_sim) = self.batch_sim {
-            self.find_best_move_gpu(board, &moves, batch_sim, search_start_moves)
+        // The GPU only knows the weighted evaluation, not the network nor quiescence
+        let gpu_sim = self
+            .batch_sim
+            .as_ref()
+            .filter(|_| self.config.nnue.is_none() && self.config.quiescence.is_none());
+        let mut root_moves = if self.config.parallelism == Parallelism::Tree {
+            self.find_best_move_tree(board, &moves, stop)
         } else {
-            self.find_best_move_cpu(board, &moves, search_start_moves)
+            self.find_best_move_root(board, &moves, gpu_sim.map(Arc::as_ref), stop)
+        };
+
+        // Merge with what earlier searches learned about this position
+        if self.config.reuse_tree {
+            let hash = zobrist::hash_binary(board);
+            let mut tree = self.tree.lock().unwrap();
+            tree.record(hash, &root_moves);
+            if let Some(known) = tree.moves(hash) {
+                for stats in root_moves.iter_mut() {
+                    if let Some(merged) = known.iter().find(|known| known.mv == stats.mv) {
+                        *stats = *merged;
+                    }
+                }
+            }
+        }
+
+        let best = self.best_root_move(&root_moves).ok_or("No valid moves found")?;
+        Ok(SearchResult {
+            best_move: best.mv,
+            score: best.mean_score().round() as i32,
+            root_moves,
+        })
+    }
+
+    /// Move with the best average score; guided searches barely try the moves they do
+    /// not believe in, so their choice is the most visited move
+    fn best_root_move(&self, root_moves: &[RootMoveStats]) -> Option<RootMoveStats> {
+        let guided = self.guided();
+        root_moves
+            .iter()
+            .filter(|stats| stats.visits > 0)
+            .max_by(|a, b| {
+                let by_mean = a.mean_score().partial_cmp(&b.mean_score()).unwrap_or(std::cmp::Ordering::Equal);
+                if guided {
+                    a.visits.cmp(&b.visits).then(by_mean)
+                } else {
+                    by_mean
+                }
+            })
+            .copied()
+    }
+
+    /// Search `board` round after round, each of `simulations_per_move` simulations per
+    /// root move, until `stop` is set. `report` receives the progress after every round
+    /// (a round cut short included). The result covers the whole analysis.
+    pub fn analyze(
+        &mut self,
+        board: &[u8; 82],
+        stop: &AtomicBool,
+        mut report: impl FnMut(&SearchInfo),
+    ) -> Result<SearchResult, String> {
+        self.ponder_miss();
+        let started = Instant::now();
+        let simulations = self.stats.simulations.load(Ordering::Relaxed);
+        // Without the search tree, rounds do not add up by themselves
+        let mut totals: Vec<RootMoveStats> = Vec::new();
+        let mut last = None;
+        let mut depth = 0;
+
+        while !stop.load(Ordering::Relaxed) {
+            self.tree.lock().unwrap().advance();
+            let result = match self.search_round(board, stop) {
+                Ok(result) => result,
+                Err(_) if last.is_some() => break,
+                Err(e) => return Err(e),
+            };
+            let forced = result.root_moves.len() < 2;
+            let result = if self.config.reuse_tree || forced {
+                result
+            } else {
+                for stats in &result.root_moves {
+                    match totals.iter_mut().find(|total| total.mv == stats.mv) {
+                        Some(total) => {
+                            total.visits += stats.visits;
+                            total.total_score += stats.total_score;
+                        }
+                        None => totals.push(*stats),
+                    }
+                }
+                let best = self.best_root_move(&totals).ok_or("No valid moves found")?;
+                SearchResult { best_move: best.mv, score: best.mean_score().round() as i32, root_moves: totals.clone() }
+            };
+
+            depth += 1;
+            report(&SearchInfo {
+                depth,
+                nodes: self.stats.simulations.load(Ordering::Relaxed) - simulations,
+                best_move: result.best_move,
+                score: result.score,
+                pv: self.principal_variation(board, result.best_move),
+                elapsed: started.elapsed(),
+            });
+            last = Some(result);
+            // Nothing to learn from a forced move
+            if forced {
+                break;
+            }
+        }
+        last.ok_or_else(|| "The analysis was stopped before any simulation finished".to_string())
+    }
+
+    /// `first` followed by the most visited moves the search tree knows after it
+    fn principal_variation(&self, board: &[u8; 82], first: u16) -> Vec<u16> {
+        let tree = self.tree.lock().unwrap();
+        let mut position = *board;
+        let mut hash = zobrist::hash_binary(board);
+        let mut pv = vec![first];
+        while pv.len() < MAX_PV_LENGTH {
+            let Ok(undo) = make_move(&mut position, pv[pv.len() - 1]) else {
+                break;
+            };
+            hash ^= undo.hash_delta;
+            if undo.captured_king() {
+                break;
+            }
+            match tree.most_visited(hash) {
+                Some(mv) => pv.push(mv),
+                None => break,
+            }
+        }
+        pv
+    }
+
+    /// Root-parallel simulations: `simulations_per_move` for every root move, run in
+    /// rounds, or with priors or widening the same total budget handed out by PUCT in
+    /// [`ROOT_WAVES`] waves
+    fn find_best_move_root(
+        &self,
+        board: &[u8; 82],
+        moves: &[u16],
+        gpu_sim: Option<&BatchSimulationEngine>,
+        stop: &AtomicBool,
+    ) -> Vec<RootMoveStats> {
+        let run = |tasks: &[RootTask]| match gpu_sim {
+            Some(batch_sim) => self.find_best_move_gpu(board, tasks, batch_sim, stop),
+            None => self.find_best_move_cpu(board, tasks, stop),
+        };
+        let per_move = self.config.simulations_per_move;
+        let priors = match self.move_priors(board, moves) {
+            Some(priors) => priors,
+            None if self.config.widening.is_some() => vec![1.0 / moves.len() as f64; moves.len()],
+            None => Vec::new(),
+        };
+
+        let mut root_moves: Vec<RootMoveStats> =
+            moves.iter().map(|&mv| RootMoveStats { mv, visits: 0, total_score: 0 }).collect();
+        if priors.is_empty() {
+            let round = match gpu_sim {
+                Some(_) => self.config.gpu_batch_size.clamp(1, u32::MAX as usize) as u32,
+                None => CPU_ROUND_SIMULATIONS,
+            };
+            let mut spent = 0;
+            while spent < per_move && !stop.load(Ordering::Relaxed) {
+                let simulations = round.min(per_move - spent);
+                spent += simulations;
+                let tasks: Vec<RootTask> = root_moves
+                    .iter()
+                    .map(|stats| RootTask { mv: stats.mv, simulations, done: stats.visits })
+                    .collect();
+                for (stats, result) in root_moves.iter_mut().zip(run(&tasks)) {
+                    stats.visits += result.visits;
+                    stats.total_score += result.total_score;
+                }
+            }
+            return root_moves;
+        }
+
+        let budget = per_move.saturating_mul(moves.len() as u32);
+        let wave = budget.div_ceil(ROOT_WAVES).max(1);
+        let mut spent = 0;
+        while spent < budget && !stop.load(Ordering::Relaxed) {
+            let size = wave.min(budget - spent);
+            let counts = priors::allocate(
+                &root_moves,
+                &priors,
+                size,
+                self.config.exploration_constant as f64,
+                self.config.widening,
+            );
+            let tasks: Vec<RootTask> = root_moves
+                .iter()
+                .zip(&counts)
+                .map(|(stats, &simulations)| RootTask { mv: stats.mv, simulations, done: stats.visits })
+                .collect();
+            for (stats, result) in root_moves.iter_mut().zip(run(&tasks)) {
+                stats.visits += result.visits;
+                stats.total_score += result.total_score;
+            }
+            spent += size;
         }
+        root_moves
     }
 
     /// GPU-accelerated move evaluation with batch processing
     fn find_best_move_gpu(
         &self,
         board: &[u8; 82],
-        moves: &[u16],
+        tasks: &[RootTask],
         batch_sim: &BatchSimulationEngine,
-        _search_start_moves: u64,
-    ) -> Result<u16, String> {
+        stop: &AtomicBool,
+    ) -> Vec<RootMoveStats> {
+        let root_hash = zobrist::hash_binary(board);
+        let rollout_plies = self.config.max_depth.saturating_sub(1);
+        // Side to move right after the root move, which GPU scores refer to
+        let opponent = 1 - board[81];
+
         // Evaluate each move using parallel processing
-        let move_scores: Vec<(u16, i32, u32)> = moves
+        tasks
             .par_iter()
-            .map(|&mv| {
-                let mut total_score = 0i32;
+            .map(|&RootTask { mv, simulations, done }| {
+                let mut total_score = 0i64;
                 let mut valid_simulations = 0u32;
                 let mut moves_evaluated = 0u64;
+                let mut rng = self.move_rng(root_hash, mv, done);
+                let Ok(child) = self.apply_move_simple(board, mv) else {
+                    return RootMoveStats { mv, visits: 0, total_score: 0 }; // Skip invalid moves
+                };
+                let child_hash = zobrist::hash_binary(&child);
+                let mut prefix = Vec::with_capacity(TREE_RECORD_PLIES);
+                let mut visits = Vec::new();
 
                 // Process simulations in batches
                 let batch_size = self.config.gpu_batch_size;
-                let num_batches = (self.config.simulations_per_move as usize + batch_size - 1) / batch_size;
+                let num_batches = (simulations as usize).div_ceil(batch_size);
 
                 for batch_idx in 0..num_batches {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
                     let sims_in_batch = batch_size.min(
-                        self.config.simulations_per_move as usize - batch_idx * batch_size
+                        simulations as usize - batch_idx * batch_size
                     );
 
                     // Prepare batch: apply initial move and create boards for simulation
-                    let mut batch_boards = Vec::with_capacity(sims_in_batch);
-                    let mut batch_moves = Vec::with_capacity(sims_in_batch);
-
-                    for _ in 0..sims_in_batch {
-                        batch_boards.push(*board);
-                        batch_moves.push(mv);
-                    }
+                    let batch_boards = vec![*board; sims_in_batch];
+                    let batch_moves = vec![mv; sims_in_batch];
+                    let seeds: Vec<u32> = (0..sims_in_batch).map(|_| rng.gen()).collect();
 
                     // Process batch on GPU
-                    match batch_sim.process_batch(&batch_boards, &batch_moves) {
+                    match batch_sim.process_rollouts(&batch_boards, &batch_moves, &seeds, rollout_plies) {
                         Ok(results) => {
                             self.stats.gpu_batches.fetch_add(1, Ordering::Relaxed);
                             
                             for result in results {
                                 if result.valid {
                                     // Negate score for opponent's perspective
-                                    total_score -= result.score;
+                                    let score = self
+                                        .tablebase_score(&result.board)
+                                        .map(|exact| if result.board[81] == opponent { exact } else { -exact })
+                                        .unwrap_or(result.score);
+                                    total_score -= score as i64;
                                     valid_simulations += 1;
                                     moves_evaluated += 1;
+
+                                    if self.config.reuse_tree {
+                                        // Replay the rollout's first plies for their hashes
+                                        prefix.clear();
+                                        let mut position = child;
+                                        for &played in result.first_moves.iter().take(TREE_RECORD_PLIES) {
+                                            match make_move(&mut position, played) {
+                                                Ok(undo) => prefix.push((played, undo.hash_delta)),
+                                                Err(_) => break,
+                                            }
+                                        }
+                                        Self::prefix_visits(child_hash, score, &prefix, &mut visits);
+                                    }
                                 }
                             }
                         }
@@ -421,85 +1011,203 @@ impl MctsEngine {
                             // Fall back to CPU for this batch
                             self.stats.cpu_sims.fetch_add(sims_in_batch as u64, Ordering::Relaxed);
                             for _ in 0..sims_in_batch {
-                                if let Ok(new_board) = self.apply_move_simple(board, mv) {
-                                    let score = -self.simulate(&new_board, 1);
-                                    total_score += score;
-                                    valid_simulations += 1;
-                                    moves_evaluated += 1;
+                                let child_score = self.rollout(&child, 1, &mut rng, &mut prefix);
+                                total_score -= child_score as i64;
+                                valid_simulations += 1;
+                                moves_evaluated += 1;
+                                if self.config.reuse_tree {
+                                    Self::prefix_visits(child_hash, child_score, &prefix, &mut visits);
                                 }
                             }
                         }
                     }
                 }
+                self.record_visits(visits);
 
                 self.stats.simulations.fetch_add(valid_simulations as u64, Ordering::Relaxed);
                 self.stats.total_moves.fetch_add(moves_evaluated, Ordering::Relaxed);
 
-                (mv, total_score, valid_simulations)
-            })
-            .collect();
-
-        // Find move with best average score
-        let best_move = move_scores
-            .iter()
-            .filter(|(_, _, sims)| *sims > 0)
-            .max_by(|a, b| {
-                let avg_a = a.1 as f32 / a.2 as f32;
-                let avg_b = b.1 as f32 / b.2 as f32;
-                avg_a.partial_cmp(&avg_b).unwrap_or(std::cmp::Ordering::Equal)
+                RootMoveStats { mv, visits: valid_simulations, total_score }
             })
-            .map(|(mv, _, _)| *mv)
-            .ok_or("No valid moves found")?;
-
-        Ok(best_move)
+            .collect()
     }
 
     /// CPU-based move evaluation with multi-threading (fallback)
     fn find_best_move_cpu(
         &self,
         board: &[u8; 82],
-        moves: &[u16],
-        _search_start_moves: u64,
-    ) -> Result<u16, String> {
+        tasks: &[RootTask],
+        stop: &AtomicBool,
+    ) -> Vec<RootMoveStats> {
+        let root_hash = zobrist::hash_binary(board);
+
         // Evaluate each move using parallel processing
-        let move_scores: Vec<(u16, i32, u32)> = moves
+        tasks
             .par_iter()
-            .map(|&mv| {
-                let mut total_score = 0;
+            .map(|&RootTask { mv, simulations: planned, done }| {
+                let mut total_score = 0i64;
                 let mut simulations = 0;
+                let mut new_board = *board;
+                let Ok(undo) = make_move(&mut new_board, mv) else {
+                    return RootMoveStats { mv, visits: 0, total_score: 0 }; // Skip invalid moves
+                };
+                let child_hash = root_hash ^ undo.hash_delta;
+                let mut rng = self.move_rng(root_hash, mv, done);
+                let mut prefix = Vec::with_capacity(TREE_RECORD_PLIES);
+                let mut visits = Vec::new();
+
+                for _ in 0..planned {
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    // Scores alternate perspective along the recorded plies
+                    let child_score = self.rollout(&new_board, 1, &mut rng, &mut prefix);
+                    total_score -= child_score as i64;
+                    simulations += 1;
 
-                for _ in 0..self.config.simulations_per_move {
-                    match self.apply_move_simple(board, mv) {
-                        Ok(new_board) => {
-                            let score = -self.simulate(&new_board, 1);
-                            total_score += score;
-                            simulations += 1;
-                        }
-                        Err(_) => continue, // Skip invalid moves
+                    if self.config.reuse_tree {
+                        Self::prefix_visits(child_hash, child_score, &prefix, &mut visits);
                     }
                 }
+                self.record_visits(visits);
 
                 self.stats.simulations.fetch_add(simulations as u64, Ordering::Relaxed);
                 self.stats.cpu_sims.fetch_add(simulations as u64, Ordering::Relaxed);
                 self.stats.total_moves.fetch_add(simulations as u64, Ordering::Relaxed);
 
-                (mv, total_score, simulations)
+                RootMoveStats { mv, visits: simulations, total_score }
             })
-            .collect();
+            .collect()
+    }
 
-        // Find move with best average score
-        let best_move = move_scores
-            .iter()
-            .filter(|(_, _, sims)| *sims > 0)
-            .max_by(|a, b| {
-                let avg_a = a.1 as f32 / a.2 as f32;
-                let avg_b = b.1 as f32 / b.2 as f32;
-                avg_a.partial_cmp(&avg_b).unwrap_or(std::cmp::Ordering::Equal)
-            })
-            .map(|(mv, _, _)| *mv)
-            .ok_or("No valid moves found")?;
+    /// Visits of the first rollout plies below a root move, as `(position hash, move,
+    /// score)`. `score` is the rollout's result for the side to move at `hash`; scores
+    /// alternate perspective along the plies.
+    fn prefix_visits(hash: u64, score: i32, prefix: &[(u16, u64)], visits: &mut Vec<(u64, u16, i32)>) {
+        let (mut hash, mut score) = (hash, score);
+        for &(played, hash_delta) in prefix {
+            visits.push((hash, played, score));
+            hash ^= hash_delta;
+            score = -score;
+        }
+    }
+
+    /// Add rollout visits to the search tree, so the next searches start from them
+    fn record_visits(&self, visits: Vec<(u64, u16, i32)>) {
+        if visits.is_empty() {
+            return;
+        }
+        let mut tree = self.tree.lock().unwrap();
+        for (hash, played, score) in visits {
+            tree.record_visit(hash, played, score);
+        }
+    }
+
+    /// Shared-tree search: every thread descends the same tree, steered apart by virtual
+    /// losses, and the total budget is `simulations_per_move` per root move as in the
+    /// root-parallel search. Seeded searches are only reproducible with a single thread.
+    fn find_best_move_tree(&self, board: &[u8; 82], moves: &[u16], stop: &AtomicBool) -> Vec<RootMoveStats> {
+        let root_hash = zobrist::hash_binary(board);
+        let shared = SharedTree::new(self.config.tree_capacity.max(1));
+        shared.expand(root_hash, moves, self.move_priors(board, moves));
+        let Some(root) = shared.get(root_hash) else {
+            return Vec::new();
+        };
+
+        let budget = self.config.simulations_per_move.saturating_mul(moves.len() as u32);
+        let claimed = AtomicU32::new(0);
+        let threads = match self.config.search_threads {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+
+        std::thread::scope(|scope| {
+            for worker in 0..threads {
+                let (shared, claimed) = (&shared, &claimed);
+                scope.spawn(move || {
+                    let mut rng = match self.config.seed {
+                        Some(seed) => StdRng::seed_from_u64(zobrist::mix64(seed ^ root_hash ^ worker as u64)),
+                        None => StdRng::from_rng(rand::thread_rng()).expect("thread_rng never fails"),
+                    };
+                    let mut path = Vec::new();
+                    let mut prefix = Vec::new();
+                    let mut simulations = 0u64;
+                    while !stop.load(Ordering::Relaxed) && claimed.fetch_add(1, Ordering::Relaxed) < budget {
+                        self.simulate_tree(board, root_hash, shared, &mut path, &mut prefix, &mut rng);
+                        simulations += 1;
+                    }
+                    self.stats.simulations.fetch_add(simulations, Ordering::Relaxed);
+                    self.stats.cpu_sims.fetch_add(simulations, Ordering::Relaxed);
+                    self.stats.total_moves.fetch_add(simulations, Ordering::Relaxed);
+                });
+            }
+        });
+
+        // Keep the opponent's replies for the next search and pondering
+        if self.config.reuse_tree {
+            let mut tree = self.tree.lock().unwrap();
+            for &mv in moves {
+                let mut child = *board;
+                let Ok(undo) = make_move(&mut child, mv) else {
+                    continue;
+                };
+                if let Some(node) = shared.get(root_hash ^ undo.hash_delta) {
+                    let replies: Vec<RootMoveStats> =
+                        node.stats().into_iter().filter(|stats| stats.visits > 0).collect();
+                    tree.record(root_hash ^ undo.hash_delta, &replies);
+                }
+            }
+        }
+
+        root.stats()
+    }
+
+    /// One tree-parallel simulation: select down the shared tree, expand the first
+    /// unknown position, roll out from it and back the result up the path
+    fn simulate_tree(
+        &self,
+        root_board: &[u8; 82],
+        root_hash: u64,
+        shared: &SharedTree,
+        path: &mut Vec<(Arc<parallel::Node>, usize)>,
+        prefix: &mut Vec<(u16, u64)>,
+        rng: &mut impl Rng,
+    ) {
+        let exploration = self.config.exploration_constant as f64;
+        let virtual_loss = self.config.virtual_loss as i64;
+        let widening = self.config.widening;
+        let mut board = *root_board;
+        let mut hash = root_hash;
+        let mut depth = 0;
+        path.clear();
+
+        let mut node = shared.get(root_hash);
+        while let Some(current) = node.take() {
+            let Some((index, mv)) = current.select(exploration, virtual_loss, widening) else {
+                break; // No legal moves: the position is scored as is
+            };
+            path.push((current, index));
+            let Ok(undo) = make_move(&mut board, mv) else {
+                break;
+            };
+            hash ^= undo.hash_delta;
+            depth += 1;
+            if depth >= self.config.max_depth {
+                break;
+            }
+            node = shared.get(hash);
+            if node.is_none() {
+                let moves = cpu_move_gen::generate_moves(&board);
+                shared.expand(hash, &moves, self.move_priors(&board, &moves));
+            }
+        }
 
-        Ok(best_move)
+        // The rollout scores the leaf for its side to move; each edge wants its mover's view
+        let mut score = self.rollout(&board, depth, rng, prefix);
+        for (node, index) in path.drain(..).rev() {
+            score = -score;
+            node.backup(index, score);
+        }
     }
 
     /// Get search statistics
@@ -520,6 +1228,11 @@ impl MctsEngine {
 
     /// Update the configuration
     pub fn set_config(&mut self, config: EngineConfig) {
+        self.ponder_miss();
+        if !config.reuse_tree || config.tree_capacity != self.config.tree_capacity {
+            *self.tree.lock().unwrap() = SearchTree::new(config.tree_capacity);
+        }
+
         // Check if we need to initialize batch sim before moving config
         let use_gpu = config.use_gpu_simulation;
         self.config = config;
@@ -528,9 +1241,30 @@ impl MctsEngine {
         if use_gpu && self.batch_sim.is_none() {
             if let Ok(batch_sim) = BatchSimulationEngine::new_sync() {
                 eprintln!("✓ GPU batch simulation engine initialized");
-                self.batch_sim = Some(batch_sim);
+                self.batch_sim = Some(Arc::new(batch_sim));
             }
         }
+
+        // Keep the GPU evaluation in sync with the CPU one
+        if let Some(ref batch_sim) = self.batch_sim {
+            batch_sim.set_eval_weights(&self.config.eval_weights);
+            batch_sim.set_playouts(self.config.playouts, &self.config.playout_weights);
+        }
+    }
+}
+
+/// Simulations to run for one root move in a root-parallel pass
+#[derive(Clone, Copy, Debug)]
+struct RootTask {
+    mv: u16,
+    simulations: u32,
+    /// Simulations the move already ran in the current search
+    done: u32,
+}
+
+impl Drop for MctsEngine {
+    fn drop(&mut self) {
+        self.stop_pondering();
     }
 }
 
@@ -565,8 +1299,392 @@ mod tests {
 
         // Test board with one white soldier
         board[40] = 0b1000001; // White Soldier at center
-        let eval = engine.evaluate_board(&board);
-        assert_eq!(eval, 1, "Board with one white soldier should evaluate to 1 for white");
+        let eval = evaluate(&board, &EvalWeights::material_only());
+        assert_eq!(eval, 100, "Board with one white soldier should evaluate to 100 for white");
+        assert!(engine.evaluate_board(&board) > 0);
+    }
+
+    #[test]
+    fn test_nnue_replaces_weighted_evaluation() {
+        let network = Arc::new(NnueNetwork::new(16, 8, 5));
+        let config = EngineConfig {
+            simulations_per_move: 2,
+            nnue: Some(network.clone()),
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        let board = crate::Game::new().to_binary();
+        assert_eq!(engine.evaluate_board(&board), network.evaluate(&board));
+        assert!(engine.find_best_move(&board).is_ok());
+        assert_eq!(engine.get_statistics().gpu_batches_processed, 0);
+    }
+
+    #[test]
+    fn test_quiescence_scores_rollout_leaves() {
+        let config = EngineConfig {
+            max_depth: 1,
+            simulations_per_move: 1,
+            quiescence: Some(QuiescenceConfig::default()),
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        // Taking the guarded Soldier loses the Commander: the leaf sees the recapture
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = 0x43; // White Commander
+        board[22] = 0x01; // Black Soldier
+        board[24] = 0x03; // Black Commander guarding it
+        board[72] = 0x78;
+        board[8] = 0x38;
+        let mut child = board;
+        make_move(&mut child, 40 | (22 << 7)).unwrap();
+        assert!(engine.leaf_score(&child) > engine.evaluate_board(&child));
+
+        let result = engine.search(&board).unwrap();
+        assert_ne!(result.best_move, 40 | (22 << 7));
+        assert_eq!(engine.get_statistics().gpu_batches_processed, 0);
+    }
+
+    #[test]
+    fn test_book_move_is_played_without_search() {
+        let board = crate::Game::new().to_binary();
+        let book_move: u16 = 54 | (46 << 7);
+        let mut builder = book::BookBuilder::new(1);
+        builder.add_position(&board, book_move, 1.0);
+
+        let config = EngineConfig {
+            book: Some(Arc::new(builder.build())),
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        assert_eq!(engine.find_best_move(&board).unwrap(), book_move);
+        assert_eq!(engine.get_statistics().simulations_run, 0);
+    }
+
+    #[test]
+    fn test_tablebase_move_is_played_without_search() {
+        let mut tablebases = Tablebases::default();
+        tablebases
+            .generate(&tablebase::MaterialClass::parse("K-K").unwrap(), |_| {})
+            .unwrap();
+
+        let config = EngineConfig {
+            tablebases: Some(Arc::new(tablebases)),
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        // White King next to the black King captures it
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = 0x78;
+        board[41] = 0x38;
+        assert_eq!(engine.find_best_move(&board).unwrap(), 40 | (41 << 7));
+        assert_eq!(engine.get_statistics().simulations_run, 0);
+    }
+
+    #[test]
+    fn test_tree_reuse_accumulates_statistics() {
+        let config = EngineConfig {
+            max_depth: 3,
+            simulations_per_move: 2,
+            use_gpu_simulation: false,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        let board = crate::Game::new().to_binary();
+
+        engine.search(&board).unwrap();
+        // Rollouts recorded the replies and our next positions below the root
+        assert!(engine.tree_size() > 1);
+        let result = engine.search(&board).unwrap();
+        assert!(result.root_moves.iter().all(|stats| stats.visits == 4));
+
+        engine.clear_tree();
+        let result = engine.search(&board).unwrap();
+        assert!(result.root_moves.iter().all(|stats| stats.visits == 2));
+    }
+
+    #[test]
+    fn test_gpu_rollouts_feed_the_next_search() {
+        let config = EngineConfig {
+            max_depth: 3,
+            simulations_per_move: 8,
+            gpu_batch_size: 8,
+            use_gpu_simulation: true,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        if engine.batch_sim.is_none() {
+            println!("Skipping test: GPU not available");
+            return;
+        }
+        let mut board = crate::Game::new().to_binary();
+        let mv = engine.find_best_move(&board).unwrap();
+        make_move(&mut board, mv).unwrap();
+        let reply = engine.tree.lock().unwrap().most_visited(zobrist::hash_binary(&board)).expect("recorded replies");
+        make_move(&mut board, reply).unwrap();
+
+        // Our next position already has visits from the first search's rollouts
+        let known = |engine: &MctsEngine| {
+            engine.tree.lock().unwrap().moves(zobrist::hash_binary(&board)).map_or(0, |moves| {
+                moves.iter().map(|stats| stats.visits).sum::<u32>()
+            })
+        };
+        let prior = known(&engine);
+        assert!(prior > 0);
+        let result = engine.search(&board).unwrap();
+        let visits: u32 = result.root_moves.iter().map(|stats| stats.visits).sum();
+        assert_eq!(visits, prior + 8 * result.root_moves.len() as u32);
+    }
+
+    #[test]
+    fn test_search_can_be_stopped() {
+        let config = EngineConfig {
+            max_depth: 4,
+            simulations_per_move: 1_000_000,
+            use_gpu_simulation: false,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        let board = crate::Game::new().to_binary();
+
+        // Stopped before starting: nothing to answer with
+        assert!(engine.find_best_move_until(&board, &AtomicBool::new(true)).is_err());
+        assert_eq!(engine.get_statistics().simulations_run, 0);
+
+        // Stopped from another thread: the simulations run so far decide
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopper = Arc::clone(&stop);
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(200));
+            stopper.store(true, Ordering::Relaxed);
+        });
+        let choice = engine.find_best_move_until(&board, &stop).unwrap();
+        handle.join().unwrap();
+        assert!(cpu_move_gen::generate_moves(&board).contains(&choice.mv));
+        assert!(choice.score.is_some());
+        assert_eq!(choice.root_moves.len(), 53);
+        assert!(engine.get_statistics().simulations_run < 53 * 1_000_000);
+    }
+
+    #[test]
+    fn test_analysis_reports_every_round() {
+        let config = EngineConfig {
+            max_depth: 4,
+            simulations_per_move: 20,
+            use_gpu_simulation: false,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        let board = crate::Game::new().to_binary();
+
+        // Stop after the third round
+        let stop = AtomicBool::new(false);
+        let mut infos = Vec::new();
+        let result = engine
+            .analyze(&board, &stop, |info| {
+                infos.push(info.clone());
+                if infos.len() == 3 {
+                    stop.store(true, Ordering::Relaxed);
+                }
+            })
+            .unwrap();
+        assert_eq!(infos.iter().map(|info| info.depth).collect::<Vec<_>>(), [1, 2, 3]);
+        assert!(infos.windows(2).all(|pair| pair[0].nodes < pair[1].nodes));
+        let last = &infos[2];
+        assert_eq!(last.nodes, 3 * 20 * 53);
+        assert_eq!((last.best_move, last.score), (result.best_move, result.score));
+        assert_eq!(last.pv[0], last.best_move);
+        assert!(last.pv.len() > 1); // Replies recorded by the rollouts
+        assert_eq!(result.root_moves.iter().map(|stats| stats.visits).sum::<u32>(), 3 * 20 * 53);
+    }
+
+    #[test]
+    fn test_seeded_search_is_reproducible() {
+        let board = crate::Game::new().to_binary();
+        for use_gpu_simulation in [false, true] {
+            let config = EngineConfig {
+                max_depth: 4,
+                simulations_per_move: 3,
+                use_gpu_simulation,
+                seed: Some(42),
+                ..EngineConfig::default()
+            };
+            let mut results = Vec::new();
+            for _ in 0..2 {
+                let engine = MctsEngine::with_config(config.clone());
+                if let Err(e) = &engine {
+                    println!("Skipping test: GPU not available - {}", e);
+                    return;
+                }
+                results.push(engine.unwrap().search(&board).unwrap());
+            }
+            assert_eq!(results[0].best_move, results[1].best_move);
+            assert_eq!(results[0].root_moves, results[1].root_moves);
+
+            // A repeated search on the same engine draws new rollouts
+            let mut engine = MctsEngine::with_config(config).unwrap();
+            let first = engine.search(&board).unwrap();
+            let second = engine.search(&board).unwrap();
+            assert!(second.root_moves.iter().all(|stats| stats.visits == 6));
+            assert_eq!(first.root_moves, results[0].root_moves);
+        }
+    }
+
+    #[test]
+    fn test_tree_parallel_search() {
+        let board = crate::Game::new().to_binary();
+        let config = EngineConfig {
+            max_depth: 4,
+            simulations_per_move: 5,
+            use_gpu_simulation: false,
+            parallelism: Parallelism::Tree,
+            search_threads: 4,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config.clone());
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        let result = engine.search(&board).unwrap();
+
+        // The whole budget is spent, with every root move tried at least once
+        let moves = cpu_move_gen::generate_moves(&board);
+        let visits: u32 = result.root_moves.iter().map(|stats| stats.visits).sum();
+        assert_eq!(visits, 5 * moves.len() as u32);
+        assert!(result.root_moves.iter().all(|stats| stats.visits > 0));
+        assert!(moves.contains(&result.best_move));
+        assert_eq!(engine.get_statistics().simulations_run, visits as u64);
+
+        // Opponent replies below the root are kept for the next search
+        assert!(engine.tree_size() > 1);
+
+        // A single seeded thread is reproducible
+        let seeded = EngineConfig { search_threads: 1, seed: Some(9), ..config };
+        let first = MctsEngine::with_config(seeded.clone()).unwrap().search(&board).unwrap();
+        let second = MctsEngine::with_config(seeded).unwrap().search(&board).unwrap();
+        assert_eq!(first.root_moves, second.root_moves);
+    }
+
+    #[test]
+    fn test_guided_search_concentrates_simulations() {
+        let board = crate::Game::new().to_binary();
+        let moves = cpu_move_gen::generate_moves(&board);
+        let config = EngineConfig {
+            max_depth: 3,
+            simulations_per_move: 4,
+            use_gpu_simulation: false,
+            priors: Priors::Heuristic,
+            widening: Some(Widening::default()),
+            seed: Some(3),
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config.clone());
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let result = engine.unwrap().search(&board).unwrap();
+
+        // Same budget as an even split, but not evenly spread
+        let visits: Vec<u32> = result.root_moves.iter().map(|stats| stats.visits).collect();
+        assert_eq!(visits.iter().sum::<u32>(), 4 * moves.len() as u32);
+        assert!(visits.iter().any(|&visits| visits > 4));
+        let best = result.root_moves.iter().find(|stats| stats.mv == result.best_move).unwrap();
+        assert_eq!(best.visits, *visits.iter().max().unwrap());
+
+        let again = MctsEngine::with_config(config.clone()).unwrap().search(&board).unwrap();
+        assert_eq!(again.root_moves, result.root_moves);
+
+        // Tree-parallel searches use the priors and widening at every node
+        let tree = EngineConfig { parallelism: Parallelism::Tree, search_threads: 2, ..config };
+        let result = MctsEngine::with_config(tree).unwrap().search(&board).unwrap();
+        let visits: u32 = result.root_moves.iter().map(|stats| stats.visits).sum();
+        assert_eq!(visits, 4 * moves.len() as u32);
+        assert!(result.root_moves.iter().any(|stats| stats.visits == 0));
+    }
+
+    #[test]
+    fn test_pondering() {
+        let config = EngineConfig {
+            max_depth: 2,
+            simulations_per_move: 1,
+            use_gpu_simulation: false,
+            ..EngineConfig::default()
+        };
+        let engine = MctsEngine::with_config(config);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+        let mut board = crate::Game::new().to_binary();
+        let mv = engine.find_best_move(&board).unwrap();
+        make_move(&mut board, mv).unwrap();
+
+        let reply = engine.start_pondering(&board).expect("expected reply");
+        assert!(cpu_move_gen::generate_moves(&board).contains(&reply));
+        assert_eq!(engine.pondered_reply(), Some(reply));
+
+        // Wait for the background search to make progress
+        let before = engine.get_statistics().simulations_run;
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+        while engine.get_statistics().simulations_run == before && std::time::Instant::now() < deadline {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(engine.get_statistics().simulations_run > before);
+
+        let answer = engine.ponder_hit().unwrap();
+        make_move(&mut board, reply).unwrap();
+        assert!(cpu_move_gen::generate_moves(&board).contains(&answer));
+        assert_eq!(engine.pondered_reply(), None);
+        assert!(engine.ponder_hit().is_err());
     }
 
     #[test]
@@ -577,6 +1695,7 @@ mod tests {
             exploration_constant: 2.0,
             gpu_batch_size: 128,
             use_gpu_simulation: true,
+            ..EngineConfig::default()
         };
         let engine = MctsEngine::with_config(config.clone());
         if let Err(e) = &engine {
@@ -0,0 +1,206 @@
+//! In-place move application on raw engine boards
+//!
+//! `make_move` plays an encoded move directly on a `[u8; 82]` board and returns a
+//! small undo record, `unmake_move` restores the previous position from it. This
+//! lets search code walk the game tree without allocating or copying boards.
+//!
+//! # Example
+//!
+//! ```
+//! use arx_engine::engine::{make_move, unmake_move};
+//!
+//! let mut board = [0u8; 82];
+//! board[40] = 0b1000001; // White Soldier
+//! board[81] = 1; // White to move
+//! let original = board;
+//!
+//! let mv: u16 = 40 | (30 << 7); // Soldier steps diagonally forward
+//! let undo = make_move(&mut board, mv).expect("valid move");
+//! assert_eq!(board[30], 0b1000001);
+//!
+//! unmake_move(&mut board, &undo);
+//! assert_eq!(board, original);
+//! ```
+
+use crate::zobrist;
+
+const BOARD_SIZE: usize = 81;
+const COLOR_BIT: u8 = 0x40;
+const KING_PAYLOAD: u8 = 0x38;
+
+/// Record of a move played with [`make_move`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MoveUndo {
+    /// Origin square index
+    pub from: u8,
+    /// Destination square index
+    pub to: u8,
+    /// Piece code on the origin square before the move (full stack when unstacking)
+    pub from_before: u8,
+    /// Piece code on the destination square before the move (captured piece or stack base)
+    pub to_before: u8,
+    /// Value to XOR into the position's Zobrist hash to account for the move
+    pub hash_delta: u64,
+}
+
+impl MoveUndo {
+    /// Whether the move removed an enemy piece or stack
+    pub fn is_capture(&self) -> bool {
+        self.to_before != 0 && (self.to_before & COLOR_BIT) != (self.from_before & COLOR_BIT)
+    }
+
+    /// Whether the move captured a king
+    pub fn captured_king(&self) -> bool {
+        self.is_capture() && (self.to_before & 0x3F) == KING_PAYLOAD
+    }
+}
+
+/// Play an encoded move in place and return the record needed to undo it.
+/// The board is left untouched if the move cannot be applied.
+pub fn make_move(board: &mut [u8; 82], move_encoding: u16) -> Result<MoveUndo, String> {
+    let from = (move_encoding & 0x7F) as usize;
+    let to = ((move_encoding >> 7) & 0x7F) as usize;
+    let unstack = (move_encoding & 0x4000) != 0;
+
+    if from >= BOARD_SIZE || to >= BOARD_SIZE {
+        return Err("Invalid move: position out of bounds".to_string());
+    }
+
+    let piece = board[from];
+    if piece == 0 {
+        return Err("No piece at source position".to_string());
+    }
+
+    let color_bit = piece & COLOR_BIT;
+    let payload = piece & 0x3F;
+    let top_code = (payload >> 3) & 0x07;
+    let bottom_code = payload & 0x07;
+    let is_king = payload == KING_PAYLOAD;
+
+    let (from_after, moving_piece) = if unstack {
+        if is_king || top_code == 0 {
+            return Err("Cannot unstack: no top piece".to_string());
+        }
+        // Bottom piece stays, top piece becomes a single moving piece
+        (color_bit | bottom_code, color_bit | top_code)
+    } else {
+        (0, piece)
+    };
+
+    let target = board[to];
+    let to_after = if target == 0 || (target & COLOR_BIT) != color_bit {
+        // Empty square or capture (the whole enemy stack is removed)
+        moving_piece
+    } else {
+        // Friendly piece: stack the moving piece on top of it
+        let target_payload = target & 0x3F;
+        let moving_payload = moving_piece & 0x3F;
+        if target_payload == KING_PAYLOAD || (target_payload >> 3) != 0 {
+            return Err("Cannot stack onto this piece (King or already stacked)".to_string());
+        }
+        if moving_payload == KING_PAYLOAD || (moving_payload >> 3) != 0 {
+            return Err("Cannot stack an already stacked piece".to_string());
+        }
+        color_bit | (moving_payload << 3) | target_payload
+    };
+
+    board[from] = from_after;
+    board[to] = to_after;
+    board[BOARD_SIZE] = if board[BOARD_SIZE] == 1 { 0 } else { 1 };
+
+    Ok(MoveUndo {
+        from: from as u8,
+        to: to as u8,
+        from_before: piece,
+        to_before: target,
+        hash_delta: zobrist::square_key(from, piece)
+            ^ zobrist::square_key(from, from_after)
+            ^ zobrist::square_key(to, target)
+            ^ zobrist::square_key(to, to_after)
+            ^ zobrist::WHITE_TO_MOVE_KEY,
+    })
+}
+
+/// Take back a move previously played with [`make_move`]
+pub fn unmake_move(board: &mut [u8; 82], undo: &MoveUndo) {
+    board[undo.from as usize] = undo.from_before;
+    board[undo.to as usize] = undo.to_before;
+    board[BOARD_SIZE] = if board[BOARD_SIZE] == 1 { 0 } else { 1 };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(from: usize, to: usize, unstack: bool) -> u16 {
+        ((unstack as u16) << 14) | ((to as u16) << 7) | from as u16
+    }
+
+    #[test]
+    fn test_make_unmake_restores_board_and_hash() {
+        let mut board = crate::Game::new().to_binary();
+        let original = board;
+        let hash = zobrist::hash_binary(&board);
+
+        // White Soldier from A3 (index 54) to B4 (index 46)
+        let undo = make_move(&mut board, encode(54, 46, false)).unwrap();
+        assert_eq!(board[54], 0);
+        assert_eq!(board[46], 0b1000001);
+        assert_eq!(board[81], 0);
+        assert_eq!(hash ^ undo.hash_delta, zobrist::hash_binary(&board));
+
+        unmake_move(&mut board, &undo);
+        assert_eq!(board, original);
+    }
+
+    #[test]
+    fn test_stacking_and_unstacking() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = 0b1000100; // White Paladin
+        board[42] = 0b1000101; // White Guard
+
+        // Paladin moves onto the Guard: P+G
+        let undo = make_move(&mut board, encode(40, 42, false)).unwrap();
+        assert_eq!(board[42], 0b1100101);
+        assert!(!undo.is_capture());
+
+        // Unstack the Paladin back
+        board[81] = 1;
+        make_move(&mut board, encode(42, 40, true)).unwrap();
+        assert_eq!(board[40], 0b1000100);
+        assert_eq!(board[42], 0b1000101);
+    }
+
+    #[test]
+    fn test_capture_of_stack_and_king() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = 0b1000011; // White Commander
+        board[44] = 0b0010110; // Black Jester+Dragon
+        board[4] = 0b0111000; // Black King
+
+        let undo = make_move(&mut board, encode(40, 44, false)).unwrap();
+        assert!(undo.is_capture());
+        assert!(!undo.captured_king());
+        assert_eq!(board[44], 0b1000011);
+
+        board[81] = 1;
+        board[13] = 0b1000011;
+        let undo = make_move(&mut board, encode(13, 4, false)).unwrap();
+        assert!(undo.captured_king());
+    }
+
+    #[test]
+    fn test_invalid_moves_leave_board_untouched() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = 0b1010100; // White Jester+Paladin
+        board[42] = 0b1000101; // White Guard
+        let original = board;
+
+        assert!(make_move(&mut board, encode(0, 1, false)).is_err());
+        assert!(make_move(&mut board, encode(40, 42, false)).is_err());
+        assert_eq!(board, original);
+    }
+}
@@ -0,0 +1,521 @@
+//! Small NNUE-style neural network evaluator (CPU inference)
+//!
+//! The network reads sparse per-square features of the board and outputs a score in
+//! hundredths of a Soldier, like [`super::evaluate`]. Its first layer is kept as an
+//! [`Accumulator`] that can be updated incrementally after each
+//! [`make_move`](super::make_move) instead of being recomputed from scratch.
+//!
+//! # Architecture
+//!
+//! - Inputs: 30 planes of 81 squares plus one side-to-move input ([`INPUTS`] in total).
+//!   Each colour has one plane for the King, seven planes for single pieces or stack
+//!   bottoms (by piece code) and seven planes for stack tops, so a stack activates two
+//!   features on its square.
+//! - Accumulator: `hidden1` neurons, clipped ReLU (0..1)
+//! - Hidden layer: `hidden2` neurons, clipped ReLU (0..1)
+//! - Output: one neuron, scaled by [`OUTPUT_SCALE`], from white's perspective
+//!
+//! # File format (version 1)
+//!
+//! Little-endian binary: the magic bytes `ARXN`, then `u32` version, input count,
+//! `hidden1` and `hidden2`, followed by `f32` arrays: input weights (feature-major,
+//! `inputs × hidden1`), input biases, hidden weights (`hidden1 × hidden2`), hidden
+//! biases, output weights (`hidden2`) and the output bias.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{make_move, nnue::NnueNetwork};
+//!
+//! let network = NnueNetwork::load("network.arxn").expect("Failed to load network");
+//! let mut board = arx_engine::Game::new().to_binary();
+//! let mut accumulator = network.refresh(&board);
+//!
+//! let undo = make_move(&mut board, 46 << 7 | 54).unwrap();
+//! accumulator.apply_move(&network, &undo, &board);
+//! println!("Score: {}", network.evaluate_accumulator(&accumulator, &board));
+//! ```
+
+use super::move_ops::MoveUndo;
+use super::tuning::LabelledPosition;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::fmt;
+use std::path::Path;
+
+const BOARD_SIZE: usize = 81;
+const KING_PAYLOAD: u8 = 0x38;
+const PLANES_PER_COLOR: usize = 15;
+
+/// Number of network inputs (30 planes of 81 squares plus the side to move)
+pub const INPUTS: usize = 2 * PLANES_PER_COLOR * BOARD_SIZE + 1;
+/// Index of the input active when white is to move
+pub const SIDE_TO_MOVE_INPUT: usize = INPUTS - 1;
+/// Factor converting the raw network output to hundredths of a Soldier
+pub const OUTPUT_SCALE: f32 = 400.0;
+
+const MAGIC: &[u8; 4] = b"ARXN";
+const FORMAT_VERSION: u32 = 1;
+
+/// Input features activated by a piece code on a square (up to two for a stack)
+fn square_features(square: usize, code: u8) -> impl Iterator<Item = usize> {
+    let color = ((code >> 6) & 1) as usize;
+    let payload = code & 0x3F;
+    let base = color * PLANES_PER_COLOR;
+
+    let (bottom, top) = if code == 0 {
+        (None, None)
+    } else if payload == KING_PAYLOAD {
+        (Some(0), None)
+    } else {
+        let top_code = ((payload >> 3) & 0x07) as usize;
+        let bottom_code = (payload & 0x07) as usize;
+        (Some(bottom_code), (top_code != 0).then_some(7 + top_code))
+    };
+
+    bottom
+        .into_iter()
+        .chain(top)
+        .map(move |plane| (base + plane) * BOARD_SIZE + square)
+}
+
+/// Active input features of a board
+pub fn active_features(board: &[u8; 82]) -> Vec<usize> {
+    let mut features: Vec<usize> = board
+        .iter()
+        .take(BOARD_SIZE)
+        .enumerate()
+        .flat_map(|(square, &code)| square_features(square, code))
+        .collect();
+    if board[BOARD_SIZE] == 1 {
+        features.push(SIDE_TO_MOVE_INPUT);
+    }
+    features
+}
+
+fn clipped_relu(x: f32) -> f32 {
+    x.clamp(0.0, 1.0)
+}
+
+/// First layer pre-activations, updated incrementally as moves are played
+#[derive(Clone, Debug, PartialEq)]
+pub struct Accumulator {
+    values: Vec<f32>,
+}
+
+impl Accumulator {
+    fn add_feature(&mut self, network: &NnueNetwork, feature: usize) {
+        let weights = &network.input_weights[feature * network.hidden1..(feature + 1) * network.hidden1];
+        for (value, weight) in self.values.iter_mut().zip(weights) {
+            *value += weight;
+        }
+    }
+
+    fn remove_feature(&mut self, network: &NnueNetwork, feature: usize) {
+        let weights = &network.input_weights[feature * network.hidden1..(feature + 1) * network.hidden1];
+        for (value, weight) in self.values.iter_mut().zip(weights) {
+            *value -= weight;
+        }
+    }
+
+    fn change_square(&mut self, network: &NnueNetwork, square: usize, old: u8, new: u8) {
+        if old == new {
+            return;
+        }
+        for feature in square_features(square, old) {
+            self.remove_feature(network, feature);
+        }
+        for feature in square_features(square, new) {
+            self.add_feature(network, feature);
+        }
+    }
+
+    fn toggle_side_to_move(&mut self, network: &NnueNetwork, white_to_move: bool) {
+        if white_to_move {
+            self.add_feature(network, SIDE_TO_MOVE_INPUT);
+        } else {
+            self.remove_feature(network, SIDE_TO_MOVE_INPUT);
+        }
+    }
+
+    /// Update after `make_move` (`board` is the position after the move)
+    pub fn apply_move(&mut self, network: &NnueNetwork, undo: &MoveUndo, board: &[u8; 82]) {
+        let (from, to) = (undo.from as usize, undo.to as usize);
+        self.change_square(network, from, undo.from_before, board[from]);
+        self.change_square(network, to, undo.to_before, board[to]);
+        self.toggle_side_to_move(network, board[BOARD_SIZE] == 1);
+    }
+
+    /// Revert a move, to be called before `unmake_move` (`board` is still the position after the move)
+    pub fn revert_move(&mut self, network: &NnueNetwork, undo: &MoveUndo, board: &[u8; 82]) {
+        let (from, to) = (undo.from as usize, undo.to as usize);
+        self.change_square(network, from, board[from], undo.from_before);
+        self.change_square(network, to, board[to], undo.to_before);
+        self.toggle_side_to_move(network, board[BOARD_SIZE] != 1);
+    }
+}
+
+/// Neural network evaluator
+#[derive(Clone, PartialEq)]
+pub struct NnueNetwork {
+    hidden1: usize,
+    hidden2: usize,
+    input_weights: Vec<f32>,
+    input_biases: Vec<f32>,
+    hidden_weights: Vec<f32>,
+    hidden_biases: Vec<f32>,
+    output_weights: Vec<f32>,
+    output_bias: f32,
+}
+
+impl fmt::Debug for NnueNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "NnueNetwork({}x{}x{}x1)", INPUTS, self.hidden1, self.hidden2)
+    }
+}
+
+/// Intermediate activations kept for training
+struct Activations {
+    accumulator: Vec<f32>,
+    hidden: Vec<f32>,
+    output: f32,
+}
+
+impl NnueNetwork {
+    /// Create a network with small random weights
+    pub fn new(hidden1: usize, hidden2: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        // Uniform Glorot initialization; only a few dozen inputs are active at once,
+        // so the first layer is scaled as if it had 64 inputs
+        let mut layer = |fan_in: usize, fan_out: usize, count: usize| -> Vec<f32> {
+            let limit = (6.0 / (fan_in + fan_out) as f32).sqrt();
+            (0..count).map(|_| rng.gen_range(-limit..limit)).collect()
+        };
+
+        Self {
+            hidden1,
+            hidden2,
+            input_weights: layer(64, hidden1, INPUTS * hidden1),
+            input_biases: vec![0.5; hidden1],
+            hidden_weights: layer(hidden1, hidden2, hidden1 * hidden2),
+            hidden_biases: vec![0.0; hidden2],
+            output_weights: layer(hidden2, 1, hidden2),
+            output_bias: 0.0,
+        }
+    }
+
+    /// Size of the accumulator and of the second hidden layer
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.hidden1, self.hidden2)
+    }
+
+    /// Compute the accumulator of a board from scratch
+    pub fn refresh(&self, board: &[u8; 82]) -> Accumulator {
+        let mut accumulator = Accumulator {
+            values: self.input_biases.clone(),
+        };
+        for feature in active_features(board) {
+            accumulator.add_feature(self, feature);
+        }
+        accumulator
+    }
+
+    fn forward(&self, accumulator: &[f32]) -> Activations {
+        let accumulator: Vec<f32> = accumulator.iter().map(|&x| clipped_relu(x)).collect();
+        let mut hidden = self.hidden_biases.clone();
+        for (input, weights) in accumulator.iter().zip(self.hidden_weights.chunks(self.hidden2)) {
+            if *input == 0.0 {
+                continue;
+            }
+            for (value, weight) in hidden.iter_mut().zip(weights) {
+                *value += input * weight;
+            }
+        }
+        let hidden: Vec<f32> = hidden.into_iter().map(clipped_relu).collect();
+        let output = self.output_bias
+            + hidden
+                .iter()
+                .zip(&self.output_weights)
+                .map(|(h, w)| h * w)
+                .sum::<f32>();
+        Activations {
+            accumulator,
+            hidden,
+            output,
+        }
+    }
+
+    /// Score from white's perspective, in hundredths of a Soldier
+    pub fn score_white(&self, accumulator: &Accumulator) -> i32 {
+        (self.forward(&accumulator.values).output * OUTPUT_SCALE).round() as i32
+    }
+
+    /// Score from the perspective of the player to move, using an up-to-date accumulator
+    pub fn evaluate_accumulator(&self, accumulator: &Accumulator, board: &[u8; 82]) -> i32 {
+        let score = self.score_white(accumulator);
+        if board[BOARD_SIZE] == 1 {
+            score
+        } else {
+            -score
+        }
+    }
+
+    /// Evaluate a board from scratch
+    /// Positive values favor the player to move
+    pub fn evaluate(&self, board: &[u8; 82]) -> i32 {
+        self.evaluate_accumulator(&self.refresh(board), board)
+    }
+
+    /// Train on labelled positions with plain SGD, calling `on_epoch` with each epoch's mean loss.
+    /// The predicted result is `1 / (1 + 10^(-score / 400))`, as in Texel tuning with k = 1.
+    pub fn train(
+        &mut self,
+        positions: &[LabelledPosition],
+        epochs: u32,
+        learning_rate: f32,
+        seed: u64,
+        mut on_epoch: impl FnMut(u32, f64),
+    ) {
+        let samples: Vec<(Vec<usize>, f32)> = positions
+            .iter()
+            .map(|p| (active_features(&p.board), p.result as f32))
+            .collect();
+        let mut order: Vec<usize> = (0..samples.len()).collect();
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        for epoch in 1..=epochs {
+            order.shuffle(&mut rng);
+            let mut total_loss = 0.0;
+            for &index in &order {
+                let (features, result) = &samples[index];
+                total_loss += self.train_sample(features, *result, learning_rate) as f64;
+            }
+            on_epoch(epoch, total_loss / samples.len().max(1) as f64);
+        }
+    }
+
+    /// One SGD step on a single sample, returns its loss before the update
+    fn train_sample(&mut self, features: &[usize], result: f32, learning_rate: f32) -> f32 {
+        let mut pre_activation = self.input_biases.clone();
+        for &feature in features {
+            let weights = &self.input_weights[feature * self.hidden1..(feature + 1) * self.hidden1];
+            for (value, weight) in pre_activation.iter_mut().zip(weights) {
+                *value += weight;
+            }
+        }
+        let activations = self.forward(&pre_activation);
+
+        // score / 400 = raw output, so the logistic exponent is simply the raw output
+        let predicted = 1.0 / (1.0 + 10f32.powf(-activations.output));
+        let loss = (predicted - result).powi(2);
+        let output_gradient = 2.0 * (predicted - result) * predicted * (1.0 - predicted) * std::f32::consts::LN_10;
+
+        // Output layer
+        let mut hidden_gradient = vec![0.0; self.hidden2];
+        for (j, gradient) in hidden_gradient.iter_mut().enumerate() {
+            let h = activations.hidden[j];
+            if h > 0.0 && h < 1.0 {
+                *gradient = output_gradient * self.output_weights[j];
+            }
+            self.output_weights[j] -= learning_rate * output_gradient * h;
+        }
+        self.output_bias -= learning_rate * output_gradient;
+
+        // Hidden layer
+        let mut accumulator_gradient = vec![0.0; self.hidden1];
+        for (i, gradient) in accumulator_gradient.iter_mut().enumerate() {
+            let a = activations.accumulator[i];
+            let weights = &mut self.hidden_weights[i * self.hidden2..(i + 1) * self.hidden2];
+            if pre_activation[i] > 0.0 && pre_activation[i] < 1.0 {
+                *gradient = weights.iter().zip(&hidden_gradient).map(|(w, g)| w * g).sum();
+            }
+            for (weight, g) in weights.iter_mut().zip(&hidden_gradient) {
+                *weight -= learning_rate * g * a;
+            }
+        }
+        for (bias, g) in self.hidden_biases.iter_mut().zip(&hidden_gradient) {
+            *bias -= learning_rate * g;
+        }
+
+        // Input layer (only active features receive a gradient)
+        for &feature in features {
+            let weights = &mut self.input_weights[feature * self.hidden1..(feature + 1) * self.hidden1];
+            for (weight, g) in weights.iter_mut().zip(&accumulator_gradient) {
+                *weight -= learning_rate * g;
+            }
+        }
+        for (bias, g) in self.input_biases.iter_mut().zip(&accumulator_gradient) {
+            *bias -= learning_rate * g;
+        }
+
+        loss
+    }
+
+    /// Serialize the network (see the module documentation for the format)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(20 + 4 * (self.input_weights.len() + self.hidden_weights.len()));
+        bytes.extend_from_slice(MAGIC);
+        for value in [FORMAT_VERSION, INPUTS as u32, self.hidden1 as u32, self.hidden2 as u32] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        let arrays = [
+            &self.input_weights,
+            &self.input_biases,
+            &self.hidden_weights,
+            &self.hidden_biases,
+            &self.output_weights,
+        ];
+        for value in arrays.into_iter().flatten().chain([&self.output_bias]) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Deserialize a network, checking the magic bytes, version and dimensions
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 20 || &bytes[0..4] != MAGIC {
+            return Err("Not an Arx network file".to_string());
+        }
+        let header = |index: usize| {
+            let offset = 4 + index * 4;
+            u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap()) as usize
+        };
+        let version = header(0) as u32;
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported network version {} (expected {})",
+                version, FORMAT_VERSION
+            ));
+        }
+        if header(1) != INPUTS {
+            return Err(format!("Network has {} inputs, expected {}", header(1), INPUTS));
+        }
+        let (hidden1, hidden2) = (header(2), header(3));
+
+        // Dimensions come from the file: a size that does not fit is invalid
+        let weight_bytes = || {
+            INPUTS
+                .checked_mul(hidden1)?
+                .checked_add(hidden1)?
+                .checked_add(hidden1.checked_mul(hidden2)?)?
+                .checked_add(hidden2)?
+                .checked_add(hidden2)?
+                .checked_add(1)?
+                .checked_mul(4)
+        };
+        let expected = weight_bytes().ok_or("Invalid network dimensions")?;
+        let data = &bytes[20..];
+        if data.len() != expected {
+            return Err(format!(
+                "Invalid network size: expected {} bytes of weights, got {}",
+                expected,
+                data.len()
+            ));
+        }
+        let mut values = data
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()));
+        let mut take = |count: usize| values.by_ref().take(count).collect::<Vec<f32>>();
+
+        Ok(Self {
+            hidden1,
+            hidden2,
+            input_weights: take(INPUTS * hidden1),
+            input_biases: take(hidden1),
+            hidden_weights: take(hidden1 * hidden2),
+            hidden_biases: take(hidden2),
+            output_weights: take(hidden2),
+            output_bias: take(1)[0],
+        })
+    }
+
+    /// Load a network from a file
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read network file {}: {}", path.display(), e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Save the network to a file
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        std::fs::write(path, self.to_bytes())
+            .map_err(|e| format!("Failed to write network file {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::cpu_move_gen::generate_moves;
+    use crate::engine::{make_move, unmake_move};
+    use crate::Game;
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let network = NnueNetwork::new(32, 16, 1);
+        let mut board = Game::new().to_binary();
+        let mut accumulator = network.refresh(&board);
+        let mut history = Vec::new();
+
+        for ply in 0..20 {
+            let moves = generate_moves(&board);
+            let undo = make_move(&mut board, moves[(ply * 13) % moves.len()]).unwrap();
+            accumulator.apply_move(&network, &undo, &board);
+            history.push(undo);
+
+            let fresh = network.refresh(&board);
+            for (a, b) in accumulator.values.iter().zip(&fresh.values) {
+                assert!((a - b).abs() < 1e-4, "ply {}", ply);
+            }
+        }
+
+        let original = Game::new().to_binary();
+        while let Some(undo) = history.pop() {
+            accumulator.revert_move(&network, &undo, &board);
+            unmake_move(&mut board, &undo);
+        }
+        assert_eq!(board, original);
+        assert_eq!(network.evaluate_accumulator(&accumulator, &board), network.evaluate(&board));
+    }
+
+    #[test]
+    fn test_file_round_trip_and_validation() {
+        let network = NnueNetwork::new(8, 4, 2);
+        let bytes = network.to_bytes();
+        assert_eq!(NnueNetwork::from_bytes(&bytes).unwrap(), network);
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[4] = 99;
+        assert!(NnueNetwork::from_bytes(&wrong_version).unwrap_err().contains("version"));
+        assert!(NnueNetwork::from_bytes(&bytes[..bytes.len() - 4]).is_err());
+        assert!(NnueNetwork::from_bytes(b"ARXW").is_err());
+
+        // Dimensions whose weight count overflows are rejected, not wrapped
+        let mut huge = bytes[..20].to_vec();
+        huge[12..20].fill(0xFF);
+        assert_eq!(NnueNetwork::from_bytes(&huge).unwrap_err(), "Invalid network dimensions");
+    }
+
+    #[test]
+    fn test_training_reduces_loss() {
+        // White is ahead by the number of missing black soldiers and wins those games
+        let positions: Vec<LabelledPosition> = (0..8)
+            .map(|count| {
+                let mut board = Game::new().to_binary();
+                board[18..18 + count].fill(0);
+                LabelledPosition {
+                    board,
+                    result: if count >= 4 { 1.0 } else { 0.0 },
+                }
+            })
+            .collect();
+
+        let mut network = NnueNetwork::new(16, 8, 3);
+        let mut losses = Vec::new();
+        network.train(&positions, 30, 0.05, 4, |_, loss| losses.push(loss));
+        assert!(losses.last().unwrap() < losses.first().unwrap());
+    }
+}
@@ -0,0 +1,281 @@
+//! Shared-tree parallel search
+//!
+//! With [`Parallelism::Root`] every root move is simulated independently on the
+//! rayon pool, so a position with five legal moves keeps at most five threads busy.
+//! [`Parallelism::Tree`] instead lets `search_threads` workers descend a single
+//! [`SharedTree`] with UCB1 (or PUCT when nodes have priors, see [`super::priors`]),
+//! expanding one node per simulation and running a CPU rollout from it.
+//!
+//! # Concurrency
+//!
+//! Nodes are keyed by Zobrist hash in a table split into shards, each behind its own
+//! mutex that is only held to look up or insert a node. Edge statistics are atomics
+//! updated without locks. While a simulation is in flight, every edge on its path
+//! carries a virtual loss: it counts as a visit scoring `-virtual_loss`, which steers
+//! concurrent workers towards other lines until the real result is backed up.
+//!
+//! # Scores
+//!
+//! Edges store the sum of the simulation scores from the perspective of the player
+//! moving at the node, like [`RootMoveStats`]. UCB1 works on win-rate-like values, so
+//! mean scores go through a logistic curve before being compared.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{parallel::Parallelism, EngineConfig, MctsEngine};
+//!
+//! let config = EngineConfig {
+//!     parallelism: Parallelism::Tree,
+//!     search_threads: 8,
+//!     use_gpu_simulation: false,
+//!     ..EngineConfig::default()
+//! };
+//! let mut engine = MctsEngine::with_config(config).expect("Failed to create engine");
+//! ```
+
+use super::priors::{self, Widening};
+use super::RootMoveStats;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicI64, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Number of independently locked parts of the node table
+const SHARDS: usize = 64;
+
+/// Score difference that moves the logistic value from 0.5 to about 0.73
+const SCORE_SCALE: f64 = 400.0;
+
+/// How the search spreads its simulations over threads
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Parallelism {
+    /// One rayon task per root move (GPU batches when available)
+    #[default]
+    Root,
+    /// All threads descend a shared tree with virtual loss (CPU rollouts)
+    Tree,
+}
+
+impl FromStr for Parallelism {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "root" => Ok(Parallelism::Root),
+            "tree" => Ok(Parallelism::Tree),
+            _ => Err(format!("Unknown parallelism '{}' (expected root or tree)", name)),
+        }
+    }
+}
+
+/// Statistics of one move of a node
+#[derive(Debug)]
+struct Edge {
+    mv: u16,
+    visits: AtomicU32,
+    virtual_loss: AtomicU32,
+    total_score: AtomicI64,
+}
+
+/// Logistic value of a mean score, between 0 and 1
+pub(crate) fn win_rate(mean: f64) -> f64 {
+    1.0 / (1.0 + (-mean / SCORE_SCALE).exp())
+}
+
+/// Expanded position
+#[derive(Debug)]
+pub struct Node {
+    edges: Vec<Edge>,
+    /// Prior of each edge, when selecting with PUCT
+    priors: Option<Vec<f64>>,
+    /// Edge indices by decreasing prior, the order in which widening admits them
+    order: Vec<usize>,
+}
+
+impl Node {
+    fn new(moves: &[u16], priors: Option<Vec<f64>>) -> Self {
+        let edges = moves
+            .iter()
+            .map(|&mv| Edge {
+                mv,
+                visits: AtomicU32::new(0),
+                virtual_loss: AtomicU32::new(0),
+                total_score: AtomicI64::new(0),
+            })
+            .collect();
+        let order = match &priors {
+            Some(priors) => priors::ranking(priors),
+            None => (0..moves.len()).collect(),
+        };
+        Self { edges, priors, order }
+    }
+
+    /// Pick the edge with the best UCB1 value (unvisited edges first), or the best PUCT
+    /// value if the node has priors, among the edges admitted by `widening`, and put a
+    /// virtual loss on it. Returns the edge index and its move, or None without moves.
+    pub fn select(&self, exploration: f64, virtual_loss: i64, widening: Option<Widening>) -> Option<(usize, u16)> {
+        let counts: Vec<(u32, u32, i64)> = self
+            .edges
+            .iter()
+            .map(|edge| {
+                (
+                    edge.visits.load(Ordering::Relaxed),
+                    edge.virtual_loss.load(Ordering::Relaxed),
+                    edge.total_score.load(Ordering::Relaxed),
+                )
+            })
+            .collect();
+        let parent_visits: u32 = counts.iter().map(|&(visits, pending, _)| visits + pending).sum();
+        let log_parent = (parent_visits.max(1) as f64).ln();
+
+        let considered = widening.map_or(self.order.len(), |widening| {
+            widening.children(parent_visits, self.order.len())
+        });
+
+        let mut best: Option<(usize, f64)> = None;
+        for &index in &self.order[..considered] {
+            let (visits, pending, total) = counts[index];
+            let n = visits + pending;
+            let total = total - pending as i64 * virtual_loss;
+            let value = match &self.priors {
+                Some(priors) => priors::puct(total, n, priors[index], parent_visits, exploration),
+                None if n == 0 => f64::INFINITY,
+                None => win_rate(total as f64 / n as f64) + exploration * (log_parent / n as f64).sqrt(),
+            };
+            if best.is_none_or(|(_, best_value)| value > best_value) {
+                best = Some((index, value));
+            }
+        }
+
+        let (index, _) = best?;
+        let edge = &self.edges[index];
+        edge.virtual_loss.fetch_add(1, Ordering::Relaxed);
+        Some((index, edge.mv))
+    }
+
+    /// Replace the virtual loss of an edge by a simulation result
+    pub fn backup(&self, index: usize, score: i32) {
+        let edge = &self.edges[index];
+        edge.total_score.fetch_add(score as i64, Ordering::Relaxed);
+        edge.visits.fetch_add(1, Ordering::Relaxed);
+        edge.virtual_loss.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Current statistics of every move
+    pub fn stats(&self) -> Vec<RootMoveStats> {
+        self.edges
+            .iter()
+            .map(|edge| RootMoveStats {
+                mv: edge.mv,
+                visits: edge.visits.load(Ordering::Relaxed),
+                total_score: edge.total_score.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// Nodes of a tree-parallel search, keyed by Zobrist hash
+#[derive(Debug)]
+pub struct SharedTree {
+    shards: Vec<Mutex<HashMap<u64, Arc<Node>>>>,
+    len: AtomicUsize,
+    capacity: usize,
+}
+
+impl SharedTree {
+    /// Empty tree holding at most `capacity` nodes
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+            len: AtomicUsize::new(0),
+            capacity,
+        }
+    }
+
+    /// Number of expanded nodes
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn shard(&self, hash: u64) -> &Mutex<HashMap<u64, Arc<Node>>> {
+        &self.shards[(hash % SHARDS as u64) as usize]
+    }
+
+    /// Node of a position, if it was expanded
+    pub fn get(&self, hash: u64) -> Option<Arc<Node>> {
+        self.shard(hash).lock().unwrap().get(&hash).cloned()
+    }
+
+    /// Expand a position with its legal moves and their priors (None selects with UCB1).
+    /// Returns false when the tree is full; a position expanded concurrently by another
+    /// worker is kept as is.
+    pub fn expand(&self, hash: u64, moves: &[u16], priors: Option<Vec<f64>>) -> bool {
+        let mut shard = self.shard(hash).lock().unwrap();
+        if shard.contains_key(&hash) {
+            return true;
+        }
+        if self.len.load(Ordering::Relaxed) >= self.capacity {
+            return false;
+        }
+        shard.insert(hash, Arc::new(Node::new(moves, priors)));
+        self.len.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_virtual_loss_spreads_selection() {
+        let tree = SharedTree::new(1);
+        assert!(tree.expand(7, &[10, 11, 12], None));
+        assert!(!tree.expand(8, &[10], None)); // Over capacity
+        assert!(tree.expand(7, &[], None)); // Already expanded
+        let node = tree.get(7).unwrap();
+
+        // Concurrent selections without results go to different moves
+        let first = node.select(1.414, 1000, None).unwrap();
+        let second = node.select(1.414, 1000, None).unwrap();
+        let third = node.select(1.414, 1000, None).unwrap();
+        let mut picked = vec![first.1, second.1, third.1];
+        picked.sort_unstable();
+        assert_eq!(picked, vec![10, 11, 12]);
+
+        node.backup(first.0, 500);
+        node.backup(second.0, -500);
+        node.backup(third.0, -500);
+        let stats = node.stats();
+        assert_eq!(stats.iter().map(|stats| stats.visits).sum::<u32>(), 3);
+        assert_eq!(stats[first.0].total_score, 500);
+
+        // Without exploration the winning move is preferred, unless it is busy
+        assert_eq!(node.select(0.0, 5000, None).unwrap().1, first.1);
+        assert_ne!(node.select(0.0, 5000, None).unwrap().1, first.1);
+    }
+
+    #[test]
+    fn test_priors_and_widening_guide_selection() {
+        let tree = SharedTree::new(1);
+        assert!(tree.expand(7, &[10, 11, 12], Some(vec![0.1, 0.2, 0.7])));
+        let node = tree.get(7).unwrap();
+
+        // A single admitted edge: the one with the best prior, whatever its results
+        let narrow = Widening { constant: 1.0, exponent: 0.0 };
+        for _ in 0..3 {
+            let (index, mv) = node.select(1.414, 1000, Some(narrow)).unwrap();
+            assert_eq!(mv, 12);
+            node.backup(index, -500);
+        }
+
+        // Once admitted, a poorly scoring edge gives way to the others
+        let (_, mv) = node.select(1.414, 0, None).unwrap();
+        assert_eq!(mv, 11);
+    }
+}
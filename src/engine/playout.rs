@@ -0,0 +1,231 @@
+//! Rollout move selection
+//!
+//! Light playouts pick every rollout move uniformly at random. Heavy playouts follow
+//! a simple policy instead, so rollouts look more like real games:
+//!
+//! - a move capturing the enemy King is always played;
+//! - otherwise each move is drawn with an integer weight: `base`, plus `capture` per
+//!   100 evaluation units of the captured piece or stack, plus `stack` for forming a
+//!   stack, while a King move onto a square the opponent attacks only gets
+//!   `hanging_king`.
+//!
+//! Attacked squares are those the opponent could move to in the position before the
+//! move, so lines opened by the move itself are not considered. The shader
+//! (`batch_simulation.wgsl`) implements the same policy with the same integer
+//! arithmetic and move order, so CPU and GPU heavy playouts are interchangeable.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{playout::Playouts, EngineConfig, MctsEngine};
+//!
+//! let config = EngineConfig {
+//!     playouts: Playouts::Heavy,
+//!     ..EngineConfig::default()
+//! };
+//! let mut engine = MctsEngine::with_config(config).expect("Failed to create engine");
+//! ```
+
+use super::cpu_move_gen;
+use super::eval::EvalWeights;
+use bytemuck::{Pod, Zeroable};
+use std::str::FromStr;
+
+const KING_PAYLOAD: u8 = 0x38;
+
+/// Rollout move selection policy
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Playouts {
+    /// Uniformly random moves
+    #[default]
+    Light,
+    /// Moves weighted by [`PlayoutWeights`]
+    Heavy,
+}
+
+impl FromStr for Playouts {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "light" => Ok(Playouts::Light),
+            "heavy" => Ok(Playouts::Heavy),
+            _ => Err(format!("Unknown playouts '{}' (expected light or heavy)", name)),
+        }
+    }
+}
+
+/// Move weights of heavy playouts
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayoutWeights {
+    /// Weight of every move
+    pub base: u32,
+    /// Extra weight per 100 evaluation units of captured material
+    pub capture: u32,
+    /// Extra weight for stacking onto a friendly piece
+    pub stack: u32,
+    /// Weight of a King move onto an attacked square (replaces the others)
+    pub hanging_king: u32,
+}
+
+impl Default for PlayoutWeights {
+    fn default() -> Self {
+        Self {
+            base: 10,
+            capture: 10,
+            stack: 10,
+            hanging_king: 1,
+        }
+    }
+}
+
+impl PlayoutWeights {
+    /// Pick a heavy playout move among `moves` (the side to move's moves, in generation
+    /// order). `random(bound)` must return a number below `bound`; it is called once,
+    /// unless no move has a weight, in which case None is returned.
+    pub fn choose(
+        &self,
+        board: &[u8; 82],
+        moves: &[u16],
+        eval: &EvalWeights,
+        random: impl FnOnce(u32) -> u32,
+    ) -> Option<u16> {
+        let weights = self.move_weights(board, moves, eval);
+        let total: u32 = weights.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = random(total);
+        for (&mv, &weight) in moves.iter().zip(&weights) {
+            if pick < weight {
+                return Some(mv);
+            }
+            pick -= weight;
+        }
+        None
+    }
+
+    /// Draw weight of each of `moves`. When the enemy King can be taken, its captures
+    /// weigh 1 and every other move 0.
+    pub fn move_weights(&self, board: &[u8; 82], moves: &[u16], eval: &EvalWeights) -> Vec<u32> {
+        if moves.iter().any(|&mv| captures_king(board, mv)) {
+            return moves.iter().map(|&mv| captures_king(board, mv) as u32).collect();
+        }
+
+        let mut attacked = [false; 81];
+        cpu_move_gen::for_each_move(board, board[81] != 1, |mv| attacked[((mv >> 7) & 0x7F) as usize] = true);
+        moves.iter().map(|&mv| self.weight(board, mv, &attacked, eval)).collect()
+    }
+
+    /// Weight of a move that does not capture the King
+    fn weight(&self, board: &[u8; 82], mv: u16, attacked: &[bool; 81], eval: &EvalWeights) -> u32 {
+        let from = (mv & 0x7F) as usize;
+        let to = ((mv >> 7) & 0x7F) as usize;
+        let piece = board[from];
+        if piece & 0x3F == KING_PAYLOAD && attacked[to] {
+            return self.hanging_king;
+        }
+
+        let target = board[to];
+        if target == 0 {
+            self.base
+        } else if (target ^ piece) & 0x40 != 0 {
+            let value = eval.piece_values[(target & 7) as usize] + eval.piece_values[((target >> 3) & 7) as usize];
+            self.base + self.capture * value.max(0) as u32 / 100
+        } else {
+            self.base + self.stack
+        }
+    }
+
+    /// Uniform buffer layout used by the batch simulation shader
+    pub(crate) fn to_gpu(self, playouts: Playouts) -> GpuPlayoutWeights {
+        GpuPlayoutWeights {
+            heavy: (playouts == Playouts::Heavy) as u32,
+            base: self.base,
+            capture: self.capture,
+            stack: self.stack,
+            hanging_king: self.hanging_king,
+            _padding: [0; 3],
+        }
+    }
+}
+
+/// `PlayoutWeights` and the playout switch as laid out in the shader's uniform buffer
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub(crate) struct GpuPlayoutWeights {
+    heavy: u32,
+    base: u32,
+    capture: u32,
+    stack: u32,
+    hanging_king: u32,
+    _padding: [u32; 3],
+}
+
+/// Whether a move takes the enemy King
+fn captures_king(board: &[u8; 82], mv: u16) -> bool {
+    let target = board[((mv >> 7) & 0x7F) as usize];
+    target != 0 && target & 0x3F == KING_PAYLOAD && (target ^ board[(mv & 0x7F) as usize]) & 0x40 != 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: usize, y: usize) -> usize {
+        y * 9 + x
+    }
+
+    fn encode(from: usize, to: usize) -> u16 {
+        ((to as u16) << 7) | from as u16
+    }
+
+    #[test]
+    fn test_king_capture_is_always_played() {
+        let mut board = [0u8; 82];
+        board[square(4, 4)] = 0x40 | 3; // White Commander
+        board[square(4, 0)] = KING_PAYLOAD; // Black King in its line
+        board[square(0, 8)] = 0x40 | KING_PAYLOAD;
+        board[square(0, 4)] = 1; // Black Soldier
+        board[81] = 1;
+
+        let moves = cpu_move_gen::generate_moves(&board);
+        let chosen = PlayoutWeights::default().choose(&board, &moves, &EvalWeights::default(), |bound| {
+            assert_eq!(bound, 1); // The only King capture, whatever the other weights
+            0
+        });
+        assert_eq!(chosen, Some(encode(square(4, 4), square(4, 0))));
+    }
+
+    #[test]
+    fn test_move_weights() {
+        let mut board = [0u8; 82];
+        board[square(4, 4)] = 0x40 | KING_PAYLOAD; // White King
+        board[square(4, 3)] = 3; // Black Commander, capturable
+        board[square(3, 6)] = 0x40 | 1; // White Soldier
+        board[square(2, 6)] = 0x40 | 4; // White Paladin, can stack onto it
+        board[square(6, 2)] = 2; // Black Jester covering (5, 3)
+        board[square(0, 0)] = KING_PAYLOAD;
+        board[81] = 1;
+
+        let weights = PlayoutWeights::default();
+        let eval = EvalWeights::default();
+        let mut attacked = [false; 81];
+        cpu_move_gen::for_each_move(&board, false, |mv| attacked[((mv >> 7) & 0x7F) as usize] = true);
+        let weight = |to: usize| weights.weight(&board, encode(square(4, 4), to), &attacked, &eval);
+
+        assert_eq!(weight(square(4, 3)), 10 + 10 * 500 / 100); // Captures the Commander
+        assert_eq!(weights.weight(&board, encode(square(2, 6), square(3, 6)), &attacked, &eval), 10 + 10);
+        assert_eq!(weight(square(5, 3)), 1); // Walks into the Jester
+        assert_eq!(weight(square(3, 4)), 10);
+
+        // Draws follow the weights: the Commander capture takes the bulk of them
+        let moves = cpu_move_gen::generate_moves(&board);
+        let total: u32 = moves.iter().map(|&mv| weights.weight(&board, mv, &attacked, &eval)).sum();
+        let capture = encode(square(4, 4), square(4, 3));
+        let picks = (0..total)
+            .filter(|&pick| weights.choose(&board, &moves, &eval, |_| pick) == Some(capture))
+            .count();
+        assert_eq!(picks, 60);
+    }
+}
@@ -0,0 +1,248 @@
+//! Move priors, PUCT selection and progressive widening
+//!
+//! Stacks of sliding pieces give Arx a large, flat branching factor. Splitting
+//! `simulations_per_move` evenly over every root move spends most of the budget on
+//! moves nobody would play, so the engine can instead give each move a prior
+//! probability and select with PUCT:
+//!
+//! `win_rate + exploration_constant * prior * sqrt(parent_visits) / (1 + visits)`
+//!
+//! where `win_rate` is the mean score through the same logistic curve as UCB1 (0.5
+//! for unvisited moves). [`Priors`] chooses where the probabilities come from.
+//!
+//! # Progressive widening
+//!
+//! With a [`Widening`], a node only considers its `ceil(constant * visits^exponent)`
+//! moves with the highest prior (in generation order for uniform priors); the others
+//! join as the node gathers visits.
+//!
+//! # Root-parallel searches
+//!
+//! When priors or widening are enabled, the root-parallel search spends its budget of
+//! `simulations_per_move` per root move in waves: each wave is split among the root
+//! moves by PUCT, then simulated in parallel (in GPU batches when available), and the
+//! best move is the most visited one. Tree-parallel searches apply PUCT and widening
+//! at every node they expand.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{priors::{Priors, Widening}, EngineConfig, MctsEngine};
+//!
+//! let config = EngineConfig {
+//!     priors: Priors::Heuristic,
+//!     widening: Some(Widening::default()),
+//!     ..EngineConfig::default()
+//! };
+//! let mut engine = MctsEngine::with_config(config).expect("Failed to create engine");
+//! ```
+
+use super::eval::EvalWeights;
+use super::parallel::win_rate;
+use super::playout::PlayoutWeights;
+use super::{make_move, RootMoveStats};
+use std::str::FromStr;
+
+/// Source of the move priors
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Priors {
+    /// No priors: root moves share the budget evenly and tree nodes use UCB1
+    #[default]
+    Uniform,
+    /// Normalized heavy playout weights (see [`PlayoutWeights`])
+    Heuristic,
+    /// Softmax of the evaluation after each move (the network when one is configured)
+    Evaluator,
+}
+
+impl FromStr for Priors {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "uniform" => Ok(Priors::Uniform),
+            "heuristic" => Ok(Priors::Heuristic),
+            "evaluator" => Ok(Priors::Evaluator),
+            _ => Err(format!("Unknown priors '{}' (expected uniform, heuristic or evaluator)", name)),
+        }
+    }
+}
+
+/// Progressive widening schedule
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Widening {
+    /// Moves considered by a node with a single visit
+    pub constant: f64,
+    /// Growth of the considered moves with the node's visits
+    pub exponent: f64,
+}
+
+impl Default for Widening {
+    fn default() -> Self {
+        Self {
+            constant: 2.0,
+            exponent: 0.5,
+        }
+    }
+}
+
+impl Widening {
+    /// Number of moves (at least one, at most `moves`) considered after `visits`
+    pub fn children(&self, visits: u32, moves: usize) -> usize {
+        let children = (self.constant * (visits.max(1) as f64).powf(self.exponent)).ceil();
+        (children as usize).clamp(1, moves.max(1))
+    }
+}
+
+/// Priors from the heavy playout weights of the moves
+pub fn heuristic(board: &[u8; 82], moves: &[u16], weights: &PlayoutWeights, eval: &EvalWeights) -> Vec<f64> {
+    let weights = weights.move_weights(board, moves, eval);
+    normalize(weights.iter().map(|&weight| weight as f64).collect())
+}
+
+/// Priors from a softmax over the evaluation of the position after each move, from the
+/// mover's perspective. `temperature` is in evaluation units; illegal moves get 0.
+pub fn from_evaluation(
+    board: &[u8; 82],
+    moves: &[u16],
+    temperature: f64,
+    evaluate: impl Fn(&[u8; 82]) -> i32,
+) -> Vec<f64> {
+    let scores: Vec<Option<f64>> = moves
+        .iter()
+        .map(|&mv| {
+            let mut child = *board;
+            make_move(&mut child, mv).ok()?;
+            Some(-evaluate(&child) as f64)
+        })
+        .collect();
+    let best = scores.iter().flatten().copied().fold(f64::NEG_INFINITY, f64::max);
+    let temperature = temperature.max(1.0);
+    normalize(
+        scores
+            .iter()
+            .map(|score| score.map_or(0.0, |score| ((score - best) / temperature).exp()))
+            .collect(),
+    )
+}
+
+/// Scale weights to sum to 1 (uniform if they are all 0)
+fn normalize(weights: Vec<f64>) -> Vec<f64> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0.0 {
+        let uniform = 1.0 / weights.len().max(1) as f64;
+        return vec![uniform; weights.len()];
+    }
+    weights.into_iter().map(|weight| weight / total).collect()
+}
+
+/// Move indices by decreasing prior, ties in generation order
+pub fn ranking(priors: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..priors.len()).collect();
+    order.sort_by(|&a, &b| priors[b].partial_cmp(&priors[a]).unwrap_or(std::cmp::Ordering::Equal));
+    order
+}
+
+/// PUCT value of a move with `visits` simulations summing to `total_score`
+pub fn puct(total_score: i64, visits: u32, prior: f64, parent_visits: u32, exploration: f64) -> f64 {
+    let value = if visits == 0 {
+        0.5
+    } else {
+        win_rate(total_score as f64 / visits as f64)
+    };
+    value + exploration * prior * (parent_visits as f64).sqrt() / (1 + visits) as f64
+}
+
+/// Split `simulations` among root moves: each one goes, by PUCT, to a move among those
+/// admitted by `widening`, counting the simulations already handed out at the move's
+/// current mean score. Returns the simulations of each move of `stats`.
+pub fn allocate(
+    stats: &[RootMoveStats],
+    priors: &[f64],
+    simulations: u32,
+    exploration: f64,
+    widening: Option<Widening>,
+) -> Vec<u32> {
+    let order = ranking(priors);
+    let mut counts = vec![0u32; stats.len()];
+    let searched: u32 = stats.iter().map(|stats| stats.visits).sum();
+
+    for handed_out in 0..simulations {
+        let parent_visits = searched + handed_out;
+        let considered = widening.map_or(order.len(), |widening| widening.children(parent_visits, order.len()));
+        let mut best: Option<(usize, f64)> = None;
+        for &index in &order[..considered] {
+            let RootMoveStats { visits, total_score, .. } = stats[index];
+            let pending = counts[index];
+            let total = if visits == 0 { 0 } else { total_score + total_score * pending as i64 / visits as i64 };
+            let value = puct(total, visits + pending, priors[index], parent_visits, exploration);
+            if best.is_none_or(|(_, best_value)| value > best_value) {
+                best = Some((index, value));
+            }
+        }
+        let Some((index, _)) = best else {
+            break;
+        };
+        counts[index] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(mv: u16, visits: u32, total_score: i64) -> RootMoveStats {
+        RootMoveStats { mv, visits, total_score }
+    }
+
+    #[test]
+    fn test_widening_schedule() {
+        let widening = Widening::default();
+        assert_eq!(widening.children(0, 30), 2);
+        assert_eq!(widening.children(4, 30), 4);
+        assert_eq!(widening.children(100, 30), 20);
+        assert_eq!(widening.children(10_000, 30), 30);
+        assert_eq!(widening.children(10_000, 0), 1);
+    }
+
+    #[test]
+    fn test_priors_favor_captures() {
+        let mut board = [0u8; 82];
+        board[40] = 0x40 | 0x38; // White King
+        board[31] = 3; // Black Commander next to it
+        board[0] = 0x38;
+        board[81] = 1;
+        let moves = super::super::cpu_move_gen::generate_moves(&board);
+        let capture = moves.iter().position(|&mv| mv == 40 | (31 << 7)).unwrap();
+
+        for priors in [
+            heuristic(&board, &moves, &PlayoutWeights::default(), &EvalWeights::default()),
+            from_evaluation(&board, &moves, 100.0, |child| super::super::evaluate(child, &EvalWeights::default())),
+        ] {
+            assert!((priors.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+            assert_eq!(ranking(&priors)[0], capture);
+        }
+    }
+
+    #[test]
+    fn test_allocation_concentrates_on_promising_moves() {
+        let root = [stats(1, 0, 0), stats(2, 0, 0), stats(3, 0, 0), stats(4, 0, 0)];
+        let priors = [0.1, 0.6, 0.2, 0.1];
+
+        // Uniform priors without results spread the budget evenly
+        assert_eq!(allocate(&root, &[0.25; 4], 8, 1.414, None), vec![2, 2, 2, 2]);
+
+        // Narrow widening keeps the budget on the moves with the best priors
+        let narrow = Widening { constant: 1.0, exponent: 0.3 };
+        let counts = allocate(&root, &priors, 8, 1.414, Some(narrow));
+        assert_eq!(counts.iter().sum::<u32>(), 8);
+        assert_eq!(counts[0] + counts[3], 0);
+        assert!(counts[1] > counts[2]);
+
+        // Results outweigh priors as visits accumulate
+        let searched = [stats(1, 10, 4000), stats(2, 10, -4000), stats(3, 10, -4000), stats(4, 10, -4000)];
+        let counts = allocate(&searched, &priors, 20, 1.414, None);
+        assert!(counts[0] > counts[1]);
+    }
+}
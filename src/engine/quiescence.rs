@@ -0,0 +1,208 @@
+//! Capture-only quiescence search
+//!
+//! A static evaluation taken in the middle of an exchange counts the piece that was
+//! just captured but not the recapture that is about to follow. [`Quiescence`] keeps
+//! resolving captures until the position is quiet and only scores quiet positions:
+//!
+//! - **Stand pat**: the side to move may decline every capture, so the static score is
+//!   a lower bound and a cutoff when it already reaches beta.
+//! - **Delta pruning**: captures that could not raise alpha even when winning the
+//!   captured material plus `delta_margin` are skipped. Captures are tried from the
+//!   most valuable victim down, so the first pruned capture ends the node.
+//! - Capturing a stack removes both of its pieces, and the victim's value counts both.
+//!   Capturing the King ends the game and scores `king_value`.
+//!
+//! The search is a fail-soft alpha-beta over captures, meant as the leaf of an
+//! alpha-beta search. MCTS rollouts use it to score their last position when
+//! `EngineConfig::quiescence` is set (on the CPU, bypassing GPU batch simulation).
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{evaluate, quiescence::{Quiescence, QuiescenceConfig}, EvalWeights};
+//!
+//! let weights = EvalWeights::default();
+//! let board = arx_engine::Game::new().to_binary();
+//! let mut search = Quiescence::new(QuiescenceConfig::default(), &weights, |board| evaluate(board, &weights));
+//! println!("Quiet score: {} ({} nodes)", search.evaluate(&board), search.nodes());
+//! ```
+
+use super::cpu_move_gen::for_each_move;
+use super::eval::EvalWeights;
+use super::{make_move, unmake_move};
+
+const COLOR_BIT: u8 = 0x40;
+const KING_PAYLOAD: u8 = 0x38;
+
+/// Bound wider than any score
+const INFINITY: i32 = i32::MAX - 1;
+
+/// Quiescence search limits
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuiescenceConfig {
+    /// Longest sequence of captures searched
+    pub max_depth: u32,
+    /// Positional swing allowed on top of the captured material by delta pruning
+    pub delta_margin: i32,
+}
+
+impl Default for QuiescenceConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 8,
+            delta_margin: 200,
+        }
+    }
+}
+
+/// Capture search around a static evaluation function
+pub struct Quiescence<'a, E: Fn(&[u8; 82]) -> i32> {
+    config: QuiescenceConfig,
+    weights: &'a EvalWeights,
+    evaluate: E,
+    nodes: u64,
+}
+
+impl<'a, E: Fn(&[u8; 82]) -> i32> Quiescence<'a, E> {
+    /// Search scoring quiet positions with `evaluate` (from the side to move's
+    /// perspective) and valuing captures with `weights`
+    pub fn new(config: QuiescenceConfig, weights: &'a EvalWeights, evaluate: E) -> Self {
+        Self {
+            config,
+            weights,
+            evaluate,
+            nodes: 0,
+        }
+    }
+
+    /// Positions visited so far
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Quiet score of a position, from the side to move's perspective
+    pub fn evaluate(&mut self, board: &[u8; 82]) -> i32 {
+        let mut board = *board;
+        self.search(&mut board, -INFINITY, INFINITY)
+    }
+
+    /// Fail-soft quiescence search within `(alpha, beta)`. The board is played on in
+    /// place and restored before returning.
+    pub fn search(&mut self, board: &mut [u8; 82], alpha: i32, beta: i32) -> i32 {
+        self.search_depth(board, alpha, beta, self.config.max_depth)
+    }
+
+    fn search_depth(&mut self, board: &mut [u8; 82], mut alpha: i32, beta: i32, depth: u32) -> i32 {
+        self.nodes += 1;
+        let stand_pat = (self.evaluate)(board);
+        if depth == 0 || stand_pat >= beta {
+            return stand_pat;
+        }
+        let mut best = stand_pat;
+        alpha = alpha.max(stand_pat);
+
+        for (mv, value) in captures(board, self.weights) {
+            if stand_pat.saturating_add(value).saturating_add(self.config.delta_margin) <= alpha {
+                break; // Every remaining victim is worth less
+            }
+            let Ok(undo) = make_move(board, mv) else {
+                continue;
+            };
+            let score = if undo.captured_king() {
+                self.weights.king_value
+            } else {
+                -self.search_depth(board, -beta, -alpha, depth - 1)
+            };
+            unmake_move(board, &undo);
+
+            if score > best {
+                best = score;
+                if score >= beta {
+                    break;
+                }
+                alpha = alpha.max(score);
+            }
+        }
+        best
+    }
+}
+
+/// Material removed by capturing a piece or stack
+fn victim_value(target: u8, weights: &EvalWeights) -> i32 {
+    if target & 0x3F == KING_PAYLOAD {
+        weights.king_value
+    } else {
+        weights.piece_values[(target & 7) as usize] + weights.piece_values[((target >> 3) & 7) as usize]
+    }
+}
+
+/// Captures of the side to move with the value of their victim, most valuable first
+fn captures(board: &[u8; 82], weights: &EvalWeights) -> Vec<(u16, i32)> {
+    let mut captures = Vec::new();
+    for_each_move(board, board[81] == 1, |mv| {
+        let target = board[((mv >> 7) & 0x7F) as usize];
+        if target != 0 && (target ^ board[(mv & 0x7F) as usize]) & COLOR_BIT != 0 {
+            captures.push((mv, victim_value(target, weights)));
+        }
+    });
+    captures.sort_by_key(|&(mv, value)| (std::cmp::Reverse(value), mv));
+    captures
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::evaluate;
+
+    fn square(x: usize, y: usize) -> usize {
+        y * 9 + x
+    }
+
+    #[test]
+    fn test_pending_recapture_is_resolved() {
+        let weights = EvalWeights::material_only();
+        let mut board = [0u8; 82];
+        board[square(4, 4)] = 0x40 | 3; // White Commander
+        board[square(4, 2)] = 1 | (1 << 3); // Black stack of two Soldiers, capturable
+        board[square(6, 2)] = 3; // Black Commander guarding the stack
+        board[square(0, 8)] = 0x40 | KING_PAYLOAD;
+        board[square(8, 0)] = KING_PAYLOAD;
+        board[81] = 1;
+
+        // Both pieces of the stack count as the victim
+        let captures = captures(&board, &weights);
+        assert_eq!(captures[0], (((square(4, 2) << 7) | square(4, 4)) as u16, 200));
+
+        // Winning the stack costs the Commander: white stands pat instead
+        let stand_pat = evaluate(&board, &weights);
+        let mut search = Quiescence::new(QuiescenceConfig::default(), &weights, |board| evaluate(board, &weights));
+        assert_eq!(search.evaluate(&board), stand_pat);
+        assert!(search.nodes() > 1);
+
+        // Without the guard the stack is won
+        board[square(6, 2)] = 0;
+        let stand_pat = evaluate(&board, &weights);
+        let mut search = Quiescence::new(QuiescenceConfig::default(), &weights, |board| evaluate(board, &weights));
+        assert_eq!(search.evaluate(&board), stand_pat + 200);
+    }
+
+    #[test]
+    fn test_king_capture_and_delta_pruning() {
+        let weights = EvalWeights::material_only();
+        let mut board = [0u8; 82];
+        board[square(4, 4)] = 0x40 | 3; // White Commander
+        board[square(4, 0)] = KING_PAYLOAD; // Black King in its line
+        board[square(0, 8)] = 0x40 | KING_PAYLOAD;
+        board[81] = 1;
+        let mut search = Quiescence::new(QuiescenceConfig::default(), &weights, |board| evaluate(board, &weights));
+        assert_eq!(search.evaluate(&board), weights.king_value);
+
+        // A Soldier cannot lift a score far below alpha: the capture is not searched
+        board[square(4, 0)] = 1;
+        board[square(8, 0)] = KING_PAYLOAD;
+        let mut search = Quiescence::new(QuiescenceConfig::default(), &weights, |board| evaluate(board, &weights));
+        let stand_pat = evaluate(&board, &weights);
+        assert_eq!(search.search(&mut board, stand_pat + 1000, INFINITY), stand_pat);
+        assert_eq!(search.nodes(), 1);
+    }
+}
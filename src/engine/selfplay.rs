@@ -0,0 +1,465 @@
+//! Self-play data generation
+//!
+//! Plays engine-vs-engine games in parallel (one [`MctsEngine`] per Rayon worker) and
+//! records every position with the search's root move statistics and the final
+//! outcome of the game. Each game starts with a few uniformly random plies so
+//! that the games do not all follow the same line.
+//!
+//! # Dataset schema
+//!
+//! The dataset is written as JSON Lines, one [`PositionRecord`] per line:
+//!
+//! ```json
+//! {"game":3,"ply":12,"board":"BQYE...AAE=","moves":[{"move":5940,"visits":100,"score":-35}],"best_move":5940,"random":false,"result":1.0}
+//! ```
+//!
+//! - `game`: index of the game in the run
+//! - `ply`: number of plies played before this position (random opening plies included)
+//! - `board`: base64 encoded 82-byte board, as printed by `arx` (byte 81 is the side to move)
+//! - `moves`: every legal move with its simulation count and average score, from the
+//!   perspective of the player to move, in hundredths of a Soldier
+//! - `best_move`: move played
+//! - `random`: whether the move was a random opening ply rather than searched (its
+//!   `moves` are then empty)
+//! - `result`: game outcome from white's perspective (1 = white won, 0.5 = draw, 0 = black won)
+//!
+//! Games are
+//! adjudicated as draws after `max_plies` plies or when the side to move has no move,
+//! and each side may resign or agree to a draw under `adjudication` (see
+//! [`super::adjudication`]). The files can be fed directly to `arx tune`.
+//!
+//! Every game starts from an empty search tree, and a seeded run also seeds the engine
+//! (unless its configuration has its own seed), so a run is reproducible whatever the
+//! number of threads.
+
+use super::adjudication::{AdjudicationConfig, Adjudicator, Verdict};
+use super::cpu_move_gen::generate_moves;
+use super::{make_move, EngineConfig, MctsEngine};
+use base64::{engine::general_purpose, Engine as _};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::Mutex;
+
+/// Self-play parameters
+#[derive(Clone, Debug)]
+pub struct SelfPlayConfig {
+    /// Number of games to play
+    pub games: u32,
+    /// Engine configuration used by both sides
+    pub engine: EngineConfig,
+    /// Number of uniformly random plies played at the start of each game
+    pub random_plies: u32,
+    /// Games still running after this many plies are scored as draws
+    pub max_plies: u32,
+    /// Seed for the random openings and, unless `engine.seed` is set, for the engine
+    /// (a random seed is used when `None`)
+    pub seed: Option<u64>,
+    /// Number of worker threads (Rayon's default when `None`)
+    pub threads: Option<usize>,
+    /// Resignation and draw rules applied to both sides
+    pub adjudication: AdjudicationConfig,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            games: 100,
+            engine: EngineConfig::default(),
+            random_plies: 4,
+            max_plies: 300,
+            seed: None,
+            threads: None,
+            adjudication: AdjudicationConfig::default(),
+        }
+    }
+}
+
+/// Search statistics of one root move
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MoveRecord {
+    /// Encoded move
+    #[serde(rename = "move")]
+    pub mv: u16,
+    /// Number of simulations run for the move
+    pub visits: u32,
+    /// Average simulation score, from the perspective of the player to move
+    pub score: i32,
+}
+
+/// A recorded position (one line of the dataset)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PositionRecord {
+    /// Index of the game in the run
+    pub game: u32,
+    /// Number of plies played before this position
+    pub ply: u32,
+    /// Base64 encoded board
+    pub board: String,
+    /// Statistics of every legal move
+    pub moves: Vec<MoveRecord>,
+    /// Move played
+    pub best_move: u16,
+    /// Whether the move was a random opening ply, with no search statistics
+    #[serde(default)]
+    pub random: bool,
+    /// Game outcome from white's perspective
+    pub result: f64,
+}
+
+impl PositionRecord {
+    /// Decode the recorded board
+    pub fn decode_board(&self) -> Result<[u8; 82], String> {
+        let bytes = general_purpose::STANDARD
+            .decode(&self.board)
+            .map_err(|e| format!("Failed to decode base64 board: {}", e))?;
+        bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| format!("Invalid board length: expected 82 bytes, got {}", b.len()))
+    }
+}
+
+/// Final outcome of a game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameOutcome {
+    /// Result from white's perspective (1, 0.5 or 0)
+    pub fn result(self) -> f64 {
+        match self {
+            GameOutcome::WhiteWins => 1.0,
+            GameOutcome::BlackWins => 0.0,
+            GameOutcome::Draw => 0.5,
+        }
+    }
+}
+
+/// How a game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    KingCaptured,
+    /// The side to move had no legal move
+    NoMoves,
+    /// `max_plies` were played
+    MaxPlies,
+    /// The side to move resigned
+    Resignation,
+    /// A draw offer was accepted
+    DrawAgreed,
+}
+
+/// A finished self-play game
+#[derive(Clone, Debug)]
+pub struct SelfPlayGame {
+    /// Index of the game in the run
+    pub index: u32,
+    /// Outcome of the game
+    pub outcome: GameOutcome,
+    /// How the game ended
+    pub termination: Termination,
+    /// Number of plies played
+    pub plies: u32,
+    /// Recorded positions, labelled with the outcome
+    pub positions: Vec<PositionRecord>,
+}
+
+/// Totals of a self-play run
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelfPlaySummary {
+    pub games: u32,
+    pub positions: u64,
+    pub white_wins: u32,
+    pub black_wins: u32,
+    pub draws: u32,
+    /// Games ended by resignation or agreed draw
+    pub adjudicated: u32,
+}
+
+/// Play a single self-play game from the initial position
+pub fn play_game(
+    engine: &mut MctsEngine,
+    index: u32,
+    config: &SelfPlayConfig,
+    rng: &mut impl Rng,
+) -> Result<SelfPlayGame, String> {
+    // Games must not depend on what the worker's engine searched before
+    engine.clear_tree();
+    let mut board = crate::Game::new().to_binary();
+    let mut positions = Vec::new();
+    let mut outcome = GameOutcome::Draw;
+    let mut termination = Termination::MaxPlies;
+    let mut plies = 0;
+    // Black's then white's adjudicator, indexed by the side to move byte
+    let mut adjudicators = [
+        Adjudicator::new(config.adjudication),
+        Adjudicator::new(config.adjudication),
+    ];
+
+    while plies < config.max_plies {
+        let moves = generate_moves(&board);
+        if moves.is_empty() {
+            termination = Termination::NoMoves;
+            break;
+        }
+        let side = (board[81] == 1) as usize;
+
+        let mv = if plies < config.random_plies {
+            let mv = moves[rng.gen_range(0..moves.len())];
+            positions.push(PositionRecord {
+                game: index,
+                ply: plies,
+                board: general_purpose::STANDARD.encode(board),
+                moves: Vec::new(),
+                best_move: mv,
+                random: true,
+                result: 0.5,
+            });
+            mv
+        } else {
+            let result = engine.search(&board)?;
+            positions.push(PositionRecord {
+                game: index,
+                ply: plies,
+                board: general_purpose::STANDARD.encode(board),
+                moves: result
+                    .root_moves
+                    .iter()
+                    .map(|stats| MoveRecord {
+                        mv: stats.mv,
+                        visits: stats.visits,
+                        score: stats.mean_score().round() as i32,
+                    })
+                    .collect(),
+                best_move: result.best_move,
+                random: false,
+                result: 0.5,
+            });
+
+            let searched = result.root_moves.iter().any(|stats| stats.visits > 0);
+            let verdict = if searched { adjudicators[side].record(result.score) } else { Verdict::Continue };
+            match verdict {
+                Verdict::Resign => {
+                    outcome = if side == 1 { GameOutcome::BlackWins } else { GameOutcome::WhiteWins };
+                    termination = Termination::Resignation;
+                    break;
+                }
+                Verdict::OfferDraw if adjudicators[1 - side].accepts_draw() => {
+                    termination = Termination::DrawAgreed;
+                    break;
+                }
+                _ => {}
+            }
+            result.best_move
+        };
+
+        let white_moved = board[81] == 1;
+        let undo = make_move(&mut board, mv)?;
+        plies += 1;
+
+        if undo.captured_king() {
+            outcome = if white_moved { GameOutcome::WhiteWins } else { GameOutcome::BlackWins };
+            termination = Termination::KingCaptured;
+            break;
+        }
+    }
+
+    for position in &mut positions {
+        position.result = outcome.result();
+    }
+
+    Ok(SelfPlayGame {
+        index,
+        outcome,
+        termination,
+        plies,
+        positions,
+    })
+}
+
+/// Play `config.games` games in parallel, writing every recorded position to `output`
+/// as JSON Lines as soon as its game finishes. `on_game` is called after each game.
+pub fn run_selfplay<W: Write + Send>(
+    config: &SelfPlayConfig,
+    output: W,
+    on_game: impl Fn(&SelfPlayGame) + Sync,
+) -> Result<SelfPlaySummary, String> {
+    let base_seed = config.seed.unwrap_or_else(rand::random);
+    let mut engine_config = config.engine.clone();
+    if engine_config.seed.is_none() {
+        engine_config.seed = config.seed;
+    }
+    let output = Mutex::new(output);
+    let summary = Mutex::new(SelfPlaySummary::default());
+
+    let play_all = || {
+        (0..config.games)
+            .into_par_iter()
+            .map_init(
+                || MctsEngine::with_config(engine_config.clone()),
+                |engine, index| {
+                    let engine = engine.as_mut().map_err(|e| e.clone())?;
+                    let mut rng = StdRng::seed_from_u64(base_seed.wrapping_add(index as u64));
+                    let game = play_game(engine, index, config, &mut rng)?;
+
+                    {
+                        let mut output = output.lock().unwrap();
+                        for position in &game.positions {
+                            let line = serde_json::to_string(position)
+                                .map_err(|e| format!("Failed to serialize position: {}", e))?;
+                            writeln!(output, "{}", line)
+                                .map_err(|e| format!("Failed to write dataset: {}", e))?;
+                        }
+                    }
+
+                    let mut summary = summary.lock().unwrap();
+                    summary.games += 1;
+                    summary.positions += game.positions.len() as u64;
+                    match game.outcome {
+                        GameOutcome::WhiteWins => summary.white_wins += 1,
+                        GameOutcome::BlackWins => summary.black_wins += 1,
+                        GameOutcome::Draw => summary.draws += 1,
+                    }
+                    if matches!(game.termination, Termination::Resignation | Termination::DrawAgreed) {
+                        summary.adjudicated += 1;
+                    }
+                    drop(summary);
+
+                    on_game(&game);
+                    Ok(())
+                },
+            )
+            .collect::<Result<(), String>>()
+    };
+
+    match config.threads {
+        Some(threads) => rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| format!("Failed to create thread pool: {}", e))?
+            .install(play_all)?,
+        None => play_all()?,
+    }
+
+    output
+        .into_inner()
+        .unwrap()
+        .flush()
+        .map_err(|e| format!("Failed to write dataset: {}", e))?;
+    Ok(summary.into_inner().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quick_config() -> SelfPlayConfig {
+        SelfPlayConfig {
+            games: 2,
+            engine: EngineConfig {
+                max_depth: 2,
+                simulations_per_move: 4,
+                gpu_batch_size: 16,
+                use_gpu_simulation: false,
+                ..EngineConfig::default()
+            },
+            random_plies: 2,
+            max_plies: 8,
+            seed: Some(7),
+            threads: Some(2),
+            adjudication: AdjudicationConfig::default(),
+        }
+    }
+
+    #[test]
+    fn test_play_game_records_positions() {
+        let config = quick_config();
+        let engine = MctsEngine::with_config(config.engine.clone());
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        let game = play_game(&mut engine, 0, &config, &mut StdRng::seed_from_u64(1)).unwrap();
+        assert!(game.plies <= config.max_plies);
+        assert_eq!(game.positions.len() as u32, game.plies);
+
+        for (ply, position) in game.positions.iter().enumerate() {
+            assert_eq!(position.ply, ply as u32);
+            assert_eq!(position.result, game.outcome.result());
+            let board = position.decode_board().unwrap();
+            assert!(generate_moves(&board).contains(&position.best_move));
+            if position.random {
+                assert!(position.ply < config.random_plies);
+                assert!(position.moves.is_empty());
+            } else {
+                assert!(position.ply >= config.random_plies);
+                assert!(position.moves.iter().any(|m| m.mv == position.best_move));
+                assert_eq!(position.moves.len(), generate_moves(&board).len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_adjudicated_games() {
+        let engine = MctsEngine::with_config(quick_config().engine);
+        if let Err(e) = &engine {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+        let mut engine = engine.unwrap();
+
+        // Every score is hopeless: the first side to search resigns
+        let config = SelfPlayConfig {
+            adjudication: AdjudicationConfig { resign_threshold: Some(i32::MAX), resign_moves: 1, ..AdjudicationConfig::default() },
+            ..quick_config()
+        };
+        let game = play_game(&mut engine, 0, &config, &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(game.termination, Termination::Resignation);
+        assert_eq!(game.plies, config.random_plies);
+        assert_eq!(game.positions.len() as u32, config.random_plies + 1);
+        assert_ne!(game.outcome, GameOutcome::Draw);
+
+        // Every score is drawish: the first offer is declined by a side that has not
+        // searched yet, the second one is accepted
+        let config = SelfPlayConfig {
+            adjudication: AdjudicationConfig {
+                draw_margin: Some(i32::MAX),
+                draw_moves: 1,
+                draw_min_moves: 0,
+                ..AdjudicationConfig::default()
+            },
+            ..quick_config()
+        };
+        let game = play_game(&mut engine, 0, &config, &mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(game.termination, Termination::DrawAgreed);
+        assert_eq!(game.outcome, GameOutcome::Draw);
+        assert_eq!(game.positions.len() as u32, config.random_plies + 2);
+    }
+
+    #[test]
+    fn test_run_selfplay_writes_jsonl() {
+        if let Err(e) = MctsEngine::with_config(quick_config().engine) {
+            println!("Skipping test: GPU not available - {}", e);
+            return;
+        }
+
+        let mut output = Vec::new();
+        let summary = run_selfplay(&quick_config(), &mut output, |_| {}).unwrap();
+        assert_eq!(summary.games, 2);
+        assert_eq!(summary.white_wins + summary.black_wins + summary.draws, 2);
+
+        let records: Vec<PositionRecord> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len() as u64, summary.positions);
+    }
+}
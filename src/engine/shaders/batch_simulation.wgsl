@@ -1,12 +1,16 @@
 // Batch simulation shader for MCTS engine
 // This shader processes multiple board positions in parallel to:
 // 1. Apply moves to board states
-// 2. Evaluate board positions
+// 2. Play seeded light or heavy rollouts from the resulting positions
+// 3. Evaluate board positions
 // This reduces CPU-GPU transfer overhead by batching operations
 
+// Layouts must match `GpuBoardState` / `GpuMoveApplication` in gpu_batch_sim.rs,
+// including their padding, or every element after the first is misread
 struct BoardState {
     squares: array<u32, 81>,  // 81 squares
     white_to_move: u32,        // 1 if white to move, 0 if black
+    _padding: array<u32, 3>,
 }
 
 struct MoveApplication {
@@ -14,9 +18,43 @@ struct MoveApplication {
     move_encoding: u32,
     result_score: i32,  // Output: evaluation score after applying move
     valid: u32,         // Output: 1 if move was valid, 0 otherwise
+    seed: u32,          // Random state of this invocation's rollout
+    rollout_plies: u32, // Random plies played after the move
+    first_moves: array<u32, 2>, // Output: first rollout moves, NO_MOVE when not played
+}
+
+// Evaluation weights, mirrored from `EvalWeights` on the CPU side.
+// Arrays in uniform buffers need a 16-byte stride, hence the vec4 packing.
+struct EvalWeights {
+    piece_values: array<vec4<i32>, 2>,  // Indexed by piece code (0 unused)
+    king_value: i32,
+    mobility: i32,
+    stack_bonus: i32,
+    soldier_advancement: i32,
+    king_attacker_penalty: i32,
+    center_control: i32,
+    _padding: vec2<i32>,
+}
+
+// Rollout policy, mirrored from `PlayoutWeights` on the CPU side
+struct PlayoutWeights {
+    heavy: u32,         // 1 for heavy playouts, 0 for uniformly random ones
+    base: u32,
+    capture: u32,       // Per 100 evaluation units of captured material
+    stack: u32,
+    hanging_king: u32,
+    _padding0: u32,
+    _padding1: u32,
+    _padding2: u32,
 }
 
 @group(0) @binding(0) var<storage, read_write> applications: array<MoveApplication>;
+@group(0) @binding(1) var<uniform> weights: EvalWeights;
+@group(0) @binding(2) var<uniform> playout: PlayoutWeights;
+
+// Squares the opponent of the side to move can move to (bit per square), filled by
+// `mark_attacks` for heavy playouts
+var<private> attacked: array<u32, 3>;
 
 // Piece type codes
 const PIECE_SOLDIER: u32 = 1u;
@@ -26,27 +64,26 @@ const PIECE_PALADIN: u32 = 4u;
 const PIECE_GUARD: u32 = 5u;
 const PIECE_DRAGON: u32 = 6u;
 const PIECE_BALLISTA: u32 = 7u;
+const PIECE_KING: u32 = 0u; // Pseudo code used for king movement
 
 // Special encoding for King
 const KING_PAYLOAD: u32 = 0x38u; // 0b111000
 
-// Piece values for evaluation
-const PIECE_VALUES: array<i32, 8> = array<i32, 8>(
-    0,  // Index 0: unused
-    1,  // Soldier
-    3,  // Jester
-    5,  // Commander
-    3,  // Paladin
-    3,  // Guard
-    3,  // Dragon
-    5,  // Ballista
-);
-const KING_VALUE: i32 = 1000;
-
 // Board dimension
 const BOARD_DIM: i32 = 9;
 const BOARD_SIZE: u32 = 81u;
 
+// Move walks return this when the requested move is not among the visited ones
+const NO_MOVE: u32 = 0xFFFFFFFFu;
+// Start value of `remaining` when walking moves only to count them
+const COUNT_ALL: i32 = 0x3FFFFFFF;
+
+// How move walks weigh each move
+const WALK_UNIFORM: u32 = 0u;       // Every move counts once
+const WALK_HEAVY: u32 = 1u;         // Heavy playout weights (King captures excluded)
+const WALK_KING_CAPTURES: u32 = 2u; // Only captures of the enemy King count
+const WALK_ATTACKS: u32 = 3u;       // Every move counts once and marks its target as attacked
+
 // Helper functions
 fn get_piece_color(piece: u32) -> u32 {
     return (piece >> 6u) & 1u;
@@ -65,6 +102,14 @@ fn is_king(piece: u32) -> bool {
     return payload == KING_PAYLOAD;
 }
 
+fn is_stackable(piece: u32) -> bool {
+    return !is_king(piece) && get_top_piece_code(piece) == 0u;
+}
+
+fn piece_value(code: u32) -> i32 {
+    return weights.piece_values[code / 4u][code % 4u];
+}
+
 // Apply a move to a board state
 fn apply_move(board: ptr<function, BoardState>, move_encoding: u32) -> bool {
     let from_idx = move_encoding & 0x7Fu;
@@ -79,31 +124,35 @@ fn apply_move(board: ptr<function, BoardState>, move_encoding: u32) -> bool {
     if piece == 0u {
         return false;
     }
-    
+
+    let color_bit = piece & 0x40u;
+    var from_after = 0u;
+    var moving_piece = piece;
+
     if unstack {
         // Unstack top piece
-        let payload = piece & 0x3Fu;
-        let top_code = (payload >> 3u) & 7u;
-        let bottom_code = payload & 7u;
-        let color_bit = piece & 0x40u;
-        
-        if top_code == 0u {
+        let top_code = get_top_piece_code(piece);
+        if is_king(piece) || top_code == 0u {
             return false; // Cannot unstack: no top piece
         }
-        
-        // Create new bottom piece (remove top)
-        (*board).squares[from_idx] = color_bit | bottom_code;
-        
-        // Create moving piece (top becomes new bottom)
-        let moving_piece = color_bit | top_code;
-        
-        // Place at destination (simple: just replace, no stacking logic)
-        (*board).squares[to] = moving_piece;
-    } else {
-        // Move entire piece/stack
-        (*board).squares[from_idx] = 0u;
-        (*board).squares[to] = piece; // Simplified: just capture/replace
+        // Bottom piece stays, top piece becomes a single moving piece
+        from_after = color_bit | get_bottom_piece_code(piece);
+        moving_piece = color_bit | top_code;
+    }
+
+    let target_piece = (*board).squares[to];
+    var to_after = moving_piece;
+    if target_piece != 0u && (target_piece & 0x40u) == color_bit {
+        // Friendly piece: stack the moving piece on top of it
+        if !is_stackable(target_piece) || !is_stackable(moving_piece) {
+            return false;
+        }
+        to_after = color_bit | ((moving_piece & 7u) << 3u) | (target_piece & 0x3Fu);
     }
+    // Otherwise: empty square or capture (the whole enemy stack is removed)
+
+    (*board).squares[from_idx] = from_after;
+    (*board).squares[to] = to_after;
     
     // Switch turn
     (*board).white_to_move = select(1u, 0u, (*board).white_to_move == 1u);
@@ -111,11 +160,242 @@ fn apply_move(board: ptr<function, BoardState>, move_encoding: u32) -> bool {
     return true;
 }
 
-// Evaluate a board position
+// Movement direction `d` of a piece code for a color
+fn piece_direction(piece_type: u32, color: u32, d: u32) -> vec2<i32> {
+    let forward = select(1, -1, color == 1u);
+    var orthogonal = array<vec2<i32>, 4>(
+        vec2<i32>(1, 0), vec2<i32>(0, 1), vec2<i32>(-1, 0), vec2<i32>(0, -1)
+    );
+    var diagonal = array<vec2<i32>, 4>(
+        vec2<i32>(1, 1), vec2<i32>(1, -1), vec2<i32>(-1, 1), vec2<i32>(-1, -1)
+    );
+    var knight = array<vec2<i32>, 8>(
+        vec2<i32>(2, 1), vec2<i32>(2, -1), vec2<i32>(-2, 1), vec2<i32>(-2, -1),
+        vec2<i32>(1, 2), vec2<i32>(1, -2), vec2<i32>(-1, 2), vec2<i32>(-1, -2)
+    );
+    switch piece_type {
+        case PIECE_SOLDIER: {
+            return vec2<i32>(select(-1, 1, d == 0u), forward);
+        }
+        case PIECE_BALLISTA: {
+            return vec2<i32>(0, forward);
+        }
+        case PIECE_JESTER, PIECE_GUARD: {
+            return diagonal[d];
+        }
+        case PIECE_COMMANDER, PIECE_PALADIN: {
+            return orthogonal[d];
+        }
+        case PIECE_DRAGON: {
+            return knight[d];
+        }
+        default: {
+            // King: all eight neighbours
+            if d < 4u {
+                return orthogonal[d];
+            }
+            return diagonal[d - 4u];
+        }
+    }
+}
+
+fn piece_direction_count(piece_type: u32) -> u32 {
+    switch piece_type {
+        case PIECE_SOLDIER: { return 2u; }
+        case PIECE_BALLISTA: { return 1u; }
+        case PIECE_DRAGON, PIECE_KING: { return 8u; }
+        default: { return 4u; }
+    }
+}
+
+fn piece_range(piece_type: u32) -> i32 {
+    switch piece_type {
+        case PIECE_JESTER, PIECE_COMMANDER, PIECE_BALLISTA: { return BOARD_DIM; }
+        case PIECE_PALADIN, PIECE_GUARD: { return 2; }
+        default: { return 1; }
+    }
+}
+
+fn is_attacked(square: u32) -> bool {
+    return (attacked[square / 32u] & (1u << (square % 32u))) != 0u;
+}
+
+// Weight of a move from `idx` to `to` in a walk (mirrors `PlayoutWeights` on the CPU side)
+fn move_weight(board: ptr<function, BoardState>, idx: u32, to: u32, piece_type: u32, mode: u32) -> i32 {
+    let target_piece = (*board).squares[to];
+    let captures = target_piece != 0u && get_piece_color(target_piece) != get_piece_color((*board).squares[idx]);
+    switch mode {
+        case WALK_KING_CAPTURES: {
+            return select(0, 1, captures && is_king(target_piece));
+        }
+        case WALK_HEAVY: {
+            if captures && is_king(target_piece) {
+                return 0;
+            }
+            if piece_type == PIECE_KING && is_attacked(to) {
+                return i32(playout.hanging_king);
+            }
+            if captures {
+                let value = piece_value(get_bottom_piece_code(target_piece)) + piece_value(get_top_piece_code(target_piece));
+                return i32(playout.base + playout.capture * u32(max(value, 0)) / 100u);
+            }
+            if target_piece != 0u {
+                return i32(playout.base + playout.stack);
+            }
+            return i32(playout.base);
+        }
+        case WALK_ATTACKS: {
+            attacked[to / 32u] |= 1u << (to % 32u);
+            return 1;
+        }
+        default: {
+            return 1;
+        }
+    }
+}
+
+// Walk the moves of one piece type standing on `idx` in move generation order
+// (same rules and order as `cpu_move_gen`), each weighing `move_weight`. Returns the
+// move during which `*remaining` drops below zero, otherwise decrements `*remaining`
+// by the total weight of the moves visited.
+fn walk_piece_moves(board: ptr<function, BoardState>, idx: u32, piece_type: u32, color: u32,
+                    is_top: bool, has_top: bool, remaining: ptr<function, i32>, mode: u32) -> u32 {
+    let x = i32(idx % 9u);
+    let y = i32(idx / 9u);
+    let range = piece_range(piece_type);
+    let unstack_bit = select(0u, 0x4000u, is_top);
+
+    for (var d = 0u; d < piece_direction_count(piece_type); d++) {
+        let dir = piece_direction(piece_type, color, d);
+        for (var dist = 1; dist <= range; dist++) {
+            let tx = x + dir.x * dist;
+            let ty = y + dir.y * dist;
+            if tx < 0 || tx >= BOARD_DIM || ty < 0 || ty >= BOARD_DIM {
+                break;
+            }
+            let to = u32(ty * BOARD_DIM + tx);
+            let target_piece = (*board).squares[to];
+            var encoding = NO_MOVE;
+            if target_piece == 0u || get_piece_color(target_piece) != color {
+                encoding = idx | (to << 7u) | unstack_bit; // Quiet move or capture
+            } else if !(!is_top && has_top) && is_stackable(target_piece) {
+                // Stacking onto a friendly piece
+                encoding = idx | (to << 7u) | unstack_bit | select(0u, 0x8000u, is_top);
+            }
+            if encoding != NO_MOVE {
+                let weight = move_weight(board, idx, to, piece_type, mode);
+                if *remaining < weight {
+                    return encoding;
+                }
+                *remaining -= weight;
+            }
+            if target_piece != 0u {
+                break;
+            }
+        }
+    }
+
+    return NO_MOVE;
+}
+
+// Walk all moves of the piece or stack on `idx` (see `walk_piece_moves`)
+fn walk_square_moves(board: ptr<function, BoardState>, idx: u32, remaining: ptr<function, i32>, mode: u32) -> u32 {
+    let piece = (*board).squares[idx];
+    let color = get_piece_color(piece);
+    if is_king(piece) {
+        // King can never be stacked: has_top=true forbids moving onto friendly pieces
+        return walk_piece_moves(board, idx, PIECE_KING, color, false, true, remaining, mode);
+    }
+    let top_code = get_top_piece_code(piece);
+    if top_code != 0u {
+        let found = walk_piece_moves(board, idx, top_code, color, true, true, remaining, mode);
+        if found != NO_MOVE {
+            return found;
+        }
+    }
+    return walk_piece_moves(board, idx, get_bottom_piece_code(piece), color, false, top_code != 0u, remaining, mode);
+}
+
+// Count all moves of the piece or stack on `idx`
+fn count_square_moves(board: ptr<function, BoardState>, idx: u32) -> i32 {
+    var remaining = COUNT_ALL;
+    walk_square_moves(board, idx, &remaining, WALK_UNIFORM);
+    return COUNT_ALL - remaining;
+}
+
+// Walk the moves of every piece of a color (see `walk_piece_moves`)
+fn walk_side_moves(board: ptr<function, BoardState>, color: u32, remaining: ptr<function, i32>, mode: u32) -> u32 {
+    for (var i = 0u; i < BOARD_SIZE; i++) {
+        let piece = (*board).squares[i];
+        if piece == 0u || get_piece_color(piece) != color {
+            continue;
+        }
+        let found = walk_square_moves(board, i, remaining, mode);
+        if found != NO_MOVE {
+            return found;
+        }
+    }
+    return NO_MOVE;
+}
+
+// Move of the side to move at weighted position `n`, in move generation order
+fn select_move(board: ptr<function, BoardState>, n: i32, mode: u32) -> u32 {
+    var remaining = n;
+    return walk_side_moves(board, (*board).white_to_move, &remaining, mode);
+}
+
+// Total weight of the moves of the side to move
+fn count_side_moves(board: ptr<function, BoardState>, mode: u32) -> i32 {
+    var remaining = COUNT_ALL;
+    walk_side_moves(board, (*board).white_to_move, &remaining, mode);
+    return COUNT_ALL - remaining;
+}
+
+// Fill `attacked` with the squares the opponent of the side to move can move to
+fn mark_attacks(board: ptr<function, BoardState>) {
+    attacked = array<u32, 3>(0u, 0u, 0u);
+    var remaining = COUNT_ALL;
+    walk_side_moves(board, 1u - (*board).white_to_move, &remaining, WALK_ATTACKS);
+}
+
+// Next rollout move: uniformly random for light playouts; for heavy ones a King
+// capture if there is one, otherwise drawn by `move_weight`
+fn choose_rollout_move(board: ptr<function, BoardState>, state: ptr<function, u32>) -> u32 {
+    if playout.heavy == 0u {
+        let count = count_side_moves(board, WALK_UNIFORM);
+        if count == 0 {
+            return NO_MOVE;
+        }
+        return select_move(board, i32(next_random(state) % u32(count)), WALK_UNIFORM);
+    }
+
+    let king_captures = count_side_moves(board, WALK_KING_CAPTURES);
+    if king_captures > 0 {
+        return select_move(board, i32(next_random(state) % u32(king_captures)), WALK_KING_CAPTURES);
+    }
+    mark_attacks(board);
+    let total = count_side_moves(board, WALK_HEAVY);
+    if total == 0 {
+        return NO_MOVE;
+    }
+    return select_move(board, i32(next_random(state) % u32(total)), WALK_HEAVY);
+}
+
+// PCG step: advances the rollout state and returns a random word
+// (mirrored by the gpu_batch_sim.rs tests)
+fn next_random(state: ptr<function, u32>) -> u32 {
+    let s = *state * 747796405u + 2891336453u;
+    *state = s;
+    let word = ((s >> ((s >> 28u) + 4u)) ^ s) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// Evaluate a board position (mirrors `EvalFeatures` on the CPU side)
 fn evaluate_board(board: ptr<function, BoardState>) -> i32 {
     let white_to_move = (*board).white_to_move;
-    var white_value: i32 = 0;
-    var black_value: i32 = 0;
+    // Index 0 = black, 1 = white
+    var values = array<i32, 2>(0, 0);
+    var king_squares = array<i32, 2>(-1, -1);
     
     for (var i = 0u; i < BOARD_SIZE; i++) {
         let piece = (*board).squares[i];
@@ -123,48 +403,68 @@ fn evaluate_board(board: ptr<function, BoardState>) -> i32 {
             continue;
         }
         
-        let is_white = (piece >> 6u) == 1u;
-        let payload = piece & 0x3Fu;
+        let color = get_piece_color(piece);
+        let x = i32(i % 9u);
+        let y = i32(i / 9u);
+
+        // Centre control and mobility apply to every piece
+        if x >= 3 && x <= 5 && y >= 3 && y <= 5 {
+            values[color] += weights.center_control;
+        }
+        values[color] += weights.mobility * count_square_moves(board, i);
         
         // Check for King
-        if payload == KING_PAYLOAD {
-            if is_white {
-                white_value += KING_VALUE;
-            } else {
-                black_value += KING_VALUE;
-            }
+        if is_king(piece) {
+            values[color] += weights.king_value;
+            king_squares[color] = i32(i);
             continue;
         }
         
-        let top_code = (payload >> 3u) & 7u;
-        let bottom_code = payload & 7u;
+        let top_code = get_top_piece_code(piece);
+        let bottom_code = get_bottom_piece_code(piece);
         
+        // White soldiers start on row 6 and advance upwards, black ones on row 2
+        let advancement = max(select(y - 2, 6 - y, color == 1u), 0);
+
         // Add value for bottom piece
-        if bottom_code > 0u && bottom_code < 8u {
-            let value = PIECE_VALUES[bottom_code];
-            if is_white {
-                white_value += value;
-            } else {
-                black_value += value;
-            }
+        values[color] += piece_value(bottom_code);
+        if bottom_code == PIECE_SOLDIER {
+            values[color] += weights.soldier_advancement * advancement;
         }
         
         // Add value for top piece if stacked
-        if top_code > 0u && top_code < 8u {
-            let value = PIECE_VALUES[top_code];
-            if is_white {
-                white_value += value;
-            } else {
-                black_value += value;
+        if top_code != 0u {
+            values[color] += piece_value(top_code) + weights.stack_bonus;
+            if top_code == PIECE_SOLDIER {
+                values[color] += weights.soldier_advancement * advancement;
             }
         }
     }
+
+    // King safety: penalty per enemy piece within two squares of the King
+    for (var i = 0u; i < BOARD_SIZE; i++) {
+        let piece = (*board).squares[i];
+        if piece == 0u {
+            continue;
+        }
+        let color = get_piece_color(piece);
+        let enemy = 1u - color;
+        let king = king_squares[enemy];
+        if king < 0 {
+            continue;
+        }
+        let dx = abs(i32(i % 9u) - king % BOARD_DIM);
+        let dy = abs(i32(i / 9u) - king / BOARD_DIM);
+        if dx <= 2 && dy <= 2 {
+            values[enemy] -= weights.king_attacker_penalty;
+        }
+    }
     
     // Return value from perspective of current player
     if white_to_move == 1u {
-        return white_value - black_value;
+        return values[1] - values[0];
     } else {
-        return black_value - white_value;
+        return values[0] - values[1];
     }
 }
 
@@ -185,8 +485,24 @@ fn main(@builtin(global_invocation_id) global_id: vec3<u32>) {
     let valid = apply_move(&board, move_encoding);
     
     if valid {
+        // Rollout; the score stays in the perspective of the player to move right
+        // after the move
+        var state = applications[idx].seed;
+        var sign = 1;
+        applications[idx].first_moves = array<u32, 2>(NO_MOVE, NO_MOVE);
+        for (var ply = 0u; ply < applications[idx].rollout_plies; ply++) {
+            let random_move = choose_rollout_move(&board, &state);
+            if random_move == NO_MOVE || !apply_move(&board, random_move) {
+                break;
+            }
+            if ply < 2u {
+                applications[idx].first_moves[ply] = random_move;
+            }
+            sign = -sign;
+        }
+
         // Evaluate the resulting position
-        let score = evaluate_board(&board);
+        let score = sign * evaluate_board(&board);
         applications[idx].result_score = score;
         applications[idx].valid = 1u;
         applications[idx].board = board;
@@ -0,0 +1,393 @@
+//! King-capture solver and tactical puzzle finder
+//!
+//! [`Solver`] proves "capture the King in N moves" sequences by exhaustive AND/OR
+//! search: at the attacker's turns one move must work, at the defender's turns every
+//! reply must still lose. Searches are run with iterative deepening so the shortest
+//! win is found first, and positions already shown to hold for a given number of
+//! moves are remembered in a table keyed by Zobrist hash. A node budget keeps hopeless
+//! searches bounded; running out of it yields [`SolveOutcome::Unknown`].
+//!
+//! A defender with no legal move is not lost (the game is scored as a draw), so such
+//! positions never count as a win.
+//!
+//! # Puzzles
+//!
+//! [`find_puzzles`] scans positions (typically from self-play datasets) for forced
+//! wins with a single winning first move, which makes them usable as exercises.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::solver::{SolveOutcome, Solver, SolverConfig};
+//!
+//! let board = arx_engine::Game::new().to_binary();
+//! let mut solver = Solver::new(SolverConfig::default());
+//! if let SolveOutcome::Win { moves, line } = solver.solve(&board) {
+//!     println!("King captured in {} moves: {:?}", moves, line);
+//! }
+//! ```
+
+use super::cpu_move_gen::generate_moves;
+use super::tuning::LabelledPosition;
+use super::{make_move, unmake_move};
+use crate::zobrist;
+use base64::{engine::general_purpose, Engine as _};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+const COLOR_BIT: u8 = 0x40;
+
+/// Solver limits
+#[derive(Clone, Debug)]
+pub struct SolverConfig {
+    /// Longest win searched for, in moves of the attacking side
+    pub max_moves: u32,
+    /// Nodes visited before giving up on a position
+    pub max_nodes: u64,
+}
+
+impl Default for SolverConfig {
+    fn default() -> Self {
+        Self {
+            max_moves: 3,
+            max_nodes: 2_000_000,
+        }
+    }
+}
+
+/// Result of solving a position for the side to move
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolveOutcome {
+    /// The King is captured in `moves` moves; `line` alternates attacker moves and the
+    /// defender's most stubborn replies, ending with the King capture
+    Win { moves: u32, line: Vec<u16> },
+    /// No forced King capture within the move limit
+    NoWin,
+    /// The node budget ran out before a conclusion
+    Unknown,
+}
+
+/// The node budget ran out
+struct Aborted;
+
+/// Exhaustive King-capture solver
+pub struct Solver {
+    config: SolverConfig,
+    nodes: u64,
+    /// Deepest move count each attacker position is known to hold against
+    holds: HashMap<u64, u32>,
+}
+
+impl Solver {
+    pub fn new(config: SolverConfig) -> Self {
+        Self {
+            config,
+            nodes: 0,
+            holds: HashMap::new(),
+        }
+    }
+
+    /// Nodes visited by the last call to [`Solver::solve`] or [`Solver::winning_moves`]
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Look for the shortest forced King capture by the side to move
+    pub fn solve(&mut self, board: &[u8; 82]) -> SolveOutcome {
+        self.nodes = 0;
+        let mut board = *board;
+        let hash = zobrist::hash_binary(&board);
+        for moves in 1..=self.config.max_moves {
+            match self.attack(&mut board, hash, moves) {
+                Ok(Some(_)) => {
+                    return match self.main_line(&board, hash, moves) {
+                        Ok(line) => SolveOutcome::Win { moves, line },
+                        Err(Aborted) => SolveOutcome::Unknown,
+                    };
+                }
+                Ok(None) => {}
+                Err(Aborted) => return SolveOutcome::Unknown,
+            }
+        }
+        SolveOutcome::NoWin
+    }
+
+    /// Every first move that captures the King within `moves` moves, or `None` if the
+    /// node budget ran out
+    pub fn winning_moves(&mut self, board: &[u8; 82], moves: u32) -> Option<Vec<u16>> {
+        self.nodes = 0;
+        let mut board = *board;
+        let hash = zobrist::hash_binary(&board);
+        let mut winning = Vec::new();
+        for mv in generate_moves(&board) {
+            let undo = make_move(&mut board, mv).ok()?;
+            let won = undo.captured_king()
+                || (moves > 1 && self.defend(&mut board, hash ^ undo.hash_delta, moves - 1).ok()?);
+            unmake_move(&mut board, &undo);
+            if won {
+                winning.push(mv);
+            }
+        }
+        Some(winning)
+    }
+
+    /// Attacker to move: a move capturing the King within `moves` moves, if any
+    fn attack(&mut self, board: &mut [u8; 82], hash: u64, moves: u32) -> Result<Option<u16>, Aborted> {
+        self.visit()?;
+        if self.holds.get(&hash).is_some_and(|&held| held >= moves) {
+            return Ok(None);
+        }
+
+        let mut candidates = generate_moves(board);
+        if let Some(&mv) = candidates.iter().find(|&&mv| captures_king(board, mv)) {
+            return Ok(Some(mv));
+        }
+        if moves > 1 {
+            // Captures first: they are the most forcing moves
+            candidates.sort_by_key(|&mv| !is_capture(board, mv));
+            for mv in candidates {
+                let Ok(undo) = make_move(board, mv) else { continue };
+                let won = self.defend(board, hash ^ undo.hash_delta, moves - 1);
+                unmake_move(board, &undo);
+                if won? {
+                    return Ok(Some(mv));
+                }
+            }
+        }
+
+        self.holds.insert(hash, moves);
+        Ok(None)
+    }
+
+    /// Defender to move: whether every reply still loses within `moves` attacker moves
+    fn defend(&mut self, board: &mut [u8; 82], hash: u64, moves: u32) -> Result<bool, Aborted> {
+        self.visit()?;
+        let replies = generate_moves(board);
+        if replies.is_empty() || replies.iter().any(|&mv| captures_king(board, mv)) {
+            return Ok(false);
+        }
+        for mv in replies {
+            let Ok(undo) = make_move(board, mv) else { continue };
+            let won = self.attack(board, hash ^ undo.hash_delta, moves);
+            unmake_move(board, &undo);
+            if won?.is_none() {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Main line of a proven win: the fastest attacker moves against the longest defence
+    fn main_line(&mut self, board: &[u8; 82], hash: u64, moves: u32) -> Result<Vec<u16>, Aborted> {
+        let mut board = *board;
+        let mut hash = hash;
+        let mut remaining = moves;
+        let mut line = Vec::new();
+
+        loop {
+            let (mv, depth) = self.shortest_attack(&mut board, hash, remaining)?.ok_or(Aborted)?;
+            let undo = make_move(&mut board, mv).map_err(|_| Aborted)?;
+            line.push(mv);
+            hash ^= undo.hash_delta;
+            if undo.captured_king() {
+                return Ok(line);
+            }
+
+            // The reply that postpones the capture the longest
+            let mut defence: Option<(u16, u32)> = None;
+            for reply in generate_moves(&board) {
+                let undo = make_move(&mut board, reply).map_err(|_| Aborted)?;
+                let needed = self.shortest_attack(&mut board, hash ^ undo.hash_delta, depth - 1);
+                unmake_move(&mut board, &undo);
+                let (_, needed) = needed?.ok_or(Aborted)?;
+                if defence.is_none_or(|(_, longest)| needed > longest) {
+                    defence = Some((reply, needed));
+                }
+            }
+            let (reply, needed) = defence.ok_or(Aborted)?;
+            let undo = make_move(&mut board, reply).map_err(|_| Aborted)?;
+            line.push(reply);
+            hash ^= undo.hash_delta;
+            remaining = needed;
+        }
+    }
+
+    /// Attacker to move: the quickest winning move within `moves` moves and its length
+    fn shortest_attack(&mut self, board: &mut [u8; 82], hash: u64, moves: u32) -> Result<Option<(u16, u32)>, Aborted> {
+        for depth in 1..=moves {
+            if let Some(mv) = self.attack(board, hash, depth)? {
+                return Ok(Some((mv, depth)));
+            }
+        }
+        Ok(None)
+    }
+
+    fn visit(&mut self) -> Result<(), Aborted> {
+        self.nodes += 1;
+        if self.nodes > self.config.max_nodes {
+            return Err(Aborted);
+        }
+        Ok(())
+    }
+}
+
+/// Whether a move lands on an enemy piece
+fn is_capture(board: &[u8; 82], mv: u16) -> bool {
+    let from = board[(mv & 0x7F) as usize];
+    let to = board[((mv >> 7) & 0x7F) as usize];
+    to != 0 && (to & COLOR_BIT) != (from & COLOR_BIT)
+}
+
+/// Whether a move captures the enemy King
+fn captures_king(board: &[u8; 82], mv: u16) -> bool {
+    is_capture(board, mv) && (board[((mv >> 7) & 0x7F) as usize] & 0x3F) == 0x38
+}
+
+/// Puzzle extraction parameters
+#[derive(Clone, Debug)]
+pub struct PuzzleConfig {
+    /// Limits of the solver run on each position
+    pub solver: SolverConfig,
+    /// Shortest win kept, in moves (1 would keep every hanging King)
+    pub min_moves: u32,
+}
+
+impl Default for PuzzleConfig {
+    fn default() -> Self {
+        Self {
+            solver: SolverConfig::default(),
+            min_moves: 2,
+        }
+    }
+}
+
+/// A position with a unique forced King capture (one line of a puzzle file)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Puzzle {
+    /// Base64 encoded board
+    pub board: String,
+    /// Length of the win in moves of the side to move
+    pub moves: u32,
+    /// Main line, starting with the unique winning move
+    pub solution: Vec<u16>,
+}
+
+impl Puzzle {
+    /// Decode the puzzle board
+    pub fn decode_board(&self) -> Result<[u8; 82], String> {
+        let bytes = general_purpose::STANDARD
+            .decode(&self.board)
+            .map_err(|e| format!("Failed to decode base64 board: {}", e))?;
+        bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| format!("Invalid board length: expected 82 bytes, got {}", b.len()))
+    }
+}
+
+/// Solve a position and keep it if it has a single winning first move
+pub fn find_puzzle(solver: &mut Solver, board: &[u8; 82], min_moves: u32) -> Option<Puzzle> {
+    let SolveOutcome::Win { moves, line } = solver.solve(board) else {
+        return None;
+    };
+    if moves < min_moves || solver.winning_moves(board, moves)?.len() != 1 {
+        return None;
+    }
+    Some(Puzzle {
+        board: general_purpose::STANDARD.encode(board),
+        moves,
+        solution: line,
+    })
+}
+
+/// Scan positions in parallel for puzzles; repeated positions are only solved once
+pub fn find_puzzles(positions: &[LabelledPosition], config: &PuzzleConfig) -> Vec<Puzzle> {
+    let mut seen = HashSet::new();
+    let boards: Vec<&[u8; 82]> = positions
+        .iter()
+        .map(|position| &position.board)
+        .filter(|board| seen.insert(zobrist::hash_binary(board)))
+        .collect();
+
+    boards
+        .par_iter()
+        .map_init(
+            || Solver::new(config.solver.clone()),
+            |solver, board| {
+                // Holds stay valid across positions, clearing only bounds memory
+                solver.holds.clear();
+                find_puzzle(solver, board, config.min_moves)
+            },
+        )
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE_KING: u8 = 0x78;
+    const BLACK_KING: u8 = 0x38;
+
+    #[test]
+    fn test_capture_in_one() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = WHITE_KING;
+        board[42] = BLACK_KING;
+        board[44] = COLOR_BIT | 3; // White Commander on the King's row
+
+        let mut solver = Solver::new(SolverConfig::default());
+        let SolveOutcome::Win { moves, line } = solver.solve(&board) else {
+            panic!("capture not found");
+        };
+        assert_eq!(moves, 1);
+        assert_eq!(line.len(), 1);
+        assert!(captures_king(&board, line[0]));
+    }
+
+    #[test]
+    fn test_no_win_and_budget() {
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[0] = WHITE_KING;
+        board[80] = BLACK_KING;
+        let mut solver = Solver::new(SolverConfig { max_moves: 2, max_nodes: 1_000_000 });
+        assert_eq!(solver.solve(&board), SolveOutcome::NoWin);
+
+        let start = crate::Game::new().to_binary();
+        let mut solver = Solver::new(SolverConfig { max_moves: 3, max_nodes: 100 });
+        assert_eq!(solver.solve(&start), SolveOutcome::Unknown);
+    }
+
+    #[test]
+    fn test_two_move_win_is_a_puzzle() {
+        // Lone black King in the corner: White Commander and King take two moves
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[0] = BLACK_KING;
+        board[20] = WHITE_KING;
+        board[13] = COLOR_BIT | 3;
+
+        let mut solver = Solver::new(SolverConfig { max_moves: 2, max_nodes: 10_000_000 });
+        let outcome = solver.solve(&board);
+        let SolveOutcome::Win { moves, line } = &outcome else {
+            panic!("expected a win, got {:?}", outcome);
+        };
+        assert_eq!(*moves, 2);
+        assert_eq!(line.len(), 3);
+
+        // Replaying the main line captures the King on the last move
+        let mut replay = board;
+        for (i, &mv) in line.iter().enumerate() {
+            let undo = make_move(&mut replay, mv).unwrap();
+            assert_eq!(undo.captured_king(), i == line.len() - 1);
+        }
+
+        let winning = solver.winning_moves(&board, 2).unwrap();
+        assert!(winning.contains(&line[0]));
+        let puzzle = find_puzzle(&mut solver, &board, 2);
+        assert_eq!(puzzle.is_some(), winning.len() == 1);
+    }
+}
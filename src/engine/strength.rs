@@ -0,0 +1,216 @@
+//! Difficulty levels and handicapped move selection
+//!
+//! A [`Strength`] maps a named level to search limits and to a [`MoveSelection`]
+//! policy. Strong levels always play the best searched move; weaker ones sample
+//! among the root moves with a softmax over their average scores, and sometimes
+//! blunder on purpose, so casual players get a fair game.
+//!
+//! | Level        | `max_depth` | `simulations_per_move` | Temperature | Blunder chance |
+//! |--------------|-------------|------------------------|-------------|----------------|
+//! | Beginner     | 2           | 50                     | 150         | 15%            |
+//! | Intermediate | 4           | 200                    | 40          | 5%             |
+//! | Advanced     | 6           | 500                    | 0           | 0%             |
+//! | Expert       | 8           | 2000                   | 0           | 0%             |
+//!
+//! Temperatures are in evaluation units (hundredths of a Soldier): a move scoring
+//! one temperature below the best is picked about e times less often.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::{strength::Strength, EngineConfig, MctsEngine};
+//!
+//! let config = Strength::Beginner.apply(EngineConfig::default());
+//! let mut engine = MctsEngine::with_config(config).expect("Failed to create engine");
+//! ```
+
+use super::{EngineConfig, RootMoveStats};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Named difficulty level
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strength {
+    Beginner,
+    Intermediate,
+    Advanced,
+    Expert,
+}
+
+impl Strength {
+    /// All levels, weakest first
+    pub const ALL: [Strength; 4] = [
+        Strength::Beginner,
+        Strength::Intermediate,
+        Strength::Advanced,
+        Strength::Expert,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strength::Beginner => "beginner",
+            Strength::Intermediate => "intermediate",
+            Strength::Advanced => "advanced",
+            Strength::Expert => "expert",
+        }
+    }
+
+    /// Next stronger level, wrapping around to the weakest
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&level| level == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Search depth and simulations per move of the level
+    pub fn limits(self) -> (u32, u32) {
+        match self {
+            Strength::Beginner => (2, 50),
+            Strength::Intermediate => (4, 200),
+            Strength::Advanced => (6, 500),
+            Strength::Expert => (8, 2000),
+        }
+    }
+
+    /// Move selection policy of the level
+    pub fn selection(self) -> MoveSelection {
+        match self {
+            Strength::Beginner => MoveSelection { temperature: 150.0, blunder_chance: 0.15 },
+            Strength::Intermediate => MoveSelection { temperature: 40.0, blunder_chance: 0.05 },
+            Strength::Advanced | Strength::Expert => MoveSelection::default(),
+        }
+    }
+
+    /// Set the search limits and move selection of a configuration to this level,
+    /// keeping its other settings (GPU, evaluation, book, ...)
+    pub fn apply(self, config: EngineConfig) -> EngineConfig {
+        let (max_depth, simulations_per_move) = self.limits();
+        EngineConfig {
+            max_depth,
+            simulations_per_move,
+            move_selection: self.selection(),
+            ..config
+        }
+    }
+}
+
+impl fmt::Display for Strength {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Strength {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|level| level.name().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                let names: Vec<&str> = Self::ALL.iter().map(|level| level.name()).collect();
+                format!("Unknown level '{}' (expected one of: {})", name, names.join(", "))
+            })
+    }
+}
+
+/// How the engine picks its move among the searched root moves
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct MoveSelection {
+    /// Softmax temperature over average root scores (0 always plays the best move)
+    pub temperature: f64,
+    /// Probability of playing a random other searched move instead
+    pub blunder_chance: f64,
+}
+
+impl MoveSelection {
+    /// Whether the policy always plays the best move
+    pub fn is_greedy(&self) -> bool {
+        self.temperature <= 0.0 && self.blunder_chance <= 0.0
+    }
+
+    /// Pick a move among the searched root moves. `decisive` is the score from which a
+    /// move is considered winning (such as capturing the King): winning moves are
+    /// never thrown away by a blunder.
+    pub fn choose(&self, root_moves: &[RootMoveStats], decisive: f64, rng: &mut impl Rng) -> Option<u16> {
+        let searched: Vec<&RootMoveStats> = root_moves.iter().filter(|stats| stats.visits > 0).collect();
+        let best = searched.iter().copied().max_by(|a, b| {
+            a.mean_score().partial_cmp(&b.mean_score()).unwrap_or(std::cmp::Ordering::Equal)
+        })?;
+
+        let others: Vec<&RootMoveStats> = searched.iter().copied().filter(|stats| stats.mv != best.mv).collect();
+        if !others.is_empty()
+            && best.mean_score() < decisive
+            && self.blunder_chance > 0.0
+            && rng.gen_bool(self.blunder_chance.min(1.0))
+        {
+            return Some(others[rng.gen_range(0..others.len())].mv);
+        }
+
+        if self.temperature <= 0.0 {
+            return Some(best.mv);
+        }
+        let weights: Vec<f64> = searched
+            .iter()
+            .map(|stats| ((stats.mean_score() - best.mean_score()) / self.temperature).exp())
+            .collect();
+        let mut pick = rng.gen_range(0.0..weights.iter().sum::<f64>());
+        for (stats, weight) in searched.iter().zip(&weights) {
+            if pick < *weight {
+                return Some(stats.mv);
+            }
+            pick -= weight;
+        }
+        Some(best.mv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn stats(mv: u16, mean: i64) -> RootMoveStats {
+        RootMoveStats { mv, visits: 10, total_score: mean * 10 }
+    }
+
+    #[test]
+    fn test_levels() {
+        for level in Strength::ALL {
+            assert_eq!(level.name().parse::<Strength>(), Ok(level));
+        }
+        assert_eq!("Expert".parse::<Strength>(), Ok(Strength::Expert));
+        assert!("grandmaster".parse::<Strength>().is_err());
+        assert_eq!(Strength::Expert.next(), Strength::Beginner);
+
+        let config = Strength::Beginner.apply(EngineConfig { use_gpu_simulation: false, ..EngineConfig::default() });
+        assert_eq!((config.max_depth, config.simulations_per_move), (2, 50));
+        assert!(!config.use_gpu_simulation);
+        assert!(!config.move_selection.is_greedy());
+        assert!(Strength::Expert.selection().is_greedy());
+    }
+
+    #[test]
+    fn test_selection() {
+        let moves = [stats(1, 100), stats(2, 60), stats(3, -500), RootMoveStats { mv: 4, visits: 0, total_score: 0 }];
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let greedy = MoveSelection::default();
+        assert_eq!(greedy.choose(&moves, 1000.0, &mut rng), Some(1));
+
+        // A warm softmax mixes the two good moves but (almost) never the bad one
+        let warm = MoveSelection { temperature: 40.0, blunder_chance: 0.0 };
+        let picks: Vec<u16> = (0..200).map(|_| warm.choose(&moves, 1000.0, &mut rng).unwrap()).collect();
+        assert!(picks.contains(&1) && picks.contains(&2));
+        assert!(!picks.contains(&3) && !picks.contains(&4));
+
+        // Blunders pick another searched move, but never give up a decisive one
+        let blunder = MoveSelection { temperature: 0.0, blunder_chance: 1.0 };
+        assert_ne!(blunder.choose(&moves, 1000.0, &mut rng), Some(1));
+        assert_eq!(blunder.choose(&moves, 50.0, &mut rng), Some(1));
+        assert_eq!(blunder.choose(&[], 50.0, &mut rng), None);
+    }
+}
@@ -0,0 +1,930 @@
+//! Retrograde endgame tablebases
+//!
+//! A tablebase stores, for every position of a small material class, whether the
+//! player to move wins, loses or draws with perfect play, and the number of plies
+//! until a King is captured. Tables are generated on the CPU by retrograde analysis
+//! and probed by the engine at the root and at rollout leaves.
+//!
+//! # Material classes
+//!
+//! A class is written like `KPG-KS`: the white pieces, a dash, then the black pieces,
+//! using the letters of the CLI (S, J, C, P, G, D, B) with each King written as `K`.
+//! Stacks count as distinct material: a class holds one table per arrangement of its
+//! pieces into single pieces and stacks (`KPG-K` covers K+P+G, K+P/G and K+G/P), since
+//! stacking and unstacking moves between them. Captures lead to smaller classes, which
+//! are generated first. Classes are limited to [`MAX_PIECES`] pieces besides the
+//! Kings, which keeps the largest table at 2·81⁴ entries.
+//!
+//! # Generation
+//!
+//! Every position is first scored from its captures (a King capture wins in one ply,
+//! other captures are looked up in the smaller classes) and its count of quiet moves
+//! is recorded. Positions are then resolved level by level: a position lost in `n`
+//! plies makes all its predecessors (found by generating un-moves) won in `n + 1`,
+//! and a position whose last undecided move turns out to lead to a win for the
+//! opponent is lost. Whatever is left unresolved is a draw. A player with no move at
+//! all is scored as a draw.
+//!
+//! # File format (version 1)
+//!
+//! One little-endian file per class, named `<class>.arxt`: the magic bytes `ARXT`, a
+//! `u32` version, the class name (`u8` length and ASCII bytes), a `u32` table count,
+//! then for each table its units (`u8` count and full piece codes, white units first),
+//! a `u32` run count and the run-length encoded values as (`u32` first index, `u16`
+//! value) pairs. Values are 0 for a draw, odd for a win and even for a loss, in plies.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use arx_engine::engine::tablebase::{MaterialClass, Tablebases};
+//!
+//! let mut tablebases = Tablebases::default();
+//! tablebases.generate(&MaterialClass::parse("KC-K").unwrap(), |name| println!("Solved {}", name))
+//!     .expect("Generation failed");
+//! tablebases.save_dir("tablebases").expect("Failed to save tablebases");
+//! ```
+
+use super::cpu_move_gen::{for_each_move, generate_moves, movement};
+use super::make_move;
+use rayon::prelude::*;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+const BOARD_SIZE: usize = 81;
+const BOARD_DIM: isize = 9;
+const COLOR_BIT: u8 = 0x40;
+const KING_PAYLOAD: u8 = 0x38;
+const PIECE_LETTERS: [char; 8] = ['?', 'S', 'J', 'C', 'P', 'G', 'D', 'B'];
+
+/// Most pieces a class may hold besides the two Kings
+pub const MAX_PIECES: usize = 2;
+
+const MAGIC: &[u8; 4] = b"ARXT";
+const FORMAT_VERSION: u32 = 1;
+
+// Generation state: a resolved value has this bit set, a position with a drawing
+// escape never runs out of undecided moves
+const RESOLVED: u16 = 0x8000;
+const ESCAPE: u8 = u8::MAX;
+
+/// Tablebase result for the player to move, with the number of plies to King capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TbValue {
+    Win(u16),
+    Loss(u16),
+    Draw,
+}
+
+impl TbValue {
+    fn from_raw(raw: u16) -> Self {
+        match raw {
+            0 => TbValue::Draw,
+            plies if plies % 2 == 1 => TbValue::Win(plies),
+            plies => TbValue::Loss(plies),
+        }
+    }
+
+    /// Value for the player who moved into a position with this value
+    pub fn for_previous_player(self) -> Self {
+        match self {
+            TbValue::Win(plies) => TbValue::Loss(plies + 1),
+            TbValue::Loss(plies) => TbValue::Win(plies + 1),
+            TbValue::Draw => TbValue::Draw,
+        }
+    }
+
+    /// Engine score: close to `king_value` for a win, shorter wins (and longer losses) preferred
+    pub fn score(self, king_value: i32) -> i32 {
+        match self {
+            TbValue::Win(plies) => king_value - plies as i32,
+            TbValue::Loss(plies) => plies as i32 - king_value,
+            TbValue::Draw => 0,
+        }
+    }
+}
+
+/// Pieces of each side besides the Kings, as sorted piece codes
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MaterialClass {
+    white: Vec<u8>,
+    black: Vec<u8>,
+}
+
+impl MaterialClass {
+    /// Create a class from piece codes (1 to 7)
+    pub fn new(white: &[u8], black: &[u8]) -> Result<Self, String> {
+        if white.iter().chain(black).any(|&code| !(1..=7).contains(&code)) {
+            return Err("Invalid piece code".to_string());
+        }
+        if white.len() + black.len() > MAX_PIECES {
+            return Err(format!(
+                "Tablebases are limited to {} pieces besides the Kings",
+                MAX_PIECES
+            ));
+        }
+        let mut white = white.to_vec();
+        let mut black = black.to_vec();
+        white.sort_unstable();
+        black.sort_unstable();
+        Ok(Self { white, black })
+    }
+
+    /// Parse a class name such as `KPG-KS`
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let side = |part: &str| -> Result<Vec<u8>, String> {
+            let pieces = part
+                .strip_prefix('K')
+                .ok_or_else(|| format!("Each side of '{}' must start with its King", name))?;
+            pieces
+                .chars()
+                .map(|c| {
+                    PIECE_LETTERS
+                        .iter()
+                        .skip(1)
+                        .position(|&letter| letter == c.to_ascii_uppercase())
+                        .map(|index| index as u8 + 1)
+                        .ok_or_else(|| format!("Unknown piece '{}' in '{}'", c, name))
+                })
+                .collect()
+        };
+        let (white, black) = name
+            .split_once('-')
+            .ok_or_else(|| format!("Expected '<white>-<black>' such as 'KC-K', got '{}'", name))?;
+        Self::new(&side(white)?, &side(black)?)
+    }
+
+    /// Class name such as `KPG-KS`
+    pub fn name(&self) -> String {
+        let side = |pieces: &[u8]| -> String {
+            std::iter::once('K')
+                .chain(pieces.iter().map(|&code| PIECE_LETTERS[code as usize]))
+                .collect()
+        };
+        format!("{}-{}", side(&self.white), side(&self.black))
+    }
+
+    /// Every arrangement of the pieces into single pieces and stacks
+    fn signatures(&self) -> Vec<Signature> {
+        let mut signatures = Vec::new();
+        for white in partitions(&self.white) {
+            for black in partitions(&self.black) {
+                signatures.push(Signature::new(&white, &black));
+            }
+        }
+        signatures
+    }
+
+    /// Classes reachable by capturing a single piece or a stack
+    fn subclasses(&self) -> Vec<MaterialClass> {
+        let mut classes = BTreeSet::new();
+        for (pieces, is_white) in [(&self.white, true), (&self.black, false)] {
+            for i in 0..pieces.len() {
+                let mut remaining = pieces.clone();
+                remaining.remove(i);
+                for j in 0..=remaining.len() {
+                    let mut after = remaining.clone();
+                    if j < remaining.len() {
+                        after.remove(j);
+                    }
+                    let class = if is_white {
+                        MaterialClass::new(&after, &self.black)
+                    } else {
+                        MaterialClass::new(&self.white, &after)
+                    };
+                    classes.insert(class.expect("subclass of a valid class"));
+                }
+            }
+        }
+        classes.into_iter().collect()
+    }
+}
+
+impl fmt::Display for MaterialClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// All ways to group pieces into single pieces and (top, bottom) stacks, as sorted payloads
+fn partitions(pieces: &[u8]) -> Vec<Vec<u8>> {
+    fn recurse(rest: &[u8], current: &mut Vec<u8>, out: &mut BTreeSet<Vec<u8>>) {
+        let Some((&first, rest)) = rest.split_first() else {
+            let mut units = current.clone();
+            units.sort_unstable();
+            out.insert(units);
+            return;
+        };
+        current.push(first);
+        recurse(rest, current, out);
+        current.pop();
+        for i in 0..rest.len() {
+            let mut others = rest.to_vec();
+            let second = others.remove(i);
+            for stack in [(first << 3) | second, (second << 3) | first] {
+                current.push(stack);
+                recurse(&others, current, out);
+                current.pop();
+            }
+        }
+    }
+    let mut out = BTreeSet::new();
+    recurse(pieces, &mut Vec::new(), &mut out);
+    out.into_iter().collect()
+}
+
+/// Units (single pieces or stacks) besides the Kings, as full piece codes:
+/// white units first, each side sorted by payload
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Signature {
+    units: [u8; MAX_PIECES],
+    count: usize,
+}
+
+impl Signature {
+    fn new(white: &[u8], black: &[u8]) -> Self {
+        let mut signature = Signature {
+            units: [0; MAX_PIECES],
+            count: 0,
+        };
+        for code in white.iter().map(|&payload| COLOR_BIT | payload).chain(black.iter().copied()) {
+            signature.units[signature.count] = code;
+            signature.count += 1;
+        }
+        signature
+    }
+
+    fn units(&self) -> &[u8] {
+        &self.units[..self.count]
+    }
+
+    /// Full piece codes in index order: white King, black King, then the units
+    fn codes(&self) -> Vec<u8> {
+        let mut codes = vec![COLOR_BIT | KING_PAYLOAD, KING_PAYLOAD];
+        codes.extend_from_slice(self.units());
+        codes
+    }
+
+    fn size(&self) -> usize {
+        2 * BOARD_SIZE.pow(2 + self.count as u32)
+    }
+
+    /// Signature and canonical index of a board, if it has exactly one King per side
+    fn locate(board: &[u8; 82]) -> Option<(Signature, usize)> {
+        Self::locate_squares(board, 0..BOARD_SIZE)
+    }
+
+    /// Same as [`Signature::locate`] when all pieces are known to be on `squares` (in ascending order)
+    fn locate_squares(board: &[u8; 82], squares: impl Iterator<Item = usize>) -> Option<(Signature, usize)> {
+        let mut kings = [None, None];
+        let mut units = [(0u8, 0usize); MAX_PIECES];
+        let mut count = 0;
+        for square in squares {
+            let code = board[square];
+            if code == 0 {
+                continue;
+            }
+            if code & 0x3F == KING_PAYLOAD {
+                let king = &mut kings[(code & COLOR_BIT == 0) as usize];
+                if king.replace(square).is_some() {
+                    return None;
+                }
+            } else {
+                if count == MAX_PIECES {
+                    return None;
+                }
+                units[count] = (code, square);
+                count += 1;
+            }
+        }
+        let (Some(white_king), Some(black_king)) = (kings[0], kings[1]) else {
+            return None;
+        };
+
+        // White units first, then by payload; identical units by ascending square
+        let units = &mut units[..count];
+        units.sort_unstable_by_key(|&(code, square)| (code & COLOR_BIT == 0, code & 0x3F, square));
+        let mut signature = Signature {
+            units: [0; MAX_PIECES],
+            count,
+        };
+        for (unit, &(code, _)) in signature.units.iter_mut().zip(units.iter()) {
+            *unit = code;
+        }
+
+        let mut index = 0;
+        for &square in units.iter().rev().map(|(_, s)| s).chain([&black_king, &white_king]) {
+            index = index * BOARD_SIZE + square;
+        }
+        Some((signature, index * 2 + board[BOARD_SIZE] as usize))
+    }
+
+    /// Board of an index, or `None` if the index is not a canonical position
+    fn decode(&self, codes: &[u8], index: usize) -> Option<[u8; 82]> {
+        let mut board = [0u8; 82];
+        board[BOARD_SIZE] = (index % 2) as u8;
+        let mut rest = index / 2;
+        let mut previous: Option<(u8, usize)> = None;
+        for &code in codes {
+            let square = rest % BOARD_SIZE;
+            rest /= BOARD_SIZE;
+            if board[square] != 0 {
+                return None;
+            }
+            // Identical units must be in ascending square order
+            if let Some((previous_code, previous_square)) = previous {
+                if previous_code == code && previous_square > square {
+                    return None;
+                }
+            }
+            board[square] = code;
+            previous = Some((code, square));
+        }
+        Some(board)
+    }
+}
+
+/// A solved table, run-length encoded
+#[derive(Clone, Debug, PartialEq)]
+struct Table {
+    starts: Vec<u32>,
+    values: Vec<u16>,
+}
+
+impl Table {
+    fn compress(values: impl Iterator<Item = u16>) -> Self {
+        let mut table = Table {
+            starts: Vec::new(),
+            values: Vec::new(),
+        };
+        for (index, value) in values.enumerate() {
+            if table.values.last() != Some(&value) {
+                table.starts.push(index as u32);
+                table.values.push(value);
+            }
+        }
+        table
+    }
+
+    fn get(&self, index: usize) -> u16 {
+        let run = self.starts.partition_point(|&start| start as usize <= index);
+        self.values[run - 1]
+    }
+}
+
+/// A collection of solved material classes
+#[derive(Clone, Default)]
+pub struct Tablebases {
+    classes: Vec<MaterialClass>,
+    tables: HashMap<Signature, Table>,
+}
+
+impl fmt::Debug for Tablebases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<String> = self.classes.iter().map(MaterialClass::name).collect();
+        write!(f, "Tablebases({})", names.join(", "))
+    }
+}
+
+impl Tablebases {
+    /// Solved material classes
+    pub fn classes(&self) -> &[MaterialClass] {
+        &self.classes
+    }
+
+    /// Whether a class has been solved or loaded
+    pub fn contains(&self, class: &MaterialClass) -> bool {
+        self.classes.contains(class)
+    }
+
+    /// Value of a position for the player to move, if its material is covered
+    pub fn probe(&self, board: &[u8; 82]) -> Option<TbValue> {
+        let (signature, index) = Signature::locate(board)?;
+        self.tables
+            .get(&signature)
+            .map(|table| TbValue::from_raw(table.get(index)))
+    }
+
+    /// Best move of a covered position with its value: the fastest win, else a draw,
+    /// else the slowest loss
+    pub fn best_move(&self, board: &[u8; 82]) -> Option<(u16, TbValue)> {
+        let rank = |value: TbValue| match value {
+            TbValue::Win(plies) => i32::MAX - plies as i32,
+            TbValue::Draw => 0,
+            TbValue::Loss(plies) => i32::MIN + plies as i32,
+        };
+
+        self.probe(board)?;
+        let mut best: Option<(u16, TbValue)> = None;
+        for mv in generate_moves(board) {
+            let mut child = *board;
+            let undo = make_move(&mut child, mv).ok()?;
+            let value = if undo.captured_king() {
+                TbValue::Win(1)
+            } else {
+                self.probe(&child)?.for_previous_player()
+            };
+            if best.is_none_or(|(_, best_value)| rank(value) > rank(best_value)) {
+                best = Some((mv, value));
+            }
+        }
+        best
+    }
+
+    /// Solve a class and any smaller class its captures lead to.
+    /// `on_solved` is called with the name of each class once it is solved.
+    pub fn generate(&mut self, class: &MaterialClass, mut on_solved: impl FnMut(&str)) -> Result<(), String> {
+        self.generate_recursive(class, &mut on_solved)
+    }
+
+    fn generate_recursive(&mut self, class: &MaterialClass, on_solved: &mut dyn FnMut(&str)) -> Result<(), String> {
+        if self.contains(class) {
+            return Ok(());
+        }
+        for subclass in class.subclasses() {
+            self.generate_recursive(&subclass, on_solved)?;
+        }
+
+        let solver = ClassSolver::new(class, self);
+        for (signature, table) in solver.solve() {
+            self.tables.insert(signature, table);
+        }
+        self.classes.push(class.clone());
+        on_solved(&class.name());
+        Ok(())
+    }
+
+    /// Serialize one class (see the module documentation for the format)
+    fn class_to_bytes(&self, class: &MaterialClass) -> Vec<u8> {
+        let name = class.name();
+        let signatures = class.signatures();
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bytes.push(name.len() as u8);
+        bytes.extend_from_slice(name.as_bytes());
+        bytes.extend_from_slice(&(signatures.len() as u32).to_le_bytes());
+        for signature in &signatures {
+            let table = &self.tables[signature];
+            bytes.push(signature.count as u8);
+            bytes.extend_from_slice(signature.units());
+            bytes.extend_from_slice(&(table.starts.len() as u32).to_le_bytes());
+            for (start, value) in table.starts.iter().zip(&table.values) {
+                bytes.extend_from_slice(&start.to_le_bytes());
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Load one class file's tables
+    fn add_class_bytes(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let mut reader = Reader { bytes, offset: 0 };
+        if reader.take(4)? != MAGIC {
+            return Err("Not an Arx tablebase file".to_string());
+        }
+        let version = reader.u32()?;
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported tablebase version {} (expected {})",
+                version, FORMAT_VERSION
+            ));
+        }
+        let name_length = reader.take(1)?[0] as usize;
+        let name = String::from_utf8_lossy(reader.take(name_length)?).to_string();
+        let class = MaterialClass::parse(&name)?;
+
+        let count = reader.u32()?;
+        for _ in 0..count {
+            let unit_count = reader.take(1)?[0] as usize;
+            if unit_count > MAX_PIECES {
+                return Err(format!("Too many units in {}", name));
+            }
+            let mut signature = Signature {
+                units: [0; MAX_PIECES],
+                count: unit_count,
+            };
+            signature.units[..unit_count].copy_from_slice(reader.take(unit_count)?);
+            let runs = reader.u32()? as usize;
+            let mut table = Table {
+                starts: Vec::with_capacity(runs),
+                values: Vec::with_capacity(runs),
+            };
+            for _ in 0..runs {
+                table.starts.push(reader.u32()?);
+                table.values.push(u16::from_le_bytes(reader.take(2)?.try_into().unwrap()));
+            }
+            if table.starts.first() != Some(&0) {
+                return Err(format!("Corrupt table in {}", name));
+            }
+            self.tables.insert(signature, table);
+        }
+        if reader.offset != bytes.len() {
+            return Err(format!("Trailing data in {}", name));
+        }
+        if !self.contains(&class) {
+            self.classes.push(class);
+        }
+        Ok(())
+    }
+
+    /// Write one `<class>.arxt` file per solved class into a directory
+    pub fn save_dir(&self, dir: impl AsRef<Path>) -> Result<(), String> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create directory {}: {}", dir.display(), e))?;
+        for class in &self.classes {
+            let path = dir.join(format!("{}.arxt", class.name()));
+            std::fs::write(&path, self.class_to_bytes(class))
+                .map_err(|e| format!("Failed to write tablebase {}: {}", path.display(), e))?;
+        }
+        Ok(())
+    }
+
+    /// Load every `.arxt` file of a directory
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self, String> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?;
+        let mut tablebases = Self::default();
+        for entry in entries {
+            let path = entry.map_err(|e| format!("Failed to read directory {}: {}", dir.display(), e))?.path();
+            if path.extension().is_some_and(|ext| ext == "arxt") {
+                let bytes = std::fs::read(&path)
+                    .map_err(|e| format!("Failed to read tablebase {}: {}", path.display(), e))?;
+                tablebases
+                    .add_class_bytes(&bytes)
+                    .map_err(|e| format!("{}: {}", path.display(), e))?;
+            }
+        }
+        tablebases.classes.sort();
+        Ok(tablebases)
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let slice = self
+            .bytes
+            .get(self.offset..self.offset + count)
+            .ok_or("Unexpected end of tablebase file")?;
+        self.offset += count;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+}
+
+/// Retrograde solver for all the tables of one class
+struct ClassSolver<'a> {
+    signatures: Vec<Signature>,
+    codes: Vec<Vec<u8>>,
+    offsets: Vec<usize>,
+    smaller: &'a Tablebases,
+}
+
+impl<'a> ClassSolver<'a> {
+    fn new(class: &MaterialClass, smaller: &'a Tablebases) -> Self {
+        let signatures = class.signatures();
+        let mut offsets = vec![0];
+        for signature in &signatures {
+            offsets.push(offsets.last().unwrap() + signature.size());
+        }
+        Self {
+            codes: signatures.iter().map(Signature::codes).collect(),
+            signatures,
+            offsets,
+            smaller,
+        }
+    }
+
+    fn len(&self) -> usize {
+        *self.offsets.last().unwrap()
+    }
+
+    fn decode(&self, position: usize) -> Option<[u8; 82]> {
+        let table = self.offsets.partition_point(|&offset| offset <= position) - 1;
+        self.signatures[table].decode(&self.codes[table], position - self.offsets[table])
+    }
+
+    /// Position of a board whose pieces all stand on `squares` (in ascending order)
+    fn position_on(&self, board: &[u8; 82], squares: impl Iterator<Item = usize>) -> Option<usize> {
+        let (signature, index) = Signature::locate_squares(board, squares)?;
+        let table = self.signatures.iter().position(|s| *s == signature)?;
+        Some(self.offsets[table] + index)
+    }
+
+    /// Initial value (win or loss candidate from captures) and number of quiet successors
+    fn initial_state(&self, position: usize) -> (u16, u8) {
+        let Some(board) = self.decode(position) else {
+            return (RESOLVED, 0);
+        };
+
+        let mut win: Option<u16> = None;
+        let mut loss = 0u16;
+        let mut escape = false;
+        let mut any_move = false;
+        let mut quiet = 0usize;
+        for_each_move(&board, board[BOARD_SIZE] == 1, |mv| {
+            any_move = true;
+            let mut child = board;
+            let Ok(undo) = make_move(&mut child, mv) else {
+                return;
+            };
+            if undo.captured_king() {
+                win = Some(1);
+            } else if undo.is_capture() {
+                match self.smaller.probe(&child).map(TbValue::for_previous_player) {
+                    Some(TbValue::Win(plies)) => win = Some(win.map_or(plies, |w| w.min(plies))),
+                    Some(TbValue::Loss(plies)) => loss = loss.max(plies),
+                    _ => escape = true,
+                }
+            } else {
+                // Distinct quiet moves always lead to distinct positions of this class
+                quiet += 1;
+            }
+        });
+
+        if !any_move {
+            return (RESOLVED, 0);
+        }
+        let counter = if escape { ESCAPE } else { quiet as u8 };
+        (win.unwrap_or(loss), counter)
+    }
+
+    /// Positions with a quiet move leading to `position` (un-move generation)
+    fn predecessors(&self, position: usize) -> Vec<usize> {
+        let Some(board) = self.decode(position) else {
+            return Vec::new();
+        };
+        // The previous move was made by the player not to move now
+        let mover_white = board[BOARD_SIZE] == 0;
+        let mover_color = if mover_white { COLOR_BIT } else { 0 };
+        let mut previous = board;
+        previous[BOARD_SIZE] = mover_white as u8;
+        let is_single = |code: u8| {
+            code != 0 && code & COLOR_BIT == mover_color && code & 0x38 == 0
+        };
+
+        let occupied: Vec<usize> = (0..BOARD_SIZE).filter(|&square| board[square] != 0).collect();
+
+        let mut result = Vec::new();
+        let mut emit = |from: usize, from_code: u8, to: usize, to_code: u8| {
+            let mut before = previous;
+            before[from] = from_code;
+            before[to] = to_code;
+            // Only the occupied squares and the origin square can hold pieces
+            let mut squares = occupied.clone();
+            if let Err(slot) = squares.binary_search(&from) {
+                squares.insert(slot, from);
+            }
+            if let Some(p) = self.position_on(&before, squares.into_iter()) {
+                result.push(p);
+            }
+        };
+
+        for to in 0..BOARD_SIZE {
+            let code = board[to];
+            if code == 0 || code & COLOR_BIT != mover_color {
+                continue;
+            }
+            let payload = code & 0x3F;
+            let top = (payload >> 3) & 0x07;
+            let bottom = payload & 0x07;
+            let is_stack = payload != KING_PAYLOAD && top != 0;
+
+            // The unit arrived whole: single piece, King or stack (moving with its bottom piece)
+            let mover = if payload == KING_PAYLOAD { 0 } else { bottom };
+            reverse_squares(to, mover, mover_white, &board, |from, occupied| {
+                if !occupied {
+                    emit(from, code, to, 0);
+                }
+            });
+
+            if is_stack {
+                // The top piece arrived on the bottom one, alone or leaving its own stack
+                reverse_squares(to, top, mover_white, &board, |from, occupied| {
+                    if !occupied {
+                        emit(from, mover_color | top, to, mover_color | bottom);
+                    } else if is_single(board[from]) {
+                        emit(from, mover_color | (top << 3) | (board[from] & 0x07), to, mover_color | bottom);
+                    }
+                });
+            } else if payload != KING_PAYLOAD {
+                // The single piece was unstacked from a piece that stayed behind
+                reverse_squares(to, bottom, mover_white, &board, |from, occupied| {
+                    if occupied && is_single(board[from]) {
+                        emit(from, mover_color | (bottom << 3) | (board[from] & 0x07), to, 0);
+                    }
+                });
+            }
+        }
+
+        result
+    }
+
+    fn solve(&self) -> Vec<(Signature, Table)> {
+        let total = self.len();
+        let (mut values, mut counters): (Vec<u16>, Vec<u8>) = (0..total)
+            .into_par_iter()
+            .map(|position| self.initial_state(position))
+            .unzip();
+
+        let mut highest = values
+            .par_iter()
+            .filter(|&&v| v & RESOLVED == 0)
+            .copied()
+            .max()
+            .unwrap_or(0);
+
+        let mut plies = 1;
+        while plies <= highest {
+            let level: Vec<usize> = (0..total)
+                .into_par_iter()
+                .filter(|&p| values[p] == plies && (plies % 2 == 1 || counters[p] == 0))
+                .collect();
+            for &position in &level {
+                values[position] |= RESOLVED;
+            }
+
+            for &position in &level {
+                for predecessor in self.predecessors(position) {
+                    let value = values[predecessor];
+                    if value & RESOLVED != 0 {
+                        continue;
+                    }
+                    if plies % 2 == 0 {
+                        // Moving into a lost position wins
+                        if value % 2 == 0 || value > plies + 1 {
+                            values[predecessor] = plies + 1;
+                            highest = highest.max(plies + 1);
+                        }
+                    } else if counters[predecessor] != ESCAPE {
+                        counters[predecessor] -= 1;
+                        if counters[predecessor] == 0 && value % 2 == 0 {
+                            // Every move loses: as slowly as possible
+                            values[predecessor] = value.max(plies + 1);
+                            highest = highest.max(plies + 1);
+                        }
+                    }
+                }
+            }
+            plies += 1;
+        }
+
+        // Unresolved positions are draws
+        let finals = values
+            .into_iter()
+            .map(|v| if v & RESOLVED != 0 { v & !RESOLVED } else { 0 });
+        let finals: Vec<u16> = finals.collect();
+        self.signatures
+            .iter()
+            .enumerate()
+            .map(|(i, signature)| {
+                let range = self.offsets[i]..self.offsets[i + 1];
+                (*signature, Table::compress(finals[range].iter().copied()))
+            })
+            .collect()
+    }
+}
+
+/// Call `visit(from, occupied)` for each square a piece could have come from to reach `to`.
+/// Sliding stops at the first occupied square (which is still visited).
+fn reverse_squares(
+    to: usize,
+    piece_code: u8,
+    white: bool,
+    board: &[u8; 82],
+    mut visit: impl FnMut(usize, bool),
+) {
+    let (directions, range) = movement(piece_code, white as u8);
+    let x = (to % 9) as isize;
+    let y = (to / 9) as isize;
+    for &(dx, dy) in directions {
+        for distance in 1..=range {
+            let fx = x - dx * distance;
+            let fy = y - dy * distance;
+            if !(0..BOARD_DIM).contains(&fx) || !(0..BOARD_DIM).contains(&fy) {
+                break;
+            }
+            let from = (fy * BOARD_DIM + fx) as usize;
+            let occupied = board[from] != 0;
+            visit(from, occupied);
+            if occupied {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Check every covered position against a one-ply search over its children
+    fn assert_consistent(tablebases: &Tablebases, class: &MaterialClass, step: usize) {
+        let solver = ClassSolver::new(class, tablebases);
+        for position in (0..solver.len()).step_by(step) {
+            let Some(board) = solver.decode(position) else {
+                continue;
+            };
+            let value = tablebases.probe(&board).unwrap();
+            let expected = match tablebases.best_move(&board) {
+                Some((_, best)) => best,
+                None => TbValue::Draw,
+            };
+            assert_eq!(value, expected, "position {}", position);
+        }
+    }
+
+    #[test]
+    fn test_class_names() {
+        let class = MaterialClass::parse("KPG-K").unwrap();
+        assert_eq!(class.name(), "KPG-K");
+        assert_eq!(class.signatures().len(), 3);
+        assert_eq!(class.subclasses().len(), 3);
+        assert!(MaterialClass::parse("KPGS-K").is_err());
+        assert!(MaterialClass::parse("PG-K").is_err());
+    }
+
+    #[test]
+    fn test_un_moves_match_moves() {
+        let class = MaterialClass::parse("KPG-K").unwrap();
+        let tablebases = Tablebases::default();
+        let solver = ClassSolver::new(&class, &tablebases);
+
+        let mut stacked = [0u8; 82];
+        stacked[81] = 1;
+        stacked[0] = COLOR_BIT | KING_PAYLOAD;
+        stacked[80] = KING_PAYLOAD;
+        stacked[40] = COLOR_BIT | (4 << 3) | 5; // White Paladin on a Guard
+        let mut separate = stacked;
+        separate[40] = COLOR_BIT | 4;
+        separate[42] = COLOR_BIT | 5;
+
+        // Every quiet move (including stacking and unstacking) must be found backwards
+        for board in [stacked, separate] {
+            let position = solver.position_on(&board, 0..BOARD_SIZE).unwrap();
+            for mv in generate_moves(&board) {
+                let mut child = board;
+                let undo = make_move(&mut child, mv).unwrap();
+                if undo.is_capture() {
+                    continue;
+                }
+                let child_position = solver.position_on(&child, 0..BOARD_SIZE).unwrap();
+                assert!(solver.predecessors(child_position).contains(&position), "move {}", mv);
+            }
+        }
+    }
+
+    #[test]
+    fn test_kings_only() {
+        let class = MaterialClass::parse("K-K").unwrap();
+        let mut tablebases = Tablebases::default();
+        tablebases.generate(&class, |_| {}).unwrap();
+
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[40] = COLOR_BIT | KING_PAYLOAD;
+        board[41] = KING_PAYLOAD;
+        assert_eq!(tablebases.probe(&board), Some(TbValue::Win(1)));
+        assert_eq!(tablebases.best_move(&board).unwrap().0, 40 | (41 << 7));
+
+        board[41] = 0;
+        board[0] = KING_PAYLOAD;
+        assert_eq!(tablebases.probe(&board), Some(TbValue::Draw));
+        assert_consistent(&tablebases, &class, 1);
+
+        let dir = std::env::temp_dir().join(format!("arx_tb_{}", std::process::id()));
+        tablebases.save_dir(&dir).unwrap();
+        let loaded = Tablebases::load_dir(&dir).unwrap();
+        assert_eq!(loaded.classes(), tablebases.classes());
+        assert_eq!(loaded.probe(&board), Some(TbValue::Draw));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_commander_wins() {
+        let class = MaterialClass::parse("KC-K").unwrap();
+        let mut tablebases = Tablebases::default();
+        let mut solved = Vec::new();
+        tablebases.generate(&class, |name| solved.push(name.to_string())).unwrap();
+        assert_eq!(solved, ["K-K", "KC-K"]);
+
+        // Black King in the corner, white Commander and King far away, white to move
+        let mut board = [0u8; 82];
+        board[81] = 1;
+        board[0] = KING_PAYLOAD;
+        board[80] = COLOR_BIT | KING_PAYLOAD;
+        board[44] = COLOR_BIT | 3;
+        assert!(matches!(tablebases.probe(&board), Some(TbValue::Win(plies)) if plies > 1));
+        assert_consistent(&tablebases, &class, 997);
+    }
+}
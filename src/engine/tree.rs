@@ -0,0 +1,137 @@
+//! Search statistics kept between engine calls
+//!
+//! [`SearchTree`] maps positions (by Zobrist hash) to the accumulated statistics of
+//! their moves, so a search adds its simulations to what earlier searches and
+//! pondering already learned instead of starting from nothing. Rollouts, on the CPU
+//! or the GPU, also record their first plies, which fills the nodes of the opponent's
+//! replies and of our next positions: after our move and the opponent's reply, the
+//! next search starts from the subtree gathered by the previous one.
+//!
+//! # Re-rooting
+//!
+//! Every search starts a new generation. Nodes not touched by the last two
+//! generations are dropped when a search starts, which keeps the subtree below the
+//! previous root (and the pondered position) while discarding older lines. The tree
+//! never grows past its capacity; nodes that would exceed it are not stored.
+
+use super::RootMoveStats;
+use std::collections::HashMap;
+
+/// Generations a node survives without being touched
+const KEPT_GENERATIONS: u32 = 2;
+
+#[derive(Clone, Debug)]
+struct TreeNode {
+    moves: Vec<RootMoveStats>,
+    generation: u32,
+}
+
+/// Move statistics of searched positions, keyed by Zobrist hash
+#[derive(Clone, Debug)]
+pub struct SearchTree {
+    nodes: HashMap<u64, TreeNode>,
+    generation: u32,
+    capacity: usize,
+}
+
+impl SearchTree {
+    /// Empty tree holding at most `capacity` positions
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            nodes: HashMap::new(),
+            generation: 0,
+            capacity,
+        }
+    }
+
+    /// Number of stored positions
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Forget everything
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+    }
+
+    /// Statistics of the moves of a position, if it was searched
+    pub fn moves(&self, hash: u64) -> Option<&[RootMoveStats]> {
+        self.nodes.get(&hash).map(|node| node.moves.as_slice())
+    }
+
+    /// Most visited move of a position
+    pub fn most_visited(&self, hash: u64) -> Option<u16> {
+        self.moves(hash)?
+            .iter()
+            .filter(|stats| stats.visits > 0)
+            .max_by_key(|stats| (stats.visits, std::cmp::Reverse(stats.mv)))
+            .map(|stats| stats.mv)
+    }
+
+    /// Start a new search generation, dropping the nodes outside the recent subtrees
+    pub fn advance(&mut self) {
+        self.generation += 1;
+        let generation = self.generation;
+        self.nodes
+            .retain(|_, node| generation - node.generation <= KEPT_GENERATIONS);
+    }
+
+    /// Add simulation results to a position's statistics
+    pub fn record(&mut self, hash: u64, results: &[RootMoveStats]) {
+        if !self.nodes.contains_key(&hash) && self.nodes.len() >= self.capacity {
+            return;
+        }
+        let node = self.nodes.entry(hash).or_insert_with(|| TreeNode {
+            moves: Vec::new(),
+            generation: 0,
+        });
+        node.generation = self.generation;
+        for result in results {
+            match node.moves.iter_mut().find(|stats| stats.mv == result.mv) {
+                Some(stats) => {
+                    stats.visits += result.visits;
+                    stats.total_score += result.total_score;
+                }
+                None => node.moves.push(*result),
+            }
+        }
+    }
+
+    /// Add a single simulation result for one move of a position
+    pub fn record_visit(&mut self, hash: u64, mv: u16, score: i32) {
+        self.record(hash, &[RootMoveStats { mv, visits: 1, total_score: score as i64 }]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_merge_and_age_out() {
+        let mut tree = SearchTree::new(2);
+        tree.advance();
+        tree.record(1, &[RootMoveStats { mv: 10, visits: 2, total_score: 50 }]);
+        tree.record_visit(1, 10, 30);
+        tree.record_visit(1, 11, -5);
+        tree.record_visit(2, 10, 0);
+        tree.record_visit(3, 10, 0); // Over capacity
+        assert_eq!(tree.len(), 2);
+
+        let moves = tree.moves(1).unwrap();
+        assert_eq!(moves[0], RootMoveStats { mv: 10, visits: 3, total_score: 80 });
+        assert_eq!(tree.most_visited(1), Some(10));
+
+        // Position 2 is touched again, position 1 ages out
+        tree.advance();
+        tree.advance();
+        tree.record_visit(2, 10, 0);
+        tree.advance();
+        assert!(tree.moves(1).is_none());
+        assert!(tree.moves(2).is_some());
+    }
+}
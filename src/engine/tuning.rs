@@ -0,0 +1,356 @@
+//! Texel-style tuning of evaluation weights
+//!
+//! Given a set of positions labelled with the final game result, the tuner looks for
+//! the [`EvalWeights`] that best predict those results. A score `s` (from white's
+//! perspective, in hundredths of a Soldier) is mapped to an expected result with
+//! `1 / (1 + 10^(-k * s / 400))`, and the mean squared error between expected and
+//! actual results is minimized by a local search on each weight in turn.
+//!
+//! The Soldier value is kept fixed as the unit of the scale and the King value is not
+//! tuned (both kings are on the board in every non-terminal position). Everything runs
+//! on the CPU: evaluation terms are extracted once per position with
+//! [`EvalFeatures::extract`], after which scoring is a cheap dot product.
+//!
+//! # Position file format
+//!
+//! One position per line: the base64 encoded 82-byte board (as printed by `arx`)
+//! followed by the game result from white's perspective, either `1-0`, `0-1`,
+//! `1/2-1/2` or a number between 0 and 1. Empty lines and lines starting with `#`
+//! are ignored. Lines of a self-play dataset (see [`super::selfplay`]) are accepted
+//! as well.
+//!
+//! ```text
+//! # board result
+//! BQYE...AAE= 1-0
+//! BQYE...AAA= 1/2-1/2
+//! ```
+
+use super::eval::{EvalFeatures, EvalWeights};
+use super::selfplay::PositionRecord;
+use base64::{engine::general_purpose, Engine as _};
+use rayon::prelude::*;
+use std::path::Path;
+
+/// A position with the result of the game it was taken from
+#[derive(Clone, Debug, PartialEq)]
+pub struct LabelledPosition {
+    /// Board in binary format
+    pub board: [u8; 82],
+    /// Game result from white's perspective (1 = win, 0.5 = draw, 0 = loss)
+    pub result: f64,
+}
+
+impl LabelledPosition {
+    /// Parse a `<base64 board> <result>` line or a self-play JSON record
+    pub fn parse(line: &str) -> Result<Self, String> {
+        if line.trim_start().starts_with('{') {
+            let record: PositionRecord = serde_json::from_str(line)
+                .map_err(|e| format!("Failed to parse self-play record: {}", e))?;
+            return Ok(Self {
+                board: record.decode_board()?,
+                result: record.result,
+            });
+        }
+
+        let mut fields = line.split_whitespace();
+        let (Some(board_field), Some(result_field), None) = (fields.next(), fields.next(), fields.next()) else {
+            return Err(format!("Expected '<board> <result>', got '{}'", line));
+        };
+
+        let bytes = general_purpose::STANDARD
+            .decode(board_field)
+            .map_err(|e| format!("Failed to decode base64 board: {}", e))?;
+        let board: [u8; 82] = bytes
+            .try_into()
+            .map_err(|b: Vec<u8>| format!("Invalid board length: expected 82 bytes, got {}", b.len()))?;
+
+        Ok(Self {
+            board,
+            result: parse_result(result_field)?,
+        })
+    }
+}
+
+/// Parse a game result from white's perspective
+fn parse_result(value: &str) -> Result<f64, String> {
+    match value {
+        "1-0" => Ok(1.0),
+        "0-1" => Ok(0.0),
+        "1/2-1/2" => Ok(0.5),
+        _ => value
+            .parse::<f64>()
+            .ok()
+            .filter(|r| (0.0..=1.0).contains(r))
+            .ok_or_else(|| format!("Invalid result '{}'", value)),
+    }
+}
+
+/// Load labelled positions from a file (see the module documentation for the format)
+pub fn load_positions(path: impl AsRef<Path>) -> Result<Vec<LabelledPosition>, String> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read positions file {}: {}", path.display(), e))?;
+
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(number, line)| {
+            LabelledPosition::parse(line).map_err(|e| format!("{}:{}: {}", path.display(), number + 1, e))
+        })
+        .collect()
+}
+
+/// Tuning parameters
+#[derive(Clone, Debug)]
+pub struct TuningConfig {
+    /// Maximum number of passes over all weights
+    pub max_passes: u32,
+    /// Initial step size; it is halved whenever a pass brings no improvement
+    pub initial_step: i32,
+    /// Scaling constant of the logistic mapping (fitted to the data when `None`)
+    pub scale_k: Option<f64>,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        Self {
+            max_passes: 100,
+            initial_step: 16,
+            scale_k: None,
+        }
+    }
+}
+
+/// Progress information reported after each tuning pass
+#[derive(Clone, Debug)]
+pub struct TuningProgress {
+    /// Pass number (starting at 1)
+    pub pass: u32,
+    /// Step size used during the pass
+    pub step: i32,
+    /// Mean squared error after the pass
+    pub error: f64,
+}
+
+/// Outcome of a tuning run
+#[derive(Clone, Debug)]
+pub struct TuningReport {
+    /// Tuned weights
+    pub weights: EvalWeights,
+    /// Scaling constant of the logistic mapping
+    pub scale_k: f64,
+    /// Mean squared error with the initial weights
+    pub initial_error: f64,
+    /// Mean squared error with the tuned weights
+    pub final_error: f64,
+    /// Number of passes run
+    pub passes: u32,
+}
+
+/// Names of the tuned weights, in the order used by the local search
+pub const TUNED_PARAMETERS: [&str; 11] = [
+    "jester",
+    "commander",
+    "paladin",
+    "guard",
+    "dragon",
+    "ballista",
+    "mobility",
+    "stack_bonus",
+    "soldier_advancement",
+    "king_attacker_penalty",
+    "center_control",
+];
+
+fn parameter_mut(weights: &mut EvalWeights, index: usize) -> &mut i32 {
+    match index {
+        0..=5 => &mut weights.piece_values[index + 2],
+        6 => &mut weights.mobility,
+        7 => &mut weights.stack_bonus,
+        8 => &mut weights.soldier_advancement,
+        9 => &mut weights.king_attacker_penalty,
+        _ => &mut weights.center_control,
+    }
+}
+
+/// Evaluation tuner over a fixed set of labelled positions
+pub struct Tuner {
+    samples: Vec<(EvalFeatures, f64)>,
+}
+
+impl Tuner {
+    /// Extract the evaluation terms of every position.
+    /// Positions where a king has already been captured are skipped.
+    pub fn new(positions: &[LabelledPosition]) -> Self {
+        let samples = positions
+            .par_iter()
+            .map(|p| (EvalFeatures::extract(&p.board), p.result))
+            .filter(|(features, _)| features.kings == 0)
+            .collect();
+        Self { samples }
+    }
+
+    /// Number of positions used for tuning
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    /// Whether there is no position to tune on
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Mean squared error between predicted and actual results
+    pub fn error(&self, weights: &EvalWeights, k: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let total: f64 = self
+            .samples
+            .par_iter()
+            .map(|(features, result)| {
+                let predicted = expected_result(features.score(weights), k);
+                (result - predicted).powi(2)
+            })
+            .sum();
+        total / self.samples.len() as f64
+    }
+
+    /// Find the scaling constant that best fits the results with the given weights
+    pub fn fit_scale(&self, weights: &EvalWeights) -> f64 {
+        // Golden-section search, the error is unimodal in k for practical data
+        let ratio = (5f64.sqrt() - 1.0) / 2.0;
+        let (mut low, mut high) = (0.01, 10.0);
+        for _ in 0..60 {
+            let a = high - ratio * (high - low);
+            let b = low + ratio * (high - low);
+            if self.error(weights, a) < self.error(weights, b) {
+                high = b;
+            } else {
+                low = a;
+            }
+        }
+        (low + high) / 2.0
+    }
+
+    /// Tune the weights starting from `initial`, calling `on_pass` after every pass
+    pub fn tune(
+        &self,
+        initial: &EvalWeights,
+        config: &TuningConfig,
+        mut on_pass: impl FnMut(&TuningProgress),
+    ) -> TuningReport {
+        let k = config.scale_k.unwrap_or_else(|| self.fit_scale(initial));
+        let initial_error = self.error(initial, k);
+
+        let mut weights = *initial;
+        let mut best_error = initial_error;
+        let mut step = config.initial_step.max(1);
+        let mut passes = 0;
+
+        while passes < config.max_passes {
+            passes += 1;
+            let mut improved = false;
+
+            for index in 0..TUNED_PARAMETERS.len() {
+                for delta in [step, -step] {
+                    let mut candidate = weights;
+                    *parameter_mut(&mut candidate, index) += delta;
+                    let error = self.error(&candidate, k);
+                    if error < best_error {
+                        weights = candidate;
+                        best_error = error;
+                        improved = true;
+                        break;
+                    }
+                }
+            }
+
+            on_pass(&TuningProgress {
+                pass: passes,
+                step,
+                error: best_error,
+            });
+
+            if !improved {
+                if step == 1 {
+                    break;
+                }
+                step /= 2;
+            }
+        }
+
+        TuningReport {
+            weights,
+            scale_k: k,
+            initial_error,
+            final_error: best_error,
+            passes,
+        }
+    }
+}
+
+/// Logistic mapping from a white-perspective score to an expected result
+fn expected_result(score: i32, k: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-k * score as f64 / 400.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Board, Game, PieceType, Position};
+
+    #[test]
+    fn test_parse_positions() {
+        let board = Game::new().to_binary();
+        let encoded = general_purpose::STANDARD.encode(board);
+
+        let position = LabelledPosition::parse(&format!("{} 1/2-1/2", encoded)).unwrap();
+        assert_eq!(position.board, board);
+        assert_eq!(position.result, 0.5);
+        assert_eq!(LabelledPosition::parse(&format!("{} 0-1", encoded)).unwrap().result, 0.0);
+        assert_eq!(LabelledPosition::parse(&format!("{} 0.75", encoded)).unwrap().result, 0.75);
+
+        assert!(LabelledPosition::parse(&format!("{} 2-0", encoded)).is_err());
+        assert!(LabelledPosition::parse("AAAA 1-0").is_err());
+        assert!(LabelledPosition::parse(&encoded).is_err());
+
+        let record = format!(r#"{{"game":0,"ply":4,"board":"{}","moves":[],"best_move":0,"result":1.0}}"#, encoded);
+        assert_eq!(LabelledPosition::parse(&record).unwrap(), LabelledPosition { board, result: 1.0 });
+    }
+
+    #[test]
+    fn test_tuning_reduces_error() {
+        // White always wins without black's Jester and the full position is a draw:
+        // the Jester is undervalued, so tuning must raise it
+        let mut without_jester = Board::new();
+        let jester = Position::new(6, 1);
+        assert_eq!(without_jester.get_piece(&jester).map(|piece| piece.bottom), Some(PieceType::Jester));
+        without_jester.set_piece(&jester, None);
+        let mut positions = Vec::new();
+        for _ in 0..6 {
+            positions.push(LabelledPosition { board: without_jester.to_binary(), result: 1.0 });
+            positions.push(LabelledPosition { board: Game::new().to_binary(), result: 0.5 });
+        }
+
+        let tuner = Tuner::new(&positions);
+        assert_eq!(tuner.len(), positions.len());
+
+        let initial = EvalWeights::default();
+        let config = TuningConfig {
+            max_passes: 20,
+            scale_k: Some(1.0),
+            ..TuningConfig::default()
+        };
+        let mut passes_seen = 0;
+        let report = tuner.tune(&initial, &config, |progress| passes_seen = progress.pass);
+
+        assert_eq!(passes_seen, report.passes);
+        assert_eq!(report.scale_k, 1.0);
+        assert!(report.final_error < report.initial_error);
+        assert!(report.weights.piece_values[PieceType::Jester as usize] > initial.piece_values[PieceType::Jester as usize]);
+        assert_eq!(report.weights.piece_values[1], initial.piece_values[1]);
+        assert_eq!(report.weights.king_value, initial.king_value);
+    }
+}
@@ -63,82 +63,237 @@ impl Move {
     }
 }
 
+impl std::fmt::Display for Move {
+    /// Move notation: origin and destination squares joined by `-`, or by `^` when
+    /// only the top piece of a stack moves (e.g. `B1-C3`, `E2^E4`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}{}", self.from, if self.unstack { '^' } else { '-' }, self.to)
+    }
+}
+
+impl std::str::FromStr for Move {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to, unstack) = match (s.split_once('-'), s.split_once('^')) {
+            (Some((from, to)), None) => (from, to, false),
+            (None, Some((from, to))) => (from, to, true),
+            _ => return Err(format!("Invalid move '{}'. Use e.g. 'B1-C3' or 'E2^E4'.", s)),
+        };
+        Ok(Move {
+            from: from.trim().parse()?,
+            to: to.trim().parse()?,
+            unstack,
+        })
+    }
+}
+
+/// Everything needed to take back a move played with [`Game::make_move`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UndoRecord {
+    /// The move that was played
+    pub mv: Move,
+    /// Contents of the origin square before the move (full stack when unstacking)
+    pub from_before: Option<Piece>,
+    /// Contents of the destination square before the move (captured piece/stack or stack base)
+    pub to_before: Option<Piece>,
+    /// Value to XOR into the position's Zobrist hash to account for the move
+    pub hash_delta: u64,
+}
+
 #[derive(Clone, Debug)]
 pub struct Game {
     pub board: Board,
+    /// Moves played with [`Game::apply_move`], oldest first
+    history: Vec<UndoRecord>,
+    /// Whether [`Game::apply_move`] records moves in the history
+    record_history: bool,
+}
+
+impl Default for Game {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Game {
     pub fn new() -> Self {
-        Game {
-            board: Board::new(),
-        }
+        Game::from_board(Board::new())
     }
 
     pub fn from_board(board: Board) -> Self {
-        Game { board }
+        Game { board, history: Vec::new(), record_history: false }
+    }
+
+    /// Record moves played from now on, enabling [`Game::undo`] and
+    /// [`Game::repetition_count`]. Off by default so long-running callers
+    /// such as self-play do not accumulate history they never read.
+    pub fn with_history(mut self) -> Self {
+        self.record_history = true;
+        self
     }
 
+    /// Play a move, recording it in the game's history if enabled
     pub fn apply_move(&mut self, mv: Move) -> Result<(), String> {
-        let new_board = self.apply_move_copy(mv)?;
-        self.board = new_board;
+        let undo = self.make_move(mv)?;
+        if self.record_history {
+            self.history.push(undo);
+        }
         Ok(())
     }
 
+    /// Moves played with [`Game::apply_move`], oldest first
+    pub fn history(&self) -> impl Iterator<Item = Move> + '_ {
+        self.history.iter().map(|undo| undo.mv)
+    }
+
+    /// Number of moves in the history
+    pub fn ply_count(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Take back the last move of the history, returning it
+    pub fn undo(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        self.unmake_move(&undo);
+        Some(undo.mv)
+    }
+
+    /// How many times the current position occurred in the history, itself included
+    pub fn repetition_count(&self) -> usize {
+        let current = self.board.hash();
+        let mut hash = current;
+        let mut count = 1;
+        for undo in self.history.iter().rev() {
+            hash ^= undo.hash_delta;
+            if hash == current {
+                count += 1;
+            }
+        }
+        count
+    }
+
     pub fn apply_move_copy(&self, mv: Move) -> Result<Board, String> {
-        // Get the piece at the 'from' position
-        let piece = self
-            .board
-            .get_piece(&mv.from)
-            .ok_or("No piece at 'from' position")?;
+        let mut game = self.clone();
+        game.make_move(mv)?;
+        Ok(game.board)
+    }
+
+    /// Play a move in place and return the record needed to undo it.
+    /// The board is left untouched if the move cannot be applied.
+    pub fn make_move(&mut self, mv: Move) -> Result<UndoRecord, String> {
+        let from_before = self.board.get_piece(&mv.from).copied();
+        let to_before = self.board.get_piece(&mv.to).copied();
+        let (from_after, to_after) = Self::resolve_move(mv, from_before, to_before)?;
+
+        self.board.set_piece(&mv.from, from_after);
+        self.board.set_piece(&mv.to, Some(to_after));
+        self.board.set_white_to_move(!self.board.is_white_to_move()); // Switch turn
+
+        let hash_delta = Self::square_hash_delta(&mv.from, from_before, from_after)
+            ^ Self::square_hash_delta(&mv.to, to_before, Some(to_after))
+            ^ crate::zobrist::WHITE_TO_MOVE_KEY;
+
+        Ok(UndoRecord {
+            mv,
+            from_before,
+            to_before,
+            hash_delta,
+        })
+    }
+
+    /// Take back a move previously played with [`Game::make_move`]
+    pub fn unmake_move(&mut self, undo: &UndoRecord) {
+        self.board.set_piece(&undo.mv.from, undo.from_before);
+        self.board.set_piece(&undo.mv.to, undo.to_before);
+        self.board.set_white_to_move(!self.board.is_white_to_move());
+    }
+
+    /// Compute the new contents of the origin and destination squares of a move
+    fn resolve_move(
+        mv: Move,
+        from_before: Option<Piece>,
+        to_before: Option<Piece>,
+    ) -> Result<(Option<Piece>, Piece), String> {
+        let piece = from_before.ok_or("No piece at 'from' position")?;
 
         // Check if the piece can be moved (e.g., not empty)
         if piece.bottom == PieceType::King && mv.unstack {
             return Err("Cannot unstack King".to_string());
         }
 
-        let source_piece: Piece;
-        let mut new_board = self.board.clone();
-        if mv.unstack {
+        let (from_after, source_piece) = if mv.unstack {
             // Unstack the top piece if it exists
-            if !piece.top.is_some() {
-                return Err("No top piece to unstack".to_string());
-            }
-
-            let new_piece = new_board.unstack_piece(&mv.from);
-            if let Err(e) = new_piece {
-                return Err(e);
-            }
-            source_piece = new_piece?;
+            let top = piece.top.ok_or("No top piece to unstack")?;
+            (
+                Some(Piece { color: piece.color, bottom: piece.bottom, top: None }),
+                Piece { color: piece.color, bottom: top, top: None },
+            )
         } else {
-            source_piece = piece.clone();
-            // Remove the piece from the 'from' position
-            new_board.set_piece(&mv.from, None);
-        }
+            (None, piece)
+        };
 
-        // Check what's at the destination position
-        let destination_piece_opt = new_board.get_piece(&mv.to).cloned();
-        
-        if destination_piece_opt.is_none() {
+        let to_after = match to_before {
             // Empty square: just place the piece
-            new_board.set_piece(&mv.to, Some(source_piece));
-        } else {
-            let destination_piece = destination_piece_opt.unwrap();
-            
-            if destination_piece.color != source_piece.color {
-                // Enemy piece: capture it (replace with our piece)
-                new_board.set_piece(&mv.to, Some(source_piece));
+            None => source_piece,
+            // Enemy piece: capture it (replace with our piece)
+            Some(destination_piece) if destination_piece.color != source_piece.color => source_piece,
+            // Friendly piece: attempt to stack
+            Some(destination_piece) => destination_piece
+                .stacked_with(source_piece)
+                .map_err(|e| format!("Cannot complete move: {}", e))?,
+        };
+
+        Ok((from_after, to_after))
+    }
+
+    fn square_hash_delta(position: &Position, before: Option<Piece>, after: Option<Piece>) -> u64 {
+        let square = position.to_absolute();
+        let code = |piece: Option<Piece>| piece.map_or(0, |p| p.to_u8());
+        crate::zobrist::square_key(square, code(before)) ^ crate::zobrist::square_key(square, code(after))
+    }
+
+    /// All distinct legal moves for the side to move, with stack/unstack choices expanded
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut legal_moves: Vec<Move> = Vec::new();
+        for potential_move in self.get_all_moves() {
+            let mut options = [None, None];
+            if potential_move.force_unstack {
+                options[0] = Some(potential_move.to_move(true));
             } else {
-                // Friendly piece: attempt to stack
-                if let Err(e) = new_board.stack_piece(&mv.to, source_piece) {
-                    return Err(format!("Cannot complete move: {}", e));
+                options[0] = Some(potential_move.to_move(false));
+                if potential_move.unstackable {
+                    options[1] = Some(potential_move.to_move(true));
+                }
+            }
+            for mv in options.into_iter().flatten() {
+                if !legal_moves.contains(&mv) {
+                    legal_moves.push(mv);
                 }
             }
         }
-        
-        new_board.set_white_to_move(!new_board.is_white_to_move()); // Switch turn
+        legal_moves
+    }
 
-        Ok(new_board)
+    /// Count the leaf nodes of the legal move tree down to `depth` plies.
+    /// Positions where a king has been captured are terminal.
+    pub fn perft(&mut self, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        if self.board.is_game_over() {
+            return 0;
+        }
+
+        let mut nodes = 0;
+        for mv in self.legal_moves() {
+            let undo = self
+                .make_move(mv)
+                .expect("legal moves can always be played");
+            nodes += self.perft(depth - 1);
+            self.unmake_move(&undo);
+        }
+        nodes
     }
 
     pub fn get_all_moves(&self) -> Vec<PotentialMove> {
@@ -194,18 +349,10 @@ impl Game {
         has_top: bool,
     ) -> Vec<PotentialMove> {
         let mut moves = Vec::new();
+        // The King cannot be stacked so we do this trick with is_top and has_top
+        let (is_top, has_top) = if piece_type == PieceType::King { (false, true) } else { (is_top, has_top) };
+        self.compute_generic_moves(position, color, is_top, has_top, &mut moves, piece_type.directions(color), piece_type.range());
 
-        match piece_type {
-            PieceType::Soldier => self.compute_soldier_moves(position, color, is_top, has_top, &mut moves),
-            PieceType::Jester => self.compute_generic_moves(position, color, is_top, has_top, &mut moves, &Position::DIAGONAL_MOVES, BOARD_DIMENSION as isize),
-            PieceType::Commander => self.compute_generic_moves(position, color, is_top, has_top, &mut moves, &Position::ORTHOGONAL_MOVES, BOARD_DIMENSION as isize),
-            PieceType::Paladin => self.compute_generic_moves(position, color, is_top, has_top, &mut moves, &Position::ORTHOGONAL_MOVES, 2),
-            PieceType::Guard => self.compute_generic_moves(position, color, is_top, has_top, &mut moves, &Position::DIAGONAL_MOVES, 2),
-            PieceType::Dragon => self.compute_dragon_moves(position, color, is_top, has_top, &mut moves),
-            PieceType::Ballista => self.compute_ballista_moves(position, color, is_top, has_top, &mut moves),
-            PieceType::King => self.compute_generic_moves(position, color, false, true, &mut moves, &Position::ALL_MOVES, 1), // King cannot be stacked so we do this trick with is_top and has_top
-        }
-        
         moves
     }
 
@@ -220,7 +367,7 @@ impl Game {
         has_top: bool,
         moves: &mut Vec<PotentialMove>,
     ) -> bool {
-        let target_piece = self.board.get_piece(&target_position);
+        let target_piece = self.board.get_piece(target_position);
         // Empty case: OK can move
         if target_piece.is_none() {
             moves.push(PotentialMove {
@@ -273,54 +420,7 @@ impl Game {
         Ok(Game::from_board(board))
     }
 
-    fn compute_soldier_moves(
-        &self,
-        position: &Position,
-        color: Color,
-        is_top: bool,
-        has_top: bool,
-        moves: &mut Vec<PotentialMove>,
-    ) {
-        // Soldier can move forward diagonally one step
-        let dy: isize = if color == Color::White { -1 } else { 1 };
-        if let Some(target_position) = position.get_new(1, dy) {
-            self.explore_position(position, color, &target_position, is_top, has_top, moves);
-        }
-        if let Some(target_position) = position.get_new(-1, dy) {
-            self.explore_position(position, color, &target_position, is_top, has_top, moves);
-        }
-    }
-
-    fn compute_ballista_moves(
-        &self,
-        position: &Position,
-        color: Color,
-        is_top: bool,
-        has_top: bool,
-        moves: &mut Vec<PotentialMove>,
-    ) {
-        // Ballista can move forward any number of steps in a straight line
-        let dy: isize = if color == Color::White { -1 } else { 1 };
-        let directions = [(0isize, dy)];
-        self.compute_generic_moves(position, color, is_top, has_top, moves, &directions, BOARD_DIMENSION as isize);
-    }
-
-    fn compute_dragon_moves(
-        &self,
-        position: &Position,
-        color: Color,
-        is_top: bool,
-        has_top: bool,
-        moves: &mut Vec<PotentialMove>,
-    ) {
-        // Dragon move like a knight in chess
-        let directions = [
-            (2, 1), (2, -1), (-2, 1), (-2, -1),
-            (1, 2), (1, -2), (-1, 2), (-1, -2),
-        ];
-        self.compute_generic_moves(position, color, is_top, has_top, moves, &directions, 1);
-    }
-
+    #[allow(clippy::too_many_arguments)]
     fn compute_generic_moves(
         &self,
         position: &Position,
@@ -349,3 +449,73 @@ impl Game {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_make_unmake_move_round_trip() {
+        let mut game = Game::new();
+        let original = game.board;
+        let hash = game.board.hash();
+
+        for mv in game.legal_moves() {
+            let undo = game.make_move(mv).unwrap();
+            assert_eq!(game.board, Game::from_board(original).apply_move_copy(mv).unwrap());
+            assert_eq!(hash ^ undo.hash_delta, game.board.hash());
+            game.unmake_move(&undo);
+            assert_eq!(game.board, original);
+        }
+    }
+
+    #[test]
+    fn test_history_undo_and_repetitions() {
+        let mut untracked = Game::new();
+        let mut game = Game::new().with_history();
+        // Both Dragons jump out and back twice
+        let out = |y: usize, to_y: usize| Move { from: Position::new(1, y), to: Position::new(3, to_y), unstack: false };
+        let back = |mv: Move| Move { from: mv.to, to: mv.from, unstack: false };
+        let (white, black) = (out(8, 7), out(0, 1));
+        for _ in 0..2 {
+            for mv in [white, black, back(white), back(black)] {
+                assert!(game.legal_moves().contains(&mv));
+                game.apply_move(mv).unwrap();
+                untracked.apply_move(mv).unwrap();
+            }
+        }
+        assert_eq!(untracked.board, game.board);
+        assert_eq!(untracked.ply_count(), 0);
+        assert_eq!(game.ply_count(), 8);
+        assert_eq!(game.repetition_count(), 3);
+        assert_eq!(game.history().next(), Some(white));
+
+        assert_eq!(game.undo(), Some(back(black)));
+        assert_eq!(game.repetition_count(), 2);
+        while game.undo().is_some() {}
+        assert_eq!(game.board, Board::new());
+        assert_eq!(game.repetition_count(), 1);
+        assert_eq!(game.undo(), None);
+    }
+
+    #[test]
+    fn test_move_notation() {
+        let mv = Move { from: Position::new(1, 8), to: Position::new(2, 6), unstack: false };
+        assert_eq!(mv.to_string(), "B1-C3");
+        assert_eq!("b1-c3".parse(), Ok(mv));
+        let unstack = Move { unstack: true, ..mv };
+        assert_eq!(unstack.to_string(), "B1^C3");
+        assert_eq!(unstack.to_string().parse(), Ok(unstack));
+        assert!("B1C3".parse::<Move>().is_err());
+        assert!("B1-Z3".parse::<Move>().is_err());
+    }
+
+    #[test]
+    fn test_perft_initial_position() {
+        let mut game = Game::new();
+        assert_eq!(game.perft(1), 53);
+        assert_eq!(game.perft(2), 2809);
+        assert_eq!(game.perft(3), 154443);
+        assert_eq!(game.board, Board::new());
+    }
+}
@@ -3,10 +3,11 @@ pub mod cli_rendering;
 pub mod game;
 pub mod tui;
 pub mod engine;
+pub mod zobrist;
 
 // Re-export main types
 pub use board::{Board, Color, Piece, PieceType, Position, BOARD_DIMENSION, BOARD_SIZE};
-pub use game::{Game, Move, PotentialMove};
-pub use tui::run_tui;
+pub use game::{Game, Move, PotentialMove, UndoRecord};
+pub use tui::{run_tui, run_tui_against_engine, EngineOpponent};
 // Re-export main engine types (others available via engine::*)
-pub use engine::{MctsEngine, EngineConfig, SearchStatistics};
+pub use engine::{MctsEngine, EngineConfig, MoveChoice, SearchInfo, SearchResult, SearchStatistics};
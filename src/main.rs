@@ -1,5 +1,7 @@
-use arx_engine::{cli_rendering::display_stack, run_tui, Game, Position, BOARD_DIMENSION, BOARD_SIZE};
+use arx_engine::{cli_rendering::display_stack, run_tui, run_tui_against_engine, Color, EngineOpponent, Game, Move, Position, BOARD_DIMENSION, BOARD_SIZE};
 use clap::{Parser, Subcommand, Args};
+use arx_engine::engine::{adjudication::AdjudicationConfig, book::{BookBuilder, OpeningBook}, nnue::NnueNetwork, selfplay::{self, SelfPlayConfig}, solver::{find_puzzles, PuzzleConfig, SolveOutcome, Solver, SolverConfig}, strength::Strength, tablebase::{MaterialClass, TbValue, Tablebases}, tuning::{load_positions, Tuner, TuningConfig}, EngineConfig, EvalWeights};
+use std::sync::Arc;
 use base64::{Engine as _, engine::general_purpose};
 
 #[derive(Parser)]
@@ -13,6 +15,22 @@ struct Cli {
 enum Commands {
     Play(PlayArgs),
     ShowMoves(ShowMovesArgs),
+    /// Tune evaluation weights from labelled positions
+    Tune(TuneArgs),
+    /// Generate a self-play dataset
+    Selfplay(SelfplayArgs),
+    /// Train a neural network evaluator from labelled positions
+    TrainNnue(TrainNnueArgs),
+    /// Build, merge and inspect opening books
+    #[command(subcommand)]
+    Book(BookCommands),
+    /// Generate, list and probe endgame tablebases
+    #[command(subcommand)]
+    Tablebase(TablebaseCommands),
+    /// Look for a forced King capture from a position
+    Solve(SolveArgs),
+    /// Extract positions with a unique forced King capture from game records
+    Puzzles(PuzzlesArgs),
 }
 
 #[derive(Args)]
@@ -20,6 +38,46 @@ struct PlayArgs {
     /// Base64 encoded board data to import
     #[arg(long)]
     board: Option<String>,
+    /// Play against the engine at this level (beginner, intermediate, advanced, expert)
+    #[arg(long)]
+    engine: Option<Strength>,
+    /// Side played by the engine (white or black)
+    #[arg(long, default_value = "black", value_parser = parse_color)]
+    engine_color: Color,
+    #[command(flatten)]
+    adjudication: AdjudicationArgs,
+}
+
+#[derive(Args)]
+struct AdjudicationArgs {
+    /// Resign when the engine's score stays below this value (disabled if omitted)
+    #[arg(long, allow_hyphen_values = true)]
+    resign_threshold: Option<i32>,
+    /// Consecutive moves below the resign threshold before resigning
+    #[arg(long, default_value_t = 5)]
+    resign_moves: u32,
+    /// Offer and accept draws when the engine's score stays within this margin of zero
+    /// (disabled if omitted)
+    #[arg(long)]
+    draw_margin: Option<i32>,
+    /// Consecutive drawish moves before offering a draw
+    #[arg(long, default_value_t = 10)]
+    draw_moves: u32,
+    /// Moves played by the engine before it offers or accepts draws
+    #[arg(long, default_value_t = 40)]
+    draw_min_moves: u32,
+}
+
+impl AdjudicationArgs {
+    fn config(&self) -> AdjudicationConfig {
+        AdjudicationConfig {
+            resign_threshold: self.resign_threshold,
+            resign_moves: self.resign_moves,
+            draw_margin: self.draw_margin,
+            draw_moves: self.draw_moves,
+            draw_min_moves: self.draw_min_moves,
+        }
+    }
 }
 
 #[derive(Args)]
@@ -31,13 +89,245 @@ struct ShowMovesArgs {
     coordinates: Option<String>,
 }
 
+#[derive(Args)]
+struct TuneArgs {
+    /// File of labelled positions, one '<base64 board> <result>' per line
+    #[arg(long)]
+    input: String,
+    /// Where to write the tuned weights (JSON)
+    #[arg(long)]
+    output: String,
+    /// Weights to start from (defaults to the built-in weights)
+    #[arg(long)]
+    initial: Option<String>,
+    /// Maximum number of passes over all weights
+    #[arg(long, default_value_t = 100)]
+    max_passes: u32,
+    /// Initial step size of the local search
+    #[arg(long, default_value_t = 16)]
+    step: i32,
+    /// Scaling constant of the logistic mapping (fitted to the data if omitted)
+    #[arg(long)]
+    k: Option<f64>,
+}
+
+#[derive(Args)]
+struct SelfplayArgs {
+    /// Number of games to play
+    #[arg(long, default_value_t = 100)]
+    games: u32,
+    /// Where to write the dataset (JSON Lines)
+    #[arg(long)]
+    output: String,
+    /// Simulations per move
+    #[arg(long, default_value_t = 100)]
+    simulations: u32,
+    /// Maximum rollout depth
+    #[arg(long, default_value_t = 3)]
+    depth: u32,
+    /// Evaluation weights (JSON) used by the engine
+    #[arg(long)]
+    weights: Option<String>,
+    /// Neural network evaluator used by the engine instead of the weights
+    #[arg(long)]
+    nnue: Option<String>,
+    /// Number of random plies at the start of each game
+    #[arg(long, default_value_t = 4)]
+    random_plies: u32,
+    /// Games longer than this many plies are scored as draws
+    #[arg(long, default_value_t = 300)]
+    max_plies: u32,
+    /// Seed for the random openings and the engine's rollouts (makes the run reproducible)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Number of games played in parallel (defaults to the number of CPUs)
+    #[arg(long)]
+    threads: Option<usize>,
+    /// Directory of endgame tablebases probed by the engine
+    #[arg(long)]
+    tablebases: Option<String>,
+    /// Run the engine's simulations on the CPU
+    #[arg(long)]
+    cpu: bool,
+    #[command(flatten)]
+    adjudication: AdjudicationArgs,
+}
+
+#[derive(Subcommand)]
+enum BookCommands {
+    /// Build a book from self-play datasets
+    Build(BookBuildArgs),
+    /// Merge several books into one
+    Merge(BookMergeArgs),
+    /// Show the book moves of a position
+    Inspect(BookInspectArgs),
+}
+
+#[derive(Args)]
+struct BookBuildArgs {
+    /// Self-play datasets (JSON Lines)
+    #[arg(long, required = true, num_args = 1..)]
+    input: Vec<String>,
+    /// Where to write the book
+    #[arg(long)]
+    output: String,
+    /// Only record positions within this many plies of the start
+    #[arg(long, default_value_t = 16)]
+    max_ply: u32,
+    /// Drop moves played in fewer games than this
+    #[arg(long, default_value_t = 1)]
+    min_games: u32,
+}
+
+#[derive(Args)]
+struct BookMergeArgs {
+    /// Books to merge
+    #[arg(required = true, num_args = 2..)]
+    books: Vec<String>,
+    /// Where to write the merged book
+    #[arg(long)]
+    output: String,
+}
+
+#[derive(Args)]
+struct BookInspectArgs {
+    /// Book to inspect
+    book: String,
+    /// Base64 encoded board to look up (defaults to the initial position)
+    #[arg(long)]
+    board: Option<String>,
+}
+
+#[derive(Subcommand)]
+enum TablebaseCommands {
+    /// Solve material classes (and the smaller classes they lead to)
+    Generate(TablebaseGenerateArgs),
+    /// List the classes of a tablebase directory
+    List(TablebaseListArgs),
+    /// Show the tablebase value and best move of a position
+    Probe(TablebaseProbeArgs),
+}
+
+#[derive(Args)]
+struct TablebaseGenerateArgs {
+    /// Classes to solve, e.g. KC-K or KPG-KS
+    #[arg(required = true, num_args = 1..)]
+    classes: Vec<String>,
+    /// Tablebase directory (classes already in it are reused)
+    #[arg(long)]
+    dir: String,
+}
+
+#[derive(Args)]
+struct TablebaseListArgs {
+    /// Tablebase directory
+    #[arg(long)]
+    dir: String,
+}
+
+#[derive(Args)]
+struct TablebaseProbeArgs {
+    /// Tablebase directory
+    #[arg(long)]
+    dir: String,
+    /// Base64 encoded board to probe
+    #[arg(long)]
+    board: String,
+}
+
+#[derive(Args)]
+struct SolveArgs {
+    /// Base64 encoded board to solve
+    #[arg(long)]
+    board: String,
+    /// Longest win searched for, in moves of the side to move
+    #[arg(long, default_value_t = 3)]
+    max_moves: u32,
+    /// Nodes visited before giving up
+    #[arg(long, default_value_t = 2_000_000)]
+    max_nodes: u64,
+}
+
+#[derive(Args)]
+struct PuzzlesArgs {
+    /// Game records (self-play datasets or labelled positions)
+    #[arg(long, required = true, num_args = 1..)]
+    input: Vec<String>,
+    /// Where to write the puzzles (JSON Lines)
+    #[arg(long)]
+    output: String,
+    /// Longest win searched for, in moves of the side to move
+    #[arg(long, default_value_t = 3)]
+    max_moves: u32,
+    /// Shortest win kept
+    #[arg(long, default_value_t = 2)]
+    min_moves: u32,
+    /// Nodes visited per position before giving up
+    #[arg(long, default_value_t = 200_000)]
+    max_nodes: u64,
+}
+
+#[derive(Args)]
+struct TrainNnueArgs {
+    /// Labelled positions (same formats as `tune`, including self-play datasets)
+    #[arg(long)]
+    input: String,
+    /// Where to write the trained network
+    #[arg(long)]
+    output: String,
+    /// Network to continue training from (a new one is created if omitted)
+    #[arg(long)]
+    initial: Option<String>,
+    /// Accumulator size of a new network
+    #[arg(long, default_value_t = 64)]
+    hidden1: usize,
+    /// Second hidden layer size of a new network
+    #[arg(long, default_value_t = 16)]
+    hidden2: usize,
+    /// Number of passes over the positions
+    #[arg(long, default_value_t = 10)]
+    epochs: u32,
+    /// SGD learning rate
+    #[arg(long, default_value_t = 0.01)]
+    learning_rate: f32,
+    /// Seed for the initialization and shuffling
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    match color.to_ascii_lowercase().as_str() {
+        "white" => Ok(Color::White),
+        "black" => Ok(Color::Black),
+        _ => Err(format!("Unknown color '{}' (expected white or black)", color)),
+    }
+}
+
 fn main() {
     let cli = Cli::parse();
 
+    let tool_result = match &cli.command {
+        Some(Commands::Tune(args)) => Some(run_tune(args)),
+        Some(Commands::Selfplay(args)) => Some(run_selfplay(args)),
+        Some(Commands::TrainNnue(args)) => Some(run_train_nnue(args)),
+        Some(Commands::Book(command)) => Some(run_book(command)),
+        Some(Commands::Tablebase(command)) => Some(run_tablebase(command)),
+        Some(Commands::Solve(args)) => Some(run_solve(args)),
+        Some(Commands::Puzzles(args)) => Some(run_puzzles(args)),
+        _ => None,
+    };
+    if let Some(result) = tool_result {
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let board_data = match &cli.command {
         Some(Commands::Play(args)) => args.board.as_deref(),
         Some(Commands::ShowMoves(args)) => args.board.as_deref(),
-        None => None,
+        _ => None,
     };
 
     let game = match create_game(board_data) {
@@ -61,7 +351,23 @@ fn main() {
             }
         }
         _ => {
-            match run_tui(Some(game)) {
+            let opponent = match &cli.command {
+                Some(Commands::Play(PlayArgs { engine: Some(strength), engine_color, adjudication, .. })) => {
+                    match EngineOpponent::new(*engine_color, *strength, EngineConfig::default()) {
+                        Ok(opponent) => Some(opponent.with_adjudication(adjudication.config())),
+                        Err(e) => {
+                            eprintln!("Error creating engine: {}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                _ => None,
+            };
+            let result = match opponent {
+                Some(opponent) => run_tui_against_engine(Some(game), opponent),
+                None => run_tui(Some(game)),
+            };
+            match result {
                 Ok(g) => {
                     println!("Game hash: {}", get_hash(&g));
                     println!("(use this to resume the game later on with the --board option)");
@@ -86,7 +392,7 @@ fn main() {
         let moves = game.get_moves(position);
         if moves.is_empty() {
             if display_empty_message {
-                println!("No moves available for position {}.", position.to_string());
+                println!("No moves available for position {}.", position);
             }
             return;
         }
@@ -96,9 +402,9 @@ fn main() {
         } else {
             "?".to_string()
         };
-        println!("Available moves for {}@{}: ", piece_string, position.to_string());
+        println!("Available moves for {}@{}: ", piece_string, position);
         for m in moves.iter() {
-            print!(" - {}", m.to.to_string());
+            print!(" - {}", m.to);
             if m.unstackable {
                 if m.force_unstack {
                     print!(" (forced unstack)");
@@ -115,8 +421,8 @@ fn main() {
             return Err("Invalid position format. Use e.g. 'B4'.".to_string());
         }
         // A1 is (0,8), I9 is (8,0)
-        let x = match position.chars().nth(0).unwrap().to_ascii_uppercase() {
-            'A'..='I' => position.chars().nth(0).unwrap() as usize - 'A' as usize,
+        let x = match position.chars().next().unwrap().to_ascii_uppercase() {
+            'A'..='I' => position.chars().next().unwrap() as usize - 'A' as usize,
             _ => return Err("Invalid column. Use letters A-I.".to_string()),
         };
         let y = match position.chars().nth(1).unwrap() {
@@ -129,8 +435,8 @@ fn main() {
 
     fn create_game(board_str: Option<&str>) -> Result<Game, String> {
         match board_str {
-            None => return Ok(Game::new()),
-            Some("") => return Ok(Game::new()),
+            None => Ok(Game::new()),
+            Some("") => Ok(Game::new()),
             Some(s) => {
                 match general_purpose::STANDARD.decode(s) {
                     Ok(bytes) => {
@@ -154,6 +460,238 @@ fn main() {
         }
     }
 
+    fn run_tune(args: &TuneArgs) -> Result<(), String> {
+        let positions = load_positions(&args.input)?;
+        let tuner = Tuner::new(&positions);
+        if tuner.is_empty() {
+            return Err(format!("No usable positions in {}", args.input));
+        }
+        println!("Loaded {} positions ({} usable)", positions.len(), tuner.len());
+
+        let initial = match &args.initial {
+            Some(path) => EvalWeights::load(path)?,
+            None => EvalWeights::default(),
+        };
+        let config = TuningConfig {
+            max_passes: args.max_passes,
+            initial_step: args.step,
+            scale_k: args.k,
+        };
+
+        let report = tuner.tune(&initial, &config, |progress| {
+            println!("Pass {:>3} (step {:>2}): error {:.6}", progress.pass, progress.step, progress.error);
+        });
+
+        println!("K = {:.4}", report.scale_k);
+        println!("Error: {:.6} -> {:.6}", report.initial_error, report.final_error);
+        report.weights.save(&args.output)?;
+        println!("Tuned weights written to {}", args.output);
+        Ok(())
+    }
+
+    fn run_selfplay(args: &SelfplayArgs) -> Result<(), String> {
+        let eval_weights = match &args.weights {
+            Some(path) => EvalWeights::load(path)?,
+            None => EvalWeights::default(),
+        };
+        let nnue = match &args.nnue {
+            Some(path) => Some(Arc::new(NnueNetwork::load(path)?)),
+            None => None,
+        };
+        let tablebases = match &args.tablebases {
+            Some(dir) => Some(Arc::new(Tablebases::load_dir(dir)?)),
+            None => None,
+        };
+        let config = SelfPlayConfig {
+            games: args.games,
+            engine: EngineConfig {
+                max_depth: args.depth,
+                simulations_per_move: args.simulations,
+                use_gpu_simulation: !args.cpu,
+                eval_weights,
+                nnue,
+                tablebases,
+                ..EngineConfig::default()
+            },
+            random_plies: args.random_plies,
+            max_plies: args.max_plies,
+            seed: args.seed,
+            threads: args.threads,
+            adjudication: args.adjudication.config(),
+        };
+
+        let file = std::fs::File::create(&args.output)
+            .map_err(|e| format!("Failed to create {}: {}", args.output, e))?;
+        let summary = selfplay::run_selfplay(&config, std::io::BufWriter::new(file), |game| {
+            println!(
+                "Game {:>4}: {:?} by {:?} after {} plies ({} positions)",
+                game.index, game.outcome, game.termination, game.plies, game.positions.len()
+            );
+        })?;
+
+        println!(
+            "{} games, {} positions: {} white wins, {} black wins, {} draws ({} adjudicated)",
+            summary.games, summary.positions, summary.white_wins, summary.black_wins, summary.draws, summary.adjudicated
+        );
+        println!("Dataset written to {}", args.output);
+        Ok(())
+    }
+
+    fn run_train_nnue(args: &TrainNnueArgs) -> Result<(), String> {
+        let positions = load_positions(&args.input)?;
+        if positions.is_empty() {
+            return Err(format!("No positions in {}", args.input));
+        }
+        let mut network = match &args.initial {
+            Some(path) => NnueNetwork::load(path)?,
+            None => NnueNetwork::new(args.hidden1, args.hidden2, args.seed),
+        };
+        println!("Training {:?} on {} positions", network, positions.len());
+
+        network.train(&positions, args.epochs, args.learning_rate, args.seed, |epoch, loss| {
+            println!("Epoch {:>3}: loss {:.6}", epoch, loss);
+        });
+
+        network.save(&args.output)?;
+        println!("Network written to {}", args.output);
+        Ok(())
+    }
+
+    fn run_book(command: &BookCommands) -> Result<(), String> {
+        match command {
+            BookCommands::Build(args) => {
+                let mut builder = BookBuilder::new(args.max_ply);
+                for input in &args.input {
+                    builder.add_dataset(input)?;
+                }
+                let mut book = builder.build();
+                book.prune(args.min_games);
+                book.save(&args.output)?;
+                println!("Book with {} positions written to {}", book.positions(), args.output);
+            }
+            BookCommands::Merge(args) => {
+                let mut book = OpeningBook::default();
+                for path in &args.books {
+                    book.merge(&OpeningBook::load(path)?);
+                }
+                book.save(&args.output)?;
+                println!("Book with {} positions written to {}", book.positions(), args.output);
+            }
+            BookCommands::Inspect(args) => {
+                let book = OpeningBook::load(&args.book)?;
+                let game = create_game(args.board.as_deref())?;
+                println!("{} positions in book", book.positions());
+
+                let moves = book.moves(&game.to_binary());
+                if moves.is_empty() {
+                    println!("Position not in book");
+                }
+                for book_move in moves {
+                    let mv = Move::from_u16(book_move.mv);
+                    let unstack = if mv.unstack { " (unstack)" } else { "" };
+                    println!(
+                        " - {} -> {}{}: {} games (+{} ={} -{}), score {:.1}%",
+                        mv.from, mv.to, unstack, book_move.games(),
+                        book_move.wins, book_move.draws, book_move.losses,
+                        book_move.score() * 100.0
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_tablebase(command: &TablebaseCommands) -> Result<(), String> {
+        match command {
+            TablebaseCommands::Generate(args) => {
+                let mut tablebases = if std::path::Path::new(&args.dir).is_dir() {
+                    Tablebases::load_dir(&args.dir)?
+                } else {
+                    Tablebases::default()
+                };
+                for name in &args.classes {
+                    let class = MaterialClass::parse(name)?;
+                    tablebases.generate(&class, |solved| println!("Solved {}", solved))?;
+                }
+                tablebases.save_dir(&args.dir)?;
+                println!("{} classes written to {}", tablebases.classes().len(), args.dir);
+            }
+            TablebaseCommands::List(args) => {
+                let tablebases = Tablebases::load_dir(&args.dir)?;
+                for class in tablebases.classes() {
+                    println!(" - {}", class);
+                }
+            }
+            TablebaseCommands::Probe(args) => {
+                let tablebases = Tablebases::load_dir(&args.dir)?;
+                let board = create_game(Some(&args.board))?.to_binary();
+                let Some((mv, value)) = tablebases.best_move(&board) else {
+                    println!("Position not in tablebases");
+                    return Ok(());
+                };
+                let verdict = match value {
+                    TbValue::Win(plies) => format!("win in {} plies", plies),
+                    TbValue::Loss(plies) => format!("loss in {} plies", plies),
+                    TbValue::Draw => "draw".to_string(),
+                };
+                let mv = Move::from_u16(mv);
+                let unstack = if mv.unstack { " (unstack)" } else { "" };
+                println!("Side to move: {}", verdict);
+                println!("Best move: {} -> {}{}", mv.from, mv.to, unstack);
+            }
+        }
+        Ok(())
+    }
+
+    fn run_solve(args: &SolveArgs) -> Result<(), String> {
+        let board = create_game(Some(&args.board))?.to_binary();
+        let mut solver = Solver::new(SolverConfig {
+            max_moves: args.max_moves,
+            max_nodes: args.max_nodes,
+        });
+        match solver.solve(&board) {
+            SolveOutcome::Win { moves, line } => {
+                println!("King captured in {} moves:", moves);
+                for mv in line {
+                    let mv = Move::from_u16(mv);
+                    let unstack = if mv.unstack { " (unstack)" } else { "" };
+                    println!(" - {} -> {}{}", mv.from, mv.to, unstack);
+                }
+            }
+            SolveOutcome::NoWin => println!("No forced King capture within {} moves", args.max_moves),
+            SolveOutcome::Unknown => println!("Node limit reached, position unsolved"),
+        }
+        println!("{} nodes visited", solver.nodes());
+        Ok(())
+    }
+
+    fn run_puzzles(args: &PuzzlesArgs) -> Result<(), String> {
+        let mut positions = Vec::new();
+        for input in &args.input {
+            positions.extend(load_positions(input)?);
+        }
+        println!("Scanning {} positions", positions.len());
+
+        let config = PuzzleConfig {
+            solver: SolverConfig {
+                max_moves: args.max_moves,
+                max_nodes: args.max_nodes,
+            },
+            min_moves: args.min_moves,
+        };
+        let puzzles = find_puzzles(&positions, &config);
+
+        let mut lines = String::new();
+        for puzzle in &puzzles {
+            let line = serde_json::to_string(puzzle).map_err(|e| format!("Failed to serialize puzzle: {}", e))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        std::fs::write(&args.output, lines).map_err(|e| format!("Failed to write {}: {}", args.output, e))?;
+        println!("{} puzzles written to {}", puzzles.len(), args.output);
+        Ok(())
+    }
+
     fn get_hash(game: &Game) -> String {
         let all_bytes = game.to_binary();
         let byte_vec = all_bytes.to_vec();
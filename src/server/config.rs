@@ -0,0 +1,233 @@
+//! Server configuration
+//!
+//! Every setting comes from, in order of precedence: its command line flag, its
+//! environment variable, the JSON configuration file (`--config`, or `ARX_CONFIG`),
+//! and its default. The file holds any subset of the [`ServerConfig`] fields:
+//!
+//! ```json
+//! {"port": 8080, "cors_origins": ["https://arx.example"], "engines": 4, "public_dir": "public"}
+//! ```
+
+use crate::pool::SearchCaps;
+use arx_engine::engine::EngineConfig;
+use clap::Parser;
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+
+#[derive(Parser)]
+#[command(name = "server", version, about = "HTTP server of the Arx engine", long_about = None)]
+pub struct Cli {
+    /// JSON configuration file
+    #[arg(long, env = "ARX_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Address to listen on
+    #[arg(long, env = "ARX_HOST")]
+    pub host: Option<IpAddr>,
+    #[arg(long, env = "ARX_PORT")]
+    pub port: Option<u16>,
+    /// Origins allowed by CORS, comma separated (`*` allows any)
+    #[arg(long, env = "ARX_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    /// Seconds an unused game session, live game or finished analysis is kept
+    #[arg(long, env = "ARX_SESSION_TTL")]
+    pub session_ttl: Option<u64>,
+    /// Engines searching in parallel
+    #[arg(long, env = "ARX_ENGINES")]
+    pub engines: Option<usize>,
+    /// Searches waiting for an engine before new ones are refused
+    #[arg(long, env = "ARX_QUEUE_SIZE")]
+    pub queue_size: Option<usize>,
+    /// Search depth when the request does not choose one
+    #[arg(long, env = "ARX_DEPTH")]
+    pub depth: Option<u32>,
+    /// Simulations per root move when the request does not choose them
+    #[arg(long, env = "ARX_SIMULATIONS")]
+    pub simulations: Option<u32>,
+    /// Simulations per GPU batch
+    #[arg(long, env = "ARX_GPU_BATCH_SIZE")]
+    pub gpu_batch_size: Option<usize>,
+    /// Run the simulations on the GPU (true or false)
+    #[arg(long, env = "ARX_GPU")]
+    pub gpu: Option<bool>,
+    /// Highest search depth a request may ask for
+    #[arg(long, env = "ARX_MAX_DEPTH")]
+    pub max_depth: Option<u32>,
+    /// Most simulations per root move a request may ask for
+    #[arg(long, env = "ARX_MAX_SIMULATIONS")]
+    pub max_simulations: Option<u32>,
+    /// Longest search time a request may ask for, in milliseconds
+    #[arg(long, env = "ARX_MAX_MOVETIME_MS")]
+    pub max_movetime_ms: Option<u64>,
+    /// Most root moves a request may have reported
+    #[arg(long, env = "ARX_MAX_MULTIPV")]
+    pub max_multipv: Option<usize>,
+    /// Longest streamed analysis, in milliseconds
+    #[arg(long, env = "ARX_MAX_ANALYSIS_TIME_MS")]
+    pub max_analysis_time_ms: Option<u64>,
+    /// Serve the web client from this directory, with a generated `config.json`
+    #[arg(long, env = "ARX_PUBLIC_DIR")]
+    pub public_dir: Option<PathBuf>,
+    /// Engine URL written to the generated `config.json` (the serving origin when omitted)
+    #[arg(long, env = "ARX_BACKEND_URL")]
+    pub backend_url: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: IpAddr,
+    pub port: u16,
+    pub cors_origins: Vec<String>,
+    pub session_ttl: u64,
+    pub engines: usize,
+    pub queue_size: usize,
+    pub depth: u32,
+    pub simulations: u32,
+    pub gpu_batch_size: usize,
+    pub gpu: bool,
+    pub max_depth: u32,
+    pub max_simulations: u32,
+    pub max_movetime_ms: u64,
+    pub max_multipv: usize,
+    pub max_analysis_time_ms: u64,
+    pub public_dir: Option<PathBuf>,
+    pub backend_url: Option<String>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        let caps = SearchCaps::default();
+        Self {
+            host: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3000,
+            cors_origins: vec!["*".to_string()],
+            session_ttl: 30 * 60,
+            engines: 2,
+            queue_size: 16,
+            depth: 16,
+            simulations: 10000,
+            gpu_batch_size: 2048,
+            gpu: true,
+            max_depth: caps.max_depth,
+            max_simulations: caps.max_simulations,
+            max_movetime_ms: caps.max_movetime.as_millis() as u64,
+            max_multipv: caps.max_multipv,
+            max_analysis_time_ms: caps.max_analysis_time.as_millis() as u64,
+            public_dir: None,
+            backend_url: None,
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Configuration from the command line, the environment and the configuration file
+    pub fn load(cli: Cli) -> Result<Self, String> {
+        let base = match &cli.config {
+            Some(path) => {
+                let text = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+                serde_json::from_str(&text).map_err(|e| format!("Invalid configuration {}: {}", path.display(), e))?
+            }
+            None => ServerConfig::default(),
+        };
+        Ok(base.with_overrides(cli))
+    }
+
+    /// This configuration with the settings given on the command line or in the environment
+    fn with_overrides(self, cli: Cli) -> Self {
+        Self {
+            host: cli.host.unwrap_or(self.host),
+            port: cli.port.unwrap_or(self.port),
+            cors_origins: cli.cors_origins.unwrap_or(self.cors_origins),
+            session_ttl: cli.session_ttl.unwrap_or(self.session_ttl),
+            engines: cli.engines.unwrap_or(self.engines),
+            queue_size: cli.queue_size.unwrap_or(self.queue_size),
+            depth: cli.depth.unwrap_or(self.depth),
+            simulations: cli.simulations.unwrap_or(self.simulations),
+            gpu_batch_size: cli.gpu_batch_size.unwrap_or(self.gpu_batch_size),
+            gpu: cli.gpu.unwrap_or(self.gpu),
+            max_depth: cli.max_depth.unwrap_or(self.max_depth),
+            max_simulations: cli.max_simulations.unwrap_or(self.max_simulations),
+            max_movetime_ms: cli.max_movetime_ms.unwrap_or(self.max_movetime_ms),
+            max_multipv: cli.max_multipv.unwrap_or(self.max_multipv),
+            max_analysis_time_ms: cli.max_analysis_time_ms.unwrap_or(self.max_analysis_time_ms),
+            public_dir: cli.public_dir.or(self.public_dir),
+            backend_url: cli.backend_url.or(self.backend_url),
+        }
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn session_ttl(&self) -> Duration {
+        Duration::from_secs(self.session_ttl)
+    }
+
+    pub fn engine_config(&self) -> EngineConfig {
+        EngineConfig {
+            max_depth: self.depth,
+            simulations_per_move: self.simulations,
+            exploration_constant: 1.414,
+            gpu_batch_size: self.gpu_batch_size,
+            use_gpu_simulation: self.gpu,
+            ..EngineConfig::default()
+        }
+    }
+
+    pub fn search_caps(&self) -> SearchCaps {
+        SearchCaps {
+            max_depth: self.max_depth,
+            max_simulations: self.max_simulations,
+            max_movetime: Duration::from_millis(self.max_movetime_ms),
+            max_multipv: self.max_multipv,
+            max_analysis_time: Duration::from_millis(self.max_analysis_time_ms),
+        }
+    }
+
+    /// CORS policy allowing the configured origins
+    pub fn cors(&self) -> Result<CorsLayer, String> {
+        let origins = if self.cors_origins.iter().any(|origin| origin == "*") {
+            AllowOrigin::any()
+        } else {
+            let origins = self
+                .cors_origins
+                .iter()
+                .map(|origin| origin.parse().map_err(|_| format!("Invalid CORS origin '{}'", origin)))
+                .collect::<Result<Vec<_>, _>>()?;
+            AllowOrigin::list(origins)
+        };
+        Ok(CorsLayer::new().allow_origin(origins).allow_methods(Any).allow_headers(Any))
+    }
+
+    /// Contents of the web client's `config.json`
+    pub fn client_config(&self) -> serde_json::Value {
+        serde_json::json!({ "backendUrl": self.backend_url.as_deref().unwrap_or("") })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        let file: ServerConfig = serde_json::from_str(r#"{"port": 8080, "engines": 4, "cors_origins": ["https://arx.example"]}"#).unwrap();
+        assert_eq!((file.port, file.engines, file.queue_size), (8080, 4, 16));
+        assert!(serde_json::from_str::<ServerConfig>(r#"{"prot": 8080}"#).is_err());
+
+        let cli = Cli::try_parse_from(["server", "--engines", "1", "--cors-origins", "http://a.test,http://b.test", "--gpu", "false"]).unwrap();
+        let config = file.with_overrides(cli);
+        assert_eq!((config.port, config.engines, config.gpu), (8080, 1, false));
+        assert_eq!(config.cors_origins, ["http://a.test", "http://b.test"]);
+        assert!(config.cors().is_ok());
+        assert_eq!(config.address(), "0.0.0.0:8080".parse().unwrap());
+        assert_eq!(config.client_config(), serde_json::json!({"backendUrl": ""}));
+
+        let invalid = ServerConfig { cors_origins: vec!["bad\norigin".to_string()], ..ServerConfig::default() };
+        assert!(invalid.cors().is_err());
+    }
+}
@@ -0,0 +1,354 @@
+//! JSON representations of boards and moves
+//!
+//! Every stateless endpoint answers in JSON when the request's `Accept` header asks
+//! for `application/json`, or when a JSON request (`Content-Type: application/json`)
+//! accepts anything. Otherwise the binary format is used.
+//!
+//! Boards are written with their text notation, the side to move and the 81 squares
+//! from A9 to I1, row by row (the order of the binary format). Clients may send either
+//! the notation or the squares. Moves are written with their notation (`B1-C3`, or
+//! `E2^E4` when only the top piece of a stack moves) and their squares; clients may
+//! send either the notation or `from`, `to` and `unstack`.
+//!
+//! Engine endpoints take their search options from the query string, or from the
+//! top level of a JSON body, and answer with the move, its score, the search
+//! statistics and the best root moves.
+
+use crate::pool::{JobResult, SearchOptions};
+use arx_engine::engine::{strength::Strength, SearchInfo};
+use arx_engine::{Board, Color, Move, Piece, PieceType, Position, BOARD_SIZE};
+use axum::http::{header, HeaderMap};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+const JSON: &str = "application/json";
+
+/// Contents of an occupied square
+#[derive(Serialize, Deserialize)]
+pub struct SquareJson {
+    pub color: Color,
+    /// Bottom piece of a stack, or the single piece
+    pub piece: PieceType,
+    #[serde(default)]
+    pub top: Option<PieceType>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BoardJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to_move: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub squares: Option<Vec<Option<SquareJson>>>,
+}
+
+impl From<&Board> for BoardJson {
+    fn from(board: &Board) -> Self {
+        let squares = (0..BOARD_SIZE as u8)
+            .map(|square| {
+                board.get_piece(&Position::from_u8(square)).map(|piece| SquareJson {
+                    color: piece.color,
+                    piece: piece.bottom,
+                    top: piece.top,
+                })
+            })
+            .collect();
+        Self {
+            notation: Some(board.to_notation()),
+            to_move: Some(board.color_to_move()),
+            squares: Some(squares),
+        }
+    }
+}
+
+impl TryFrom<BoardJson> for Board {
+    type Error = String;
+
+    fn try_from(json: BoardJson) -> Result<Self, Self::Error> {
+        if let Some(notation) = json.notation {
+            return Board::from_notation(&notation);
+        }
+        let (Some(squares), Some(to_move)) = (json.squares, json.to_move) else {
+            return Err("A board needs its notation, or its squares and side to move".to_string());
+        };
+        if squares.len() != BOARD_SIZE {
+            return Err(format!("Expected {} squares, found {}", BOARD_SIZE, squares.len()));
+        }
+        let mut board = Board::from_binary([0; BOARD_SIZE + 1])?;
+        board.set_white_to_move(to_move == Color::White);
+        for (square, contents) in squares.into_iter().enumerate() {
+            if let Some(SquareJson { color, piece, top }) = contents {
+                if piece == PieceType::King && top.is_some() || top == Some(PieceType::King) {
+                    return Err("The King cannot be stacked".to_string());
+                }
+                board.set_piece(&Position::from_u8(square as u8), Some(Piece { color, bottom: piece, top }));
+            }
+        }
+        Ok(board)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MoveJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    #[serde(default)]
+    pub unstack: bool,
+}
+
+impl From<Move> for MoveJson {
+    fn from(mv: Move) -> Self {
+        Self {
+            notation: Some(mv.to_string()),
+            from: Some(mv.from.to_string()),
+            to: Some(mv.to.to_string()),
+            unstack: mv.unstack,
+        }
+    }
+}
+
+impl TryFrom<MoveJson> for Move {
+    type Error = String;
+
+    fn try_from(json: MoveJson) -> Result<Self, Self::Error> {
+        match (json.notation, json.from, json.to) {
+            (Some(notation), _, _) => notation.parse(),
+            (None, Some(from), Some(to)) => Ok(Move { from: from.parse()?, to: to.parse()?, unstack: json.unstack }),
+            _ => Err("A move needs its notation, or its from and to squares".to_string()),
+        }
+    }
+}
+
+/// Body of requests sending a board
+#[derive(Deserialize)]
+pub struct BoardRequest {
+    pub board: BoardJson,
+}
+
+/// Body of `POST /play`
+#[derive(Deserialize)]
+pub struct PlayRequest {
+    pub board: BoardJson,
+    #[serde(rename = "move")]
+    pub mv: MoveJson,
+}
+
+/// Search options of an engine request
+#[derive(Clone, Default, Deserialize)]
+pub struct SearchParams {
+    /// Difficulty level (beginner, intermediate, advanced, expert)
+    pub level: Option<String>,
+    /// Time limit of the request in milliseconds, queueing included
+    pub timeout_ms: Option<u64>,
+    /// Search time in milliseconds
+    pub movetime_ms: Option<u64>,
+    pub simulations: Option<u32>,
+    pub depth: Option<u32>,
+    pub seed: Option<u64>,
+    /// Number of best root moves to report
+    pub multipv: Option<usize>,
+}
+
+impl SearchParams {
+    /// These options, completed with `fallback` where missing
+    pub fn or(self, fallback: SearchParams) -> SearchParams {
+        SearchParams {
+            level: self.level.or(fallback.level),
+            timeout_ms: self.timeout_ms.or(fallback.timeout_ms),
+            movetime_ms: self.movetime_ms.or(fallback.movetime_ms),
+            simulations: self.simulations.or(fallback.simulations),
+            depth: self.depth.or(fallback.depth),
+            seed: self.seed.or(fallback.seed),
+            multipv: self.multipv.or(fallback.multipv),
+        }
+    }
+
+    pub fn options(&self) -> Result<SearchOptions, String> {
+        let level = self.level.as_deref().map(str::parse::<Strength>).transpose()?;
+        Ok(SearchOptions {
+            level,
+            movetime: self.movetime_ms.map(Duration::from_millis),
+            simulations: self.simulations,
+            depth: self.depth,
+            seed: self.seed,
+            multipv: self.multipv.unwrap_or(0),
+            infinite: false,
+        })
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout_ms.map(Duration::from_millis)
+    }
+}
+
+/// Body of `POST /engine-move` and `POST /analysis`
+#[derive(Deserialize)]
+pub struct SearchRequest {
+    pub board: BoardJson,
+    #[serde(flatten)]
+    pub params: SearchParams,
+}
+
+/// Root move of a search
+#[derive(Serialize)]
+pub struct LineJson {
+    #[serde(rename = "move")]
+    pub mv: MoveJson,
+    /// Average score for the side to move
+    pub score: i32,
+    /// Simulations run for the move
+    pub visits: u32,
+}
+
+#[derive(Serialize)]
+pub struct StatisticsJson {
+    pub simulations: u64,
+    pub elapsed_ms: u64,
+}
+
+/// Move chosen by a search
+#[derive(Serialize)]
+pub struct SearchResponse {
+    #[serde(rename = "move")]
+    pub mv: MoveJson,
+    /// Score for the side to move (null for book and forced moves)
+    pub score: Option<i32>,
+    pub statistics: StatisticsJson,
+    pub lines: Vec<LineJson>,
+}
+
+impl From<&JobResult> for SearchResponse {
+    fn from(result: &JobResult) -> Self {
+        Self {
+            mv: Move::from_u16(result.best_move).into(),
+            score: result.score,
+            statistics: StatisticsJson {
+                simulations: result.simulations,
+                elapsed_ms: result.elapsed.as_millis() as u64,
+            },
+            lines: result
+                .lines
+                .iter()
+                .map(|line| LineJson {
+                    mv: Move::from_u16(line.mv).into(),
+                    score: line.mean_score().round() as i32,
+                    visits: line.visits,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Progress of a streamed analysis
+#[derive(Serialize)]
+pub struct InfoJson {
+    /// Rounds completed
+    pub depth: u32,
+    /// Simulations run so far
+    pub nodes: u64,
+    #[serde(rename = "move")]
+    pub mv: MoveJson,
+    pub score: i32,
+    /// Principal variation, in notation
+    pub pv: Vec<String>,
+    pub elapsed_ms: u64,
+}
+
+impl From<&SearchInfo> for InfoJson {
+    fn from(info: &SearchInfo) -> Self {
+        Self {
+            depth: info.depth,
+            nodes: info.nodes,
+            mv: Move::from_u16(info.best_move).into(),
+            score: info.score,
+            pv: info.pv.iter().map(|&mv| Move::from_u16(mv).to_string()).collect(),
+            elapsed_ms: info.elapsed.as_millis() as u64,
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct BoardResponse {
+    pub board: BoardJson,
+}
+
+#[derive(Serialize)]
+pub struct MovesResponse {
+    pub moves: Vec<MoveJson>,
+}
+
+/// Whether the request body is JSON
+pub fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with(JSON))
+}
+
+/// Whether the response should be JSON
+pub fn wants_json(headers: &HeaderMap) -> bool {
+    match headers.get(header::ACCEPT).and_then(|value| value.to_str().ok()) {
+        Some(accept) if accept.contains(JSON) => true,
+        Some(accept) if !accept.contains("*/*") && !accept.trim().is_empty() => false,
+        _ => is_json(headers),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_board_and_move_round_trip() {
+        let board = Board::new();
+        let json = serde_json::to_value(BoardJson::from(&board)).unwrap();
+        assert_eq!(json["to_move"], "white");
+        assert_eq!(json["squares"][4], serde_json::json!({"color": "black", "piece": "king", "top": null}));
+
+        // Either the notation or the squares describe the board
+        let squares_only = serde_json::json!({"to_move": "white", "squares": json["squares"]});
+        assert_eq!(Board::try_from(serde_json::from_value::<BoardJson>(squares_only).unwrap()), Ok(board));
+        let notation_only = serde_json::json!({"notation": json["notation"]});
+        assert_eq!(Board::try_from(serde_json::from_value::<BoardJson>(notation_only).unwrap()), Ok(board));
+        assert!(Board::try_from(serde_json::from_value::<BoardJson>(serde_json::json!({})).unwrap()).is_err());
+
+        let mv: Move = "E2^E4".parse().unwrap();
+        let json = serde_json::to_value(MoveJson::from(mv)).unwrap();
+        assert_eq!(json, serde_json::json!({"notation": "E2^E4", "from": "E2", "to": "E4", "unstack": true}));
+        let squares_only = serde_json::json!({"from": "E2", "to": "E4", "unstack": true});
+        assert_eq!(Move::try_from(serde_json::from_value::<MoveJson>(squares_only).unwrap()), Ok(mv));
+    }
+
+    #[test]
+    fn test_search_request() {
+        let body = r#"{"board": {"notation": "9/9/9/9/4k4/9/9/9/4K4 w"}, "movetime_ms": 500, "multipv": 3, "level": "Expert"}"#;
+        let request: SearchRequest = serde_json::from_str(body).unwrap();
+        let query = SearchParams { movetime_ms: Some(100), seed: Some(9), ..SearchParams::default() };
+        let params = request.params.or(query);
+        let options = params.options().unwrap();
+        assert_eq!(options.movetime, Some(Duration::from_millis(500)));
+        assert_eq!((options.level, options.seed, options.multipv), (Some(Strength::Expert), Some(9), 3));
+        assert!(SearchParams { level: Some("grandmaster".to_string()), ..SearchParams::default() }.options().is_err());
+    }
+
+    #[test]
+    fn test_content_negotiation() {
+        let headers = |pairs: &[(header::HeaderName, &'static str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(name, value.parse().unwrap());
+            }
+            headers
+        };
+        assert!(!wants_json(&headers(&[])));
+        assert!(wants_json(&headers(&[(header::ACCEPT, "application/json")])));
+        assert!(wants_json(&headers(&[(header::CONTENT_TYPE, "application/json"), (header::ACCEPT, "*/*")])));
+        assert!(!wants_json(&headers(&[(header::CONTENT_TYPE, "application/json"), (header::ACCEPT, "application/octet-stream")])));
+        assert!(is_json(&headers(&[(header::CONTENT_TYPE, "application/json; charset=utf-8")])));
+    }
+}
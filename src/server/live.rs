@@ -0,0 +1,336 @@
+//! Live two-player games over WebSocket
+//!
+//! A live game is created with `POST /live` and played through `GET /live/{id}/ws`.
+//! Every connection watches the game; a connection becomes a player by claiming a free
+//! seat, and receives a join token to reclaim it after reconnecting. The server keeps
+//! the authoritative [`Session`]: moves are checked against the game's legal moves and
+//! the side to move before being broadcast to every connection.
+//!
+//! Messages are JSON text frames tagged by `type`, with boards and moves in the JSON
+//! API format. Clients send:
+//!
+//! - `{"type": "join", "seat": "white"}`, or `{"type": "join", "token": "..."}`
+//! - `{"type": "move", "move": {"notation": "B1-C3"}}`
+//! - `{"type": "offer_draw"}`, `{"type": "accept_draw"}`, `{"type": "resign"}`
+//!
+//! The server answers a join with `joined` (seat and token) and an invalid request with
+//! `error`, to the sender only. It sends the full `state` on connection and broadcasts
+//! `state` when a seat is claimed, `move` (with the new state) after every move,
+//! `draw_offer` and `game_over`.
+
+use crate::json::{BoardJson, MoveJson};
+use crate::sessions::{Outcome, Reason, Session, SessionOptions};
+use arx_engine::{Color, Game, Move};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+
+/// Broadcast messages kept for slow connections before they resynchronize
+const EVENT_CAPACITY: usize = 64;
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Claim a free seat, or reclaim one with its token
+    Join {
+        #[serde(default)]
+        seat: Option<Color>,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    Move {
+        #[serde(rename = "move")]
+        mv: MoveJson,
+    },
+    OfferDraw,
+    AcceptDraw,
+    Resign,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    Joined { seat: Color, token: String },
+    State { state: LiveState },
+    Move {
+        #[serde(rename = "move")]
+        mv: MoveJson,
+        state: LiveState,
+    },
+    DrawOffer { by: Color },
+    GameOver { outcome: Outcome, reason: Reason },
+    Error { message: String },
+}
+
+impl ServerMessage {
+    pub fn to_text(&self) -> String {
+        serde_json::to_string(self).expect("server messages serialize")
+    }
+}
+
+/// Claimed seats of a live game
+#[derive(Serialize)]
+pub struct Seats {
+    pub white: bool,
+    pub black: bool,
+}
+
+/// Everything a client needs to display a live game
+#[derive(Serialize)]
+pub struct LiveState {
+    pub board: BoardJson,
+    /// Moves played so far, in notation
+    pub moves: Vec<String>,
+    pub seats: Seats,
+    pub outcome: Outcome,
+    pub reason: Reason,
+    /// Side whose draw offer is pending
+    pub draw_offer: Option<Color>,
+}
+
+/// Game and seats of one live game
+pub struct LiveGame {
+    session: Session,
+    /// Join token of each seat (white, black), once claimed
+    tokens: [Option<String>; 2],
+    draw_offer: Option<Color>,
+}
+
+fn seat_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+impl LiveGame {
+    pub fn new(game: Game) -> Self {
+        Self {
+            session: Session::new(game, SessionOptions::default()),
+            tokens: [None, None],
+            draw_offer: None,
+        }
+    }
+
+    pub fn state(&self) -> LiveState {
+        LiveState {
+            board: (&self.session.game.board).into(),
+            moves: self.session.game.history().map(|mv| mv.to_string()).collect(),
+            seats: Seats {
+                white: self.tokens[0].is_some(),
+                black: self.tokens[1].is_some(),
+            },
+            outcome: self.session.outcome,
+            reason: self.session.reason,
+            draw_offer: self.draw_offer,
+        }
+    }
+
+    /// Claim a free seat (the first free one when none is given) or reclaim a seat
+    /// with its token. Returns the seat and its token.
+    pub fn join(&mut self, seat: Option<Color>, token: Option<&str>) -> Result<(Color, String), String> {
+        if let Some(token) = token {
+            return [Color::White, Color::Black]
+                .into_iter()
+                .find(|&color| self.tokens[seat_index(color)].as_deref() == Some(token))
+                .map(|color| (color, token.to_string()))
+                .ok_or_else(|| "Unknown join token".to_string());
+        }
+        let seat = match seat {
+            Some(seat) => seat,
+            None => [Color::White, Color::Black]
+                .into_iter()
+                .find(|&color| self.tokens[seat_index(color)].is_none())
+                .ok_or("Both seats are taken")?,
+        };
+        let slot = &mut self.tokens[seat_index(seat)];
+        if slot.is_some() {
+            return Err("This seat is taken".to_string());
+        }
+        let token = format!("{:032x}", rand::thread_rng().gen::<u128>());
+        *slot = Some(token.clone());
+        Ok((seat, token))
+    }
+
+    /// Apply a request of the player sitting at `seat`, returning the messages to
+    /// broadcast or the error to send back
+    pub fn handle(&mut self, seat: Option<Color>, message: ClientMessage) -> Result<Vec<ServerMessage>, String> {
+        let seat = seat.ok_or("Join a seat first")?;
+        if self.session.is_over() {
+            return Err("The game is over".to_string());
+        }
+
+        let mut events = Vec::new();
+        match message {
+            ClientMessage::Join { .. } => return Err("Already joined".to_string()),
+            ClientMessage::Move { mv } => {
+                if self.session.game.board.color_to_move() != seat {
+                    return Err("Not your turn".to_string());
+                }
+                let mv = Move::try_from(mv)?;
+                self.session.play(mv).map_err(|e| e.to_string())?;
+                // Moving instead of accepting declines the opponent's offer
+                if self.draw_offer == Some(seat.opposite()) {
+                    self.draw_offer = None;
+                }
+                events.push(ServerMessage::Move { mv: mv.into(), state: self.state() });
+            }
+            ClientMessage::OfferDraw if self.draw_offer == Some(seat.opposite()) => {
+                self.session.agree_draw().map_err(|e| e.to_string())?;
+            }
+            ClientMessage::OfferDraw => {
+                self.draw_offer = Some(seat);
+                events.push(ServerMessage::DrawOffer { by: seat });
+            }
+            ClientMessage::AcceptDraw => {
+                if self.draw_offer != Some(seat.opposite()) {
+                    return Err("No draw offer to accept".to_string());
+                }
+                self.session.agree_draw().map_err(|e| e.to_string())?;
+            }
+            ClientMessage::Resign => self.session.resign(seat).map_err(|e| e.to_string())?,
+        }
+
+        if self.session.is_over() {
+            self.draw_offer = None;
+            events.push(ServerMessage::GameOver {
+                outcome: self.session.outcome,
+                reason: self.session.reason,
+            });
+        }
+        Ok(events)
+    }
+}
+
+/// A live game shared by its connections
+pub struct LiveRoom {
+    pub game: Mutex<LiveGame>,
+    /// Serialized messages for every connection
+    pub events: broadcast::Sender<String>,
+    last_activity: Mutex<Instant>,
+}
+
+impl LiveRoom {
+    pub fn broadcast(&self, message: &ServerMessage) {
+        // Nobody may be listening
+        let _ = self.events.send(message.to_text());
+    }
+
+    pub fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+}
+
+/// Live games by ID, dropped once nobody watched them for `ttl`
+pub struct LiveRooms {
+    rooms: Mutex<HashMap<u64, Arc<LiveRoom>>>,
+    ttl: Duration,
+}
+
+impl LiveRooms {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            rooms: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Open a live game and return its random ID
+    pub fn create(&self, game: Game) -> u64 {
+        let mut rooms = self.rooms.lock().unwrap();
+        let mut rng = rand::thread_rng();
+        let mut id = rng.gen();
+        while rooms.contains_key(&id) {
+            id = rng.gen();
+        }
+        let room = LiveRoom {
+            game: Mutex::new(LiveGame::new(game)),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+            last_activity: Mutex::new(Instant::now()),
+        };
+        rooms.insert(id, Arc::new(room));
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<Arc<LiveRoom>> {
+        self.rooms.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Drop every game without connections that stayed idle for the time to live
+    pub fn purge_expired(&self) -> usize {
+        let mut rooms = self.rooms.lock().unwrap();
+        let before = rooms.len();
+        rooms.retain(|_, room| {
+            room.events.receiver_count() > 0 || room.last_activity.lock().unwrap().elapsed() <= self.ttl
+        });
+        before - rooms.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(notation: &str) -> ClientMessage {
+        ClientMessage::Move { mv: MoveJson { notation: Some(notation.to_string()), from: None, to: None, unstack: false } }
+    }
+
+    #[test]
+    fn test_seats_and_moves() {
+        let mut live = LiveGame::new(Game::new());
+        assert!(live.handle(None, play("B1-C3")).is_err()); // Spectators cannot play
+
+        let (white, token) = live.join(Some(Color::White), None).unwrap();
+        assert_eq!(white, Color::White);
+        assert!(live.join(Some(Color::White), None).is_err());
+        assert_eq!(live.join(None, Some(&token)), Ok((Color::White, token.clone())));
+        assert!(live.join(None, Some("forged")).is_err());
+        let (black, _) = live.join(None, None).unwrap();
+        assert_eq!(black, Color::Black);
+        assert!(live.join(None, None).is_err());
+
+        assert_eq!(live.handle(Some(black), play("B9-C7")).err().as_deref(), Some("Not your turn"));
+        assert_eq!(live.handle(Some(white), play("B1-B2")).err().as_deref(), Some("Illegal move"));
+        let events = live.handle(Some(white), play("B1-C3")).unwrap();
+        assert!(matches!(&events[..], [ServerMessage::Move { .. }]));
+        assert_eq!(live.state().moves, vec!["B1-C3"]);
+    }
+
+    #[test]
+    fn test_draws_and_resignation() {
+        let mut live = LiveGame::new(Game::new());
+        let (white, _) = live.join(Some(Color::White), None).unwrap();
+        let (black, _) = live.join(Some(Color::Black), None).unwrap();
+
+        // Moving instead of accepting declines the offer
+        live.handle(Some(white), ClientMessage::OfferDraw).unwrap();
+        assert!(live.handle(Some(white), ClientMessage::AcceptDraw).is_err());
+        live.handle(Some(white), play("B1-C3")).unwrap();
+        assert_eq!(live.state().draw_offer, Some(Color::White));
+        live.handle(Some(black), play("B9-C7")).unwrap();
+        assert_eq!(live.state().draw_offer, None);
+
+        live.handle(Some(white), ClientMessage::OfferDraw).unwrap();
+        let events = live.handle(Some(black), ClientMessage::AcceptDraw).unwrap();
+        assert!(matches!(events[..], [ServerMessage::GameOver { outcome: Outcome::Draw, reason: Reason::DrawAgreed }]));
+        assert!(live.handle(Some(white), play("C3-D5")).is_err());
+
+        let mut live = LiveGame::new(Game::new());
+        live.join(Some(Color::White), None).unwrap();
+        let events = live.handle(Some(white), ClientMessage::Resign).unwrap();
+        assert!(matches!(events[..], [ServerMessage::GameOver { outcome: Outcome::BlackWon, reason: Reason::Resignation }]));
+    }
+
+    #[test]
+    fn test_client_messages_parse() {
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "join", "seat": "black"}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Join { seat: Some(Color::Black), token: None }));
+        let message: ClientMessage = serde_json::from_str(r#"{"type": "move", "move": {"from": "E2", "to": "E4"}}"#).unwrap();
+        assert!(matches!(message, ClientMessage::Move { .. }));
+        let text = ServerMessage::DrawOffer { by: Color::White }.to_text();
+        assert_eq!(text, r#"{"type":"draw_offer","by":"white"}"#);
+    }
+}
@@ -0,0 +1,616 @@
+mod config;
+mod json;
+mod live;
+mod pool;
+mod sessions;
+
+use arx_engine::board::{Board, BOARD_SIZE};
+use arx_engine::game::{Game, Move};
+use arx_engine::engine::{adjudication::AdjudicationConfig, strength::Strength};
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Router,
+    body::Bytes,
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    Json,
+};
+use clap::Parser;
+use config::{Cli, ServerConfig};
+use live::{ClientMessage, LiveRoom, LiveRooms, ServerMessage};
+use futures_util::stream::{self, Stream};
+use json::{BoardRequest, BoardResponse, InfoJson, MoveJson, MovesResponse, PlayRequest, SearchParams, SearchRequest, SearchResponse};
+use pool::{EnginePool, Job, JobRequest, JobResult, JobStatus, SearchOptions, SubmitError};
+use serde::Deserialize;
+use sessions::{Session, SessionError, SessionOptions, SessionStore};
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tower_http::services::ServeDir;
+
+// Shared engine state
+struct AppState {
+    /// Engines searching on their own threads
+    pool: EnginePool,
+    /// Games created with `POST /games`
+    sessions: SessionStore,
+    /// Two-player games created with `POST /live`
+    live: LiveRooms,
+}
+
+/// Options of `POST /games`
+#[derive(Deserialize)]
+struct NewGameParams {
+    /// Difficulty level of the game's engine moves
+    level: Option<String>,
+    resign_threshold: Option<i32>,
+    resign_moves: Option<u32>,
+    draw_margin: Option<i32>,
+    draw_moves: Option<u32>,
+    draw_min_moves: Option<u32>,
+}
+
+impl NewGameParams {
+    fn options(&self) -> Result<SessionOptions, StatusCode> {
+        let level = match self.level.as_deref() {
+            Some(level) => Some(level.parse::<Strength>().map_err(|_| StatusCode::BAD_REQUEST)?),
+            None => None,
+        };
+        let defaults = AdjudicationConfig::default();
+        let adjudication = AdjudicationConfig {
+            resign_threshold: self.resign_threshold,
+            resign_moves: self.resign_moves.unwrap_or(defaults.resign_moves),
+            draw_margin: self.draw_margin,
+            draw_moves: self.draw_moves.unwrap_or(defaults.draw_moves),
+            draw_min_moves: self.draw_min_moves.unwrap_or(defaults.draw_min_moves),
+        };
+        Ok(SessionOptions { level, adjudication })
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let settings = match ServerConfig::load(Cli::parse()) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let cors = match settings.cors() {
+        Ok(cors) => cors,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let session_ttl = settings.session_ttl();
+    let pool = EnginePool::new(
+        settings.engine_config(),
+        settings.search_caps(),
+        settings.engines,
+        settings.queue_size,
+        session_ttl,
+    );
+    if pool.engines() > 0 {
+        println!("✓ {} of {} engines initialized successfully", pool.engines(), settings.engines);
+    } else {
+        eprintln!("  Engine endpoints will return errors");
+    }
+
+    let state = Arc::new(AppState {
+        pool,
+        sessions: SessionStore::new(session_ttl),
+        live: LiveRooms::new(session_ttl),
+    });
+
+    // Drop idle game sessions, live games and old search jobs in the background
+    let purged = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(purged.sessions.ttl().clamp(Duration::from_secs(1), Duration::from_secs(60)));
+        loop {
+            interval.tick().await;
+            purged.sessions.purge_expired();
+            purged.live.purge_expired();
+            purged.pool.purge_expired();
+        }
+    });
+
+    let mut app = Router::new()
+        .route("/new", get(new_game))
+        .route("/moves", post(post_moves))
+        .route("/play", post(play_move))
+        .route("/engine-move", post(engine_move))
+        .route("/games", post(create_game))
+        .route("/games/:id", get(get_game).delete(delete_game))
+        .route("/games/:id/moves", get(game_history).post(game_play))
+        .route("/games/:id/legal-moves", get(game_legal_moves))
+        .route("/games/:id/undo", post(game_undo))
+        .route("/games/:id/engine-move", post(game_engine_move))
+        .route("/games/:id/draw", post(game_draw))
+        .route("/analysis", post(create_analysis))
+        .route("/analysis/stream", get(stream_analysis_query).post(stream_analysis))
+        .route("/analysis/:id", get(get_analysis).delete(cancel_analysis))
+        .route("/live", post(create_live_game))
+        .route("/live/:id", get(get_live_game))
+        .route("/live/:id/ws", get(live_socket))
+        .with_state(state);
+
+    // The web client, pointed at this server unless told otherwise
+    if let Some(public_dir) = &settings.public_dir {
+        let client_config = settings.client_config();
+        app = app
+            .route("/config.json", get(move || async move { Json(client_config) }))
+            .fallback_service(ServeDir::new(public_dir));
+        println!("Serving the web client from {}", public_dir.display());
+    }
+    let app = app.layer(cors);
+
+    let addr = settings.address();
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("Error: failed to listen on {}: {}", addr, e);
+            std::process::exit(1);
+        }
+    };
+    println!("Listening on {}", addr);
+    axum::serve(listener, app).await.unwrap();
+}
+
+async fn new_game(headers: HeaderMap) -> Response {
+    let game = Game::new();
+    if json::wants_json(&headers) {
+        return Json(BoardResponse { board: (&game.board).into() }).into_response();
+    }
+    let binary_board = game.to_binary();
+    (StatusCode::OK, binary_board).into_response()
+}
+
+/// Board of a request, from its JSON `board` field or its leading binary bytes
+fn request_board(headers: &HeaderMap, payload: &[u8]) -> Result<Board, StatusCode> {
+    if json::is_json(headers) {
+        let request: BoardRequest = serde_json::from_slice(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+        return Board::try_from(request.board).map_err(|_| StatusCode::BAD_REQUEST);
+    }
+    if payload.len() < BOARD_SIZE + 1 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mut board_array = [0u8; BOARD_SIZE + 1];
+    board_array.copy_from_slice(&payload[..BOARD_SIZE + 1]);
+    Board::from_binary(board_array).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+async fn post_moves(headers: HeaderMap, payload: Bytes) -> Result<Response, StatusCode> {
+    if !json::is_json(&headers) && payload.len() != BOARD_SIZE + 1 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let game = Game::from_board(request_board(&headers, &payload)?);
+    if json::wants_json(&headers) {
+        // Playable moves, with stack/unstack choices expanded
+        let moves = game.legal_moves().into_iter().map(MoveJson::from).collect();
+        return Ok(Json(MovesResponse { moves }).into_response());
+    }
+    let moves = game.get_all_moves();
+    let mut response = Vec::new();
+    for m in moves {
+        response.extend_from_slice(&m.to_u16().to_le_bytes());
+    }
+    Ok(response.into_response())
+}
+
+async fn play_move(headers: HeaderMap, payload: Bytes) -> Result<Response, StatusCode> {
+    let (board, mv) = if json::is_json(&headers) {
+        let request: PlayRequest = serde_json::from_slice(&payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let board = Board::try_from(request.board).map_err(|_| StatusCode::BAD_REQUEST)?;
+        (board, Move::try_from(request.mv).map_err(|_| StatusCode::BAD_REQUEST)?)
+    } else {
+        if payload.len() < BOARD_SIZE + 3 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        let move_bytes = &payload[BOARD_SIZE + 1..BOARD_SIZE + 3];
+        let board = request_board(&headers, &payload)?;
+        (board, Move::from_u16(u16::from_le_bytes([move_bytes[0], move_bytes[1]])))
+    };
+    let mut game = Game::from_board(board);
+    game.apply_move(mv).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if json::wants_json(&headers) {
+        return Ok(Json(BoardResponse { board: (&game.board).into() }).into_response());
+    }
+    let new_binary_board = game.to_binary();
+    Ok(new_binary_board.to_vec().into_response())
+}
+
+/// Search of an engine request: its board, and its options from the JSON body or
+/// the query string (the body wins)
+fn search_request(headers: &HeaderMap, payload: &[u8], query: SearchParams) -> Result<JobRequest, StatusCode> {
+    let (board, params) = if json::is_json(headers) {
+        let request: SearchRequest = serde_json::from_slice(payload).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let board = Board::try_from(request.board).map_err(|_| StatusCode::BAD_REQUEST)?;
+        (board, request.params.or(query))
+    } else {
+        if payload.len() != BOARD_SIZE + 1 {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+        (request_board(headers, payload)?, query)
+    };
+    Ok(JobRequest {
+        board: board.to_binary(),
+        options: params.options().map_err(|_| StatusCode::BAD_REQUEST)?,
+        timeout: params.timeout(),
+    })
+}
+
+async fn engine_move(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchParams>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Response, StatusCode> {
+    let request = search_request(&headers, &payload, query)?;
+    let result = search(&state, request).await?;
+
+    if json::wants_json(&headers) {
+        return Ok(Json(SearchResponse::from(&result)).into_response());
+    }
+    // Return the move as 2-byte little-endian u16
+    Ok(result.best_move.to_le_bytes().to_vec().into_response())
+}
+
+fn submit_status(error: SubmitError) -> StatusCode {
+    match error {
+        SubmitError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        SubmitError::Saturated => StatusCode::TOO_MANY_REQUESTS,
+    }
+}
+
+/// Cancels a job when the request waiting for it goes away
+struct CancelOnDrop(Arc<Job>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Search a position on the engine pool and wait for the result
+async fn search(state: &AppState, request: JobRequest) -> Result<JobResult, StatusCode> {
+    let job = state.pool.submit(request).map_err(submit_status)?;
+    let guard = CancelOnDrop(job);
+    match guard.0.finished().await {
+        JobStatus::Done(result) => Ok(result),
+        JobStatus::TimedOut => Err(StatusCode::GATEWAY_TIMEOUT),
+        status => {
+            eprintln!("Engine error: {:?}", status);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+fn session_status(error: SessionError) -> StatusCode {
+    match error {
+        SessionError::IllegalMove => StatusCode::BAD_REQUEST,
+        SessionError::GameOver | SessionError::NothingToUndo | SessionError::PositionChanged => StatusCode::CONFLICT,
+    }
+}
+
+fn parse_game_id(id: &str) -> Result<u64, StatusCode> {
+    u64::from_str_radix(id, 16).map_err(|_| StatusCode::NOT_FOUND)
+}
+
+/// Run `f` on a session, answering with its state
+fn with_session(
+    state: &AppState,
+    id: &str,
+    f: impl FnOnce(&mut Session) -> Result<(), SessionError>,
+) -> Result<Vec<u8>, StatusCode> {
+    let id = parse_game_id(id)?;
+    state
+        .sessions
+        .with(id, |session| f(session).map(|_| session.to_binary(id)))
+        .ok_or(StatusCode::NOT_FOUND)?
+        .map_err(session_status)
+}
+
+async fn create_game(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<NewGameParams>,
+    payload: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let options = params.options()?;
+    let game = match payload.len() {
+        0 => Game::new(),
+        len if len == BOARD_SIZE + 1 => {
+            let mut board_array = [0u8; BOARD_SIZE + 1];
+            board_array.copy_from_slice(&payload);
+            Game::from_binary(board_array).map_err(|_| StatusCode::BAD_REQUEST)?
+        }
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let session = Session::new(game, options);
+    let id = state.sessions.insert(session);
+    let body = with_session(&state, &format!("{:016x}", id), |_| Ok(()))?;
+    Ok((StatusCode::CREATED, [(header::LOCATION, format!("/games/{:016x}", id))], body))
+}
+
+async fn get_game(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    with_session(&state, &id, |_| Ok(()))
+}
+
+async fn delete_game(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> StatusCode {
+    match parse_game_id(&id) {
+        Ok(id) if state.sessions.remove(id) => StatusCode::NO_CONTENT,
+        _ => StatusCode::NOT_FOUND,
+    }
+}
+
+async fn game_history(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    let id = parse_game_id(&id)?;
+    state
+        .sessions
+        .with(id, |session| session.game.history().flat_map(|mv| mv.to_u16().to_le_bytes()).collect())
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn game_legal_moves(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    let id = parse_game_id(&id)?;
+    state
+        .sessions
+        .with(id, |session| {
+            let moves = if session.is_over() { Vec::new() } else { session.game.legal_moves() };
+            moves.into_iter().flat_map(|mv| mv.to_u16().to_le_bytes()).collect()
+        })
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn game_play(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    payload: Bytes,
+) -> Result<Vec<u8>, StatusCode> {
+    if payload.len() != 2 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let mv = Move::from_u16(u16::from_le_bytes([payload[0], payload[1]]));
+    with_session(&state, &id, |session| session.play(mv))
+}
+
+async fn game_undo(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    with_session(&state, &id, |session| session.undo().map(|_| ()))
+}
+
+async fn game_draw(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    with_session(&state, &id, |session| session.offer_draw().map(|_| ()))
+}
+
+async fn game_engine_move(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Vec<u8>, StatusCode> {
+    let session_id = parse_game_id(&id)?;
+    // Search without holding the sessions lock; the position must not change meanwhile
+    let (board, plies, level) = state
+        .sessions
+        .with(session_id, |session| {
+            if session.is_over() {
+                return Err(StatusCode::CONFLICT);
+            }
+            Ok((session.game.to_binary(), session.game.ply_count(), session.options.level))
+        })
+        .ok_or(StatusCode::NOT_FOUND)??;
+    let request = JobRequest { board, options: SearchOptions { level, ..SearchOptions::default() }, timeout: None };
+    let JobResult { best_move, score, .. } = search(&state, request).await?;
+    with_session(&state, &id, |session| {
+        if session.game.ply_count() != plies || session.game.to_binary() != board {
+            return Err(SessionError::PositionChanged);
+        }
+        session.play_engine(Move::from_u16(best_move), score)
+    })
+}
+
+async fn create_live_game(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let game = if payload.is_empty() {
+        Game::new()
+    } else {
+        Game::from_board(request_board(&headers, &payload)?)
+    };
+    let id = format!("{:016x}", state.live.create(game));
+    let location = format!("/live/{}", id);
+    Ok((StatusCode::CREATED, [(header::LOCATION, location)], Json(serde_json::json!({ "id": id }))))
+}
+
+fn live_room(state: &AppState, id: &str) -> Result<Arc<LiveRoom>, StatusCode> {
+    state.live.get(parse_game_id(id)?).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_live_game(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Response, StatusCode> {
+    let room = live_room(&state, &id)?;
+    let live_state = room.game.lock().unwrap().state();
+    Ok(Json(live_state).into_response())
+}
+
+async fn live_socket(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let room = live_room(&state, &id)?;
+    Ok(ws.on_upgrade(move |socket| live_connection(socket, room)))
+}
+
+/// Relay one connection's requests to its live game and the game's broadcasts back
+async fn live_connection(mut socket: WebSocket, room: Arc<LiveRoom>) {
+    let mut events = room.events.subscribe();
+    let mut seat = None;
+    let current = |room: &LiveRoom| ServerMessage::State { state: room.game.lock().unwrap().state() }.to_text();
+    if socket.send(Message::Text(current(&room))).await.is_err() {
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            incoming = socket.recv() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                room.touch();
+                let reply = match serde_json::from_str::<ClientMessage>(&text) {
+                    Err(e) => Some(ServerMessage::Error { message: format!("Invalid message: {}", e) }),
+                    Ok(ClientMessage::Join { seat: requested, token }) if seat.is_none() => {
+                        let joined = room.game.lock().unwrap().join(requested, token.as_deref());
+                        match joined {
+                            Ok((claimed, token)) => {
+                                seat = Some(claimed);
+                                room.broadcast(&ServerMessage::State { state: room.game.lock().unwrap().state() });
+                                Some(ServerMessage::Joined { seat: claimed, token })
+                            }
+                            Err(message) => Some(ServerMessage::Error { message }),
+                        }
+                    }
+                    Ok(message) => {
+                        let handled = room.game.lock().unwrap().handle(seat, message);
+                        match handled {
+                            Ok(broadcasts) => {
+                                broadcasts.iter().for_each(|message| room.broadcast(message));
+                                None
+                            }
+                            Err(message) => Some(ServerMessage::Error { message }),
+                        }
+                    }
+                };
+                if let Some(reply) = reply {
+                    if socket.send(Message::Text(reply.to_text())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+            event = events.recv() => {
+                let text = match event {
+                    Ok(text) => text,
+                    // Too far behind: resynchronize with the whole state
+                    Err(broadcast::error::RecvError::Lagged(_)) => current(&room),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if socket.send(Message::Text(text)).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+    room.touch();
+}
+
+fn job_json(job: &Job) -> serde_json::Value {
+    let status = job.status();
+    let mut json = serde_json::json!({ "id": format!("{:016x}", job.id), "status": status.name() });
+    match status {
+        JobStatus::Done(result) => json["result"] = serde_json::json!(SearchResponse::from(&result)),
+        JobStatus::Failed(error) => json["error"] = error.into(),
+        _ => {}
+    }
+    json
+}
+
+async fn create_analysis(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchParams>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<impl IntoResponse, StatusCode> {
+    let request = search_request(&headers, &payload, query)?;
+    let job = state.pool.submit(request).map_err(submit_status)?;
+    let location = format!("/analysis/{:016x}", job.id);
+    Ok((StatusCode::ACCEPTED, [(header::LOCATION, location)], Json(job_json(&job))))
+}
+
+fn analysis_job(state: &AppState, id: &str) -> Result<Arc<Job>, StatusCode> {
+    state.pool.job(parse_game_id(id)?).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn get_analysis(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Response, StatusCode> {
+    let job = analysis_job(&state, &id)?;
+    Ok(Json(job_json(&job)).into_response())
+}
+
+async fn cancel_analysis(State(state): State<Arc<AppState>>, Path(id): Path<String>) -> Result<Response, StatusCode> {
+    let job = analysis_job(&state, &id)?;
+    if !job.cancel() {
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(Json(job_json(&job)).into_response())
+}
+
+/// Board of `GET /analysis/stream`, in notation
+#[derive(Deserialize)]
+struct BoardQuery {
+    board: String,
+}
+
+async fn stream_analysis_query(
+    State(state): State<Arc<AppState>>,
+    Query(BoardQuery { board }): Query<BoardQuery>,
+    Query(query): Query<SearchParams>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let board = Board::from_notation(&board).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let request = JobRequest {
+        board: board.to_binary(),
+        options: query.options().map_err(|_| StatusCode::BAD_REQUEST)?,
+        timeout: query.timeout(),
+    };
+    analysis_events(&state, request)
+}
+
+async fn stream_analysis(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<SearchParams>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    let request = search_request(&headers, &payload, query)?;
+    analysis_events(&state, request)
+}
+
+/// Analyze a position until its search time ends or the client goes away, streaming
+/// `info` events after every round and a final `bestmove` (or `error`) event
+fn analysis_events(
+    state: &AppState,
+    mut request: JobRequest,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    request.options.infinite = true;
+    // An open stream must not hold its engine forever
+    let max = state.pool.caps().max_analysis_time;
+    request.timeout = Some(request.timeout.map_or(max, |timeout| timeout.min(max)));
+    let job = state.pool.submit(request).map_err(submit_status)?;
+    let progress = job.progress();
+    // The stream owns the guard: a disconnected client drops it and stops the search
+    let events = stream::unfold(Some((CancelOnDrop(job), progress)), |current| async move {
+        let (guard, mut progress) = current?;
+        let event = tokio::select! {
+            biased;
+            Ok(()) = progress.changed() => {
+                let info = progress.borrow_and_update().as_ref().map(InfoJson::from);
+                let event = Event::default().event("info").json_data(info).expect("infos serialize");
+                return Some((Ok(event), Some((guard, progress))));
+            }
+            status = guard.0.finished() => match status {
+                JobStatus::Done(result) => Event::default().event("bestmove").json_data(SearchResponse::from(&result)),
+                JobStatus::Failed(error) => Event::default().event("error").json_data(error),
+                status => Event::default().event("error").json_data(status.name()),
+            },
+        };
+        Some((Ok(event.expect("results serialize")), None))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,605 @@
+//! Engine pool and search jobs
+//!
+//! Searches never run on the async runtime. An [`EnginePool`] owns a fixed number of
+//! engines, each on its own thread, fed from a bounded queue of [`Job`]s. Submitting to
+//! a full queue fails right away, so the server can answer `429 Too Many Requests`
+//! instead of piling up work.
+//!
+//! A job can be cancelled at any time and may carry a timeout, counted from its
+//! submission. A running search is stopped either way: a cancelled job has no result,
+//! a timed out one answers with the simulations finished in time (or times out without
+//! a result if there were none). Finished jobs are kept for their owners to fetch until
+//! they expire.
+//!
+//! Clients choose their search with [`SearchOptions`]: a level, a search time, a number
+//! of simulations, a depth, a seed and the number of lines to report. The pool clamps
+//! them to its [`SearchCaps`] before queueing the job. An infinite job analyzes
+//! round after round until it is cancelled or its search time ends, publishing its
+//! progress after every round.
+
+use arx_engine::engine::{strength::Strength, EngineConfig, MctsEngine, MoveChoice, RootMoveStats, SearchInfo};
+use arx_engine::BOARD_SIZE;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// Move chosen by a search job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobResult {
+    pub best_move: u16,
+    /// Score for the side to move (None for book and forced moves)
+    pub score: Option<i32>,
+    pub simulations: u64,
+    pub elapsed: Duration,
+    /// Most simulated root moves, best first, as many as the requested lines
+    pub lines: Vec<RootMoveStats>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done(JobResult),
+    Failed(String),
+    Cancelled,
+    /// The timeout expired before any result was found
+    TimedOut,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done(_) => "done",
+            JobStatus::Failed(_) => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::TimedOut => "timed_out",
+        }
+    }
+}
+
+/// How to search, each option overriding the pool's configuration
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchOptions {
+    /// Engine level, applied before the other options
+    pub level: Option<Strength>,
+    /// Time spent searching, counted from the start of the search. Without a number
+    /// of simulations, the search runs up to the maximum simulations until then.
+    pub movetime: Option<Duration>,
+    pub simulations: Option<u32>,
+    pub depth: Option<u32>,
+    pub seed: Option<u64>,
+    /// Root moves reported with the result (0 reports none)
+    pub multipv: usize,
+    /// Analyze until cancelled or out of search time, `simulations` per root move and
+    /// round, instead of searching once
+    pub infinite: bool,
+}
+
+/// Maxima of the options clients may ask for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SearchCaps {
+    pub max_depth: u32,
+    pub max_simulations: u32,
+    pub max_movetime: Duration,
+    pub max_multipv: usize,
+    /// Longest infinite search, queueing included
+    pub max_analysis_time: Duration,
+}
+
+impl Default for SearchCaps {
+    fn default() -> Self {
+        Self {
+            max_depth: 16,
+            max_simulations: 10000,
+            max_movetime: Duration::from_secs(30),
+            max_multipv: 8,
+            max_analysis_time: Duration::from_secs(300),
+        }
+    }
+}
+
+impl SearchCaps {
+    /// `options` with every value brought within the caps. Infinite searches always
+    /// get a search time, the longest allowed by default.
+    pub fn clamp(&self, options: SearchOptions) -> SearchOptions {
+        let movetime = if options.infinite {
+            Some(options.movetime.map_or(self.max_analysis_time, |movetime| movetime.min(self.max_analysis_time)))
+        } else {
+            options.movetime.map(|movetime| movetime.min(self.max_movetime))
+        };
+        SearchOptions {
+            level: options.level,
+            movetime,
+            simulations: options.simulations.map(|simulations| simulations.clamp(1, self.max_simulations)),
+            depth: options.depth.map(|depth| depth.clamp(1, self.max_depth)),
+            seed: options.seed,
+            multipv: options.multipv.min(self.max_multipv),
+            infinite: options.infinite,
+        }
+    }
+
+    /// Engine configuration of a search with `options`, starting from `base`
+    fn config(&self, base: &EngineConfig, options: &SearchOptions) -> EngineConfig {
+        let mut config = match options.level {
+            Some(level) => level.apply(base.clone()),
+            None => base.clone(),
+        };
+        if let Some(depth) = options.depth {
+            config.max_depth = depth;
+        }
+        match (options.simulations, options.movetime) {
+            (Some(simulations), _) => config.simulations_per_move = simulations,
+            (None, Some(_)) if !options.infinite => config.simulations_per_move = self.max_simulations,
+            (None, Some(_)) => {}
+            (None, None) => {}
+        }
+        if options.seed.is_some() {
+            config.seed = options.seed;
+        }
+        config.max_depth = config.max_depth.min(self.max_depth);
+        config.simulations_per_move = config.simulations_per_move.min(self.max_simulations);
+        config
+    }
+}
+
+/// What to search
+#[derive(Clone, Debug)]
+pub struct JobRequest {
+    pub board: [u8; BOARD_SIZE + 1],
+    pub options: SearchOptions,
+    pub timeout: Option<Duration>,
+}
+
+/// One search, queued, running or finished
+pub struct Job {
+    pub id: u64,
+    request: JobRequest,
+    submitted: Instant,
+    /// Stops the running search
+    stop: AtomicBool,
+    cancelled: AtomicBool,
+    status: watch::Sender<JobStatus>,
+    /// Latest progress of an infinite job
+    progress: watch::Sender<Option<SearchInfo>>,
+    finished_at: Mutex<Option<Instant>>,
+}
+
+impl Job {
+    pub fn status(&self) -> JobStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Progress of an infinite job, updated after every round
+    pub fn progress(&self) -> watch::Receiver<Option<SearchInfo>> {
+        self.progress.subscribe()
+    }
+
+    /// Wait until the job is finished and return its final status
+    pub async fn finished(&self) -> JobStatus {
+        let mut status = self.status.subscribe();
+        let finished = status.wait_for(JobStatus::is_finished).await;
+        // The sender lives as long as the job
+        finished.map(|status| status.clone()).unwrap_or(JobStatus::Cancelled)
+    }
+
+    /// Stop the job; queued jobs finish right away and never start. Returns false if
+    /// it already finished.
+    pub fn cancel(&self) -> bool {
+        if self.status.borrow().is_finished() {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        self.stop.store(true, Ordering::Relaxed);
+        self.leave_queue(JobStatus::Cancelled);
+        true
+    }
+
+    fn finish(&self, status: JobStatus) {
+        *self.finished_at.lock().unwrap() = Some(Instant::now());
+        self.status.send_replace(status);
+    }
+
+    /// Move a queued job to `status`. Returns false if it had already left the queue.
+    fn leave_queue(&self, status: JobStatus) -> bool {
+        self.status.send_if_modified(|current| {
+            if *current != JobStatus::Queued {
+                return false;
+            }
+            if status.is_finished() {
+                *self.finished_at.lock().unwrap() = Some(Instant::now());
+            }
+            *current = status;
+            true
+        })
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.request.timeout.map(|timeout| self.submitted + timeout)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmitError {
+    /// No engine could be created
+    Unavailable,
+    /// The queue is full
+    Saturated,
+}
+
+struct Queue {
+    jobs: VecDeque<Arc<Job>>,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+    capacity: usize,
+    jobs: Mutex<HashMap<u64, Arc<Job>>>,
+    base_config: EngineConfig,
+    caps: SearchCaps,
+}
+
+/// Engines searching queued jobs on dedicated threads
+pub struct EnginePool {
+    shared: Arc<Shared>,
+    engines: usize,
+    /// Time finished jobs are kept
+    retention: Duration,
+}
+
+impl EnginePool {
+    /// Start up to `engines` engines with `config`, queueing at most `capacity` jobs
+    /// searching within `caps`. Engines that fail to start are reported and left out.
+    pub fn new(config: EngineConfig, caps: SearchCaps, engines: usize, capacity: usize, retention: Duration) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue { jobs: VecDeque::new(), closed: false }),
+            available: Condvar::new(),
+            capacity,
+            jobs: Mutex::new(HashMap::new()),
+            base_config: config.clone(),
+            caps,
+        });
+
+        let mut started = 0;
+        for index in 0..engines {
+            match MctsEngine::with_config(config.clone()) {
+                Ok(engine) => {
+                    let shared = Arc::clone(&shared);
+                    std::thread::Builder::new()
+                        .name(format!("engine-{}", index))
+                        .spawn(move || run_worker(engine, &shared))
+                        .expect("Failed to spawn engine thread");
+                    started += 1;
+                }
+                Err(e) => eprintln!("⚠ Failed to initialize engine {}: {}", index, e),
+            }
+        }
+        Self { shared, engines: started, retention }
+    }
+
+    /// Number of running engines
+    pub fn engines(&self) -> usize {
+        self.engines
+    }
+
+    pub fn caps(&self) -> &SearchCaps {
+        &self.shared.caps
+    }
+
+    /// Queue a search, with its options clamped to the pool's caps
+    pub fn submit(&self, mut request: JobRequest) -> Result<Arc<Job>, SubmitError> {
+        request.options = self.shared.caps.clamp(request.options);
+        if self.engines == 0 {
+            return Err(SubmitError::Unavailable);
+        }
+        let mut queue = self.shared.queue.lock().unwrap();
+        // Cancelled jobs give their place back
+        queue.jobs.retain(|job| *job.status.borrow() == JobStatus::Queued);
+        if queue.jobs.len() >= self.shared.capacity {
+            return Err(SubmitError::Saturated);
+        }
+
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        let mut rng = rand::thread_rng();
+        let mut id = rng.gen();
+        while jobs.contains_key(&id) {
+            id = rng.gen();
+        }
+        let job = Arc::new(Job {
+            id,
+            request,
+            submitted: Instant::now(),
+            stop: AtomicBool::new(false),
+            cancelled: AtomicBool::new(false),
+            status: watch::channel(JobStatus::Queued).0,
+            progress: watch::channel(None).0,
+            finished_at: Mutex::new(None),
+        });
+        jobs.insert(id, Arc::clone(&job));
+        queue.jobs.push_back(Arc::clone(&job));
+        self.shared.available.notify_one();
+        Ok(job)
+    }
+
+    pub fn job(&self, id: u64) -> Option<Arc<Job>> {
+        self.shared.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Forget jobs finished longer than the retention time ago
+    pub fn purge_expired(&self) -> usize {
+        let mut jobs = self.shared.jobs.lock().unwrap();
+        let before = jobs.len();
+        jobs.retain(|_, job| job.finished_at.lock().unwrap().is_none_or(|at| at.elapsed() <= self.retention));
+        before - jobs.len()
+    }
+}
+
+impl Drop for EnginePool {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.available.notify_all();
+    }
+}
+
+/// Search jobs from the queue until the pool is dropped
+fn run_worker(mut engine: MctsEngine, shared: &Shared) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.closed {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop_front() {
+                    break job;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+        run_job(&mut engine, shared, &job);
+    }
+}
+
+fn run_job(engine: &mut MctsEngine, shared: &Shared, job: &Job) {
+    let deadline = job.deadline();
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        job.leave_queue(JobStatus::TimedOut);
+        return;
+    }
+    // Cancelled jobs already finished
+    if !job.leave_queue(JobStatus::Running) {
+        return;
+    }
+
+    // Only reconfigure when the search changes, so the search tree is kept between moves
+    let options = job.request.options;
+    let config = shared.caps.config(&shared.base_config, &options);
+    let current = engine.config();
+    if (current.max_depth, current.simulations_per_move, current.move_selection, current.seed)
+        != (config.max_depth, config.simulations_per_move, config.move_selection, config.seed)
+    {
+        engine.set_config(config);
+    }
+    // A seeded search must not depend on what this engine searched before
+    if options.seed.is_some() {
+        engine.clear_tree();
+    }
+
+    let started = Instant::now();
+    let deadline = match (deadline, options.movetime.map(|movetime| started + movetime)) {
+        (Some(deadline), Some(end)) => Some(deadline.min(end)),
+        (deadline, end) => deadline.or(end),
+    };
+    let simulations = engine.get_statistics().simulations_run;
+    // Stop the search at the deadline, unless it finished first
+    let finished = (Mutex::new(false), Condvar::new());
+    let outcome = std::thread::scope(|scope| {
+        if let Some(deadline) = deadline {
+            let finished = &finished;
+            scope.spawn(move || {
+                let (lock, condvar) = finished;
+                let done = lock.lock().unwrap();
+                let timeout = deadline.saturating_duration_since(Instant::now());
+                let (done, _) = condvar.wait_timeout_while(done, timeout, |done| !*done).unwrap();
+                if !*done {
+                    job.stop.store(true, Ordering::Relaxed);
+                }
+            });
+        }
+        let outcome = if options.infinite {
+            let report = |info: &SearchInfo| {
+                job.progress.send_replace(Some(info.clone()));
+            };
+            engine.analyze(&job.request.board, &job.stop, report).map(|result| MoveChoice {
+                mv: result.best_move,
+                score: Some(result.score),
+                root_moves: result.root_moves,
+            })
+        } else {
+            engine.find_best_move_until(&job.request.board, &job.stop)
+        };
+        *finished.0.lock().unwrap() = true;
+        finished.1.notify_all();
+        outcome
+    });
+
+    let status = match outcome {
+        _ if job.cancelled.load(Ordering::Relaxed) => JobStatus::Cancelled,
+        Ok(choice) => {
+            let mut lines: Vec<_> = choice.root_moves.into_iter().filter(|stats| stats.visits > 0).collect();
+            lines.sort_by(|a, b| b.visits.cmp(&a.visits).then(b.mean_score().total_cmp(&a.mean_score())));
+            lines.truncate(options.multipv);
+            JobStatus::Done(JobResult {
+                best_move: choice.mv,
+                score: choice.score,
+                simulations: engine.get_statistics().simulations_run - simulations,
+                elapsed: started.elapsed(),
+                lines,
+            })
+        }
+        // Stopped by the timeout, or by the search time before any simulation finished
+        Err(_) if job.stop.load(Ordering::Relaxed) => JobStatus::TimedOut,
+        Err(e) => JobStatus::Failed(e),
+    };
+    job.finish(status);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arx_engine::Game;
+
+    fn pool(simulations_per_move: u32, capacity: usize) -> Option<EnginePool> {
+        let config = EngineConfig {
+            max_depth: 3,
+            simulations_per_move,
+            use_gpu_simulation: false,
+            ..EngineConfig::default()
+        };
+        let caps = SearchCaps { max_simulations: 1_000_000, ..SearchCaps::default() };
+        let pool = EnginePool::new(config, caps, 1, capacity, Duration::from_secs(60));
+        if pool.engines() == 0 {
+            println!("Skipping test: GPU not available");
+            return None;
+        }
+        Some(pool)
+    }
+
+    fn request(timeout: Option<Duration>) -> JobRequest {
+        JobRequest { board: Game::new().to_binary(), options: SearchOptions::default(), timeout }
+    }
+
+    #[test]
+    fn test_jobs_run_and_time_out() {
+        let Some(pool) = pool(2, 4) else {
+            return;
+        };
+        let job = pool.submit(request(None)).unwrap();
+        let JobStatus::Done(result) = pollster::block_on(job.finished()) else {
+            panic!("the job did not finish: {:?}", job.status());
+        };
+        assert!(Game::new().legal_moves().iter().any(|mv| mv.to_u16() == result.best_move));
+        assert!(result.lines.is_empty());
+        assert_eq!(pool.job(job.id).unwrap().status(), JobStatus::Done(result));
+
+        // A search far too long for its timeout answers with what it found
+        let pool = self::pool(1_000_000, 4).unwrap();
+        let job = pool.submit(request(Some(Duration::from_millis(300)))).unwrap();
+        assert!(matches!(pollster::block_on(job.finished()), JobStatus::Done(_)));
+    }
+
+    #[test]
+    fn test_search_options_are_clamped() {
+        let caps = SearchCaps::default();
+        let options = SearchOptions {
+            movetime: Some(Duration::from_secs(600)),
+            depth: Some(100),
+            multipv: 50,
+            ..SearchOptions::default()
+        };
+        let clamped = caps.clamp(options);
+        assert_eq!(clamped.movetime, Some(caps.max_movetime));
+        assert_eq!(clamped.depth, Some(caps.max_depth));
+        assert_eq!(clamped.multipv, caps.max_multipv);
+        let infinite = caps.clamp(SearchOptions { infinite: true, ..SearchOptions::default() });
+        assert_eq!(infinite.movetime, Some(caps.max_analysis_time));
+
+        // A search time without simulations searches up to the maximum
+        let config = caps.config(&EngineConfig::default(), &clamped);
+        assert_eq!(config.simulations_per_move, caps.max_simulations);
+        let level = SearchOptions { level: Some(Strength::Beginner), seed: Some(7), ..SearchOptions::default() };
+        let config = caps.config(&EngineConfig::default(), &level);
+        assert_eq!((config.max_depth, config.simulations_per_move, config.seed), (2, 50, Some(7)));
+    }
+
+    #[test]
+    fn test_search_time_and_lines() {
+        let Some(pool) = pool(2, 4) else {
+            return;
+        };
+        let options = SearchOptions { movetime: Some(Duration::from_millis(200)), multipv: 3, ..SearchOptions::default() };
+        let job = pool.submit(JobRequest { options, ..request(None) }).unwrap();
+        let JobStatus::Done(result) = pollster::block_on(job.finished()) else {
+            panic!("the job did not finish: {:?}", job.status());
+        };
+        assert_eq!(result.lines.len(), 3);
+        assert!(result.lines.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+    }
+
+    #[test]
+    fn test_seeded_jobs_are_reproducible() {
+        let Some(pool) = pool(4, 4) else {
+            return;
+        };
+        let seeded = SearchOptions { seed: Some(42), multipv: 8, ..SearchOptions::default() };
+        let mut results = Vec::new();
+        for options in [seeded, SearchOptions::default(), seeded] {
+            let job = pool.submit(JobRequest { options, ..request(None) }).unwrap();
+            let JobStatus::Done(result) = pollster::block_on(job.finished()) else {
+                panic!("the job did not finish: {:?}", job.status());
+            };
+            results.push(JobResult { elapsed: Duration::ZERO, ..result });
+        }
+        assert!(!results[0].lines.is_empty());
+        assert_eq!(results[0], results[2]);
+    }
+
+    #[test]
+    fn test_infinite_jobs_report_progress() {
+        let Some(pool) = pool(5, 4) else {
+            return;
+        };
+        let options = SearchOptions { infinite: true, multipv: 1, ..SearchOptions::default() };
+        let job = pool.submit(JobRequest { options, ..request(None) }).unwrap();
+        let mut progress = job.progress();
+        pollster::block_on(progress.wait_for(|info| info.as_ref().is_some_and(|info| info.depth >= 2))).unwrap();
+        assert_eq!(job.status(), JobStatus::Running);
+
+        assert!(job.cancel());
+        assert_eq!(pollster::block_on(job.finished()), JobStatus::Cancelled);
+        let info = job.progress().borrow().clone().unwrap();
+        assert_eq!(info.pv[0], info.best_move);
+
+        // A search time ends the analysis with its best move
+        let options = SearchOptions { movetime: Some(Duration::from_millis(200)), ..options };
+        let job = pool.submit(JobRequest { options, ..request(None) }).unwrap();
+        let JobStatus::Done(result) = pollster::block_on(job.finished()) else {
+            panic!("the job did not finish: {:?}", job.status());
+        };
+        assert_eq!(Some(result.best_move), job.progress().borrow().as_ref().map(|info| info.best_move));
+    }
+
+    #[test]
+    fn test_saturation_and_cancellation() {
+        let Some(pool) = pool(1_000_000, 1) else {
+            return;
+        };
+        let running = pool.submit(request(None)).unwrap();
+        while running.status() == JobStatus::Queued {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        let queued = pool.submit(request(None)).unwrap();
+        assert_eq!(pool.submit(request(None)).err(), Some(SubmitError::Saturated));
+
+        // A cancelled job finishes at once and leaves room in the queue
+        assert!(queued.cancel());
+        assert_eq!(queued.status(), JobStatus::Cancelled);
+        let next = pool.submit(request(None)).unwrap();
+        assert!(next.cancel());
+        assert!(running.cancel());
+        assert_eq!(pollster::block_on(running.finished()), JobStatus::Cancelled);
+        assert_eq!(pollster::block_on(next.finished()), JobStatus::Cancelled);
+        assert!(!running.cancel());
+        assert_eq!(pool.purge_expired(), 0);
+    }
+}
@@ -0,0 +1,341 @@
+//! In-memory game sessions
+//!
+//! Each `/games` resource is a [`Session`]: a [`Game`] with its move history, the
+//! options it was created with, and an [`Adjudicator`] following the engine's scores
+//! when the engine plays in it. Sessions live in a [`SessionStore`] and expire after
+//! staying unused for the store's time to live.
+
+use arx_engine::engine::adjudication::{AdjudicationConfig, Adjudicator, Verdict};
+use arx_engine::engine::strength::Strength;
+use arx_engine::{Color, Game, Move, BOARD_SIZE};
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Options chosen when creating a game
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SessionOptions {
+    /// Engine level used by the game's engine moves (server default when None)
+    pub level: Option<Strength>,
+    /// Resignation and draw rules of the engine
+    pub adjudication: AdjudicationConfig,
+}
+
+/// Result of a game, from white's point of view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Ongoing = 0,
+    WhiteWon = 1,
+    BlackWon = 2,
+    Draw = 3,
+}
+
+/// How a finished game ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    None = 0,
+    KingCaptured = 1,
+    /// The side to move had no legal move
+    NoMoves = 2,
+    /// A player (or the engine) resigned
+    Resignation = 3,
+    DrawAgreed = 4,
+}
+
+/// Why a request could not change the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    /// The move is not legal in the current position
+    IllegalMove,
+    /// The game is already over
+    GameOver,
+    /// There is no move to take back
+    NothingToUndo,
+    /// The position changed while the engine was searching it
+    PositionChanged,
+}
+
+impl std::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SessionError::IllegalMove => "Illegal move",
+            SessionError::GameOver => "The game is over",
+            SessionError::NothingToUndo => "No move to take back",
+            SessionError::PositionChanged => "The position changed during the search",
+        })
+    }
+}
+
+pub struct Session {
+    pub game: Game,
+    pub options: SessionOptions,
+    pub outcome: Outcome,
+    pub reason: Reason,
+    /// The engine offered a draw with its last move
+    pub draw_offered: bool,
+    adjudicator: Adjudicator,
+    last_access: Instant,
+}
+
+impl Session {
+    pub fn new(game: Game, options: SessionOptions) -> Self {
+        let mut session = Self {
+            game: game.with_history(),
+            options,
+            outcome: Outcome::Ongoing,
+            reason: Reason::None,
+            draw_offered: false,
+            adjudicator: Adjudicator::new(options.adjudication),
+            last_access: Instant::now(),
+        };
+        session.update_outcome();
+        session
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.outcome != Outcome::Ongoing
+    }
+
+    /// Play a legal move of the side to move
+    pub fn play(&mut self, mv: Move) -> Result<(), SessionError> {
+        if self.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        if !self.game.legal_moves().contains(&mv) {
+            return Err(SessionError::IllegalMove);
+        }
+        self.game.apply_move(mv).map_err(|_| SessionError::IllegalMove)?;
+        self.draw_offered = false;
+        self.update_outcome();
+        Ok(())
+    }
+
+    /// Play the engine's move, or resign on its behalf, according to its search score
+    pub fn play_engine(&mut self, mv: Move, score: Option<i32>) -> Result<(), SessionError> {
+        if self.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        match score.map_or(Verdict::Continue, |score| self.adjudicator.record(score)) {
+            Verdict::Resign => self.resign(self.game.board.color_to_move()),
+            verdict => {
+                self.play(mv)?;
+                self.draw_offered = verdict == Verdict::OfferDraw && !self.is_over();
+                Ok(())
+            }
+        }
+    }
+
+    /// Offer a draw, or accept the engine's pending offer. Returns whether the game
+    /// ended in a draw.
+    pub fn offer_draw(&mut self) -> Result<bool, SessionError> {
+        if self.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        let agreed = self.draw_offered || self.adjudicator.accepts_draw();
+        if agreed {
+            self.agree_draw()?;
+        }
+        Ok(agreed)
+    }
+
+    /// End the game in a draw both sides agreed to
+    pub fn agree_draw(&mut self) -> Result<(), SessionError> {
+        if self.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        self.end(Outcome::Draw, Reason::DrawAgreed);
+        Ok(())
+    }
+
+    /// End the game with `loser` giving up
+    pub fn resign(&mut self, loser: Color) -> Result<(), SessionError> {
+        if self.is_over() {
+            return Err(SessionError::GameOver);
+        }
+        self.end(self.winner_against(loser), Reason::Resignation);
+        Ok(())
+    }
+
+    /// Take back the last move, reopening the game if it ended, and return it. A
+    /// resignation or agreed draw is taken back on its own, leaving the moves in
+    /// place, and returns `None`. The engine's score history starts over.
+    pub fn undo(&mut self) -> Result<Option<Move>, SessionError> {
+        let mv = match self.reason {
+            Reason::Resignation | Reason::DrawAgreed => None,
+            _ => Some(self.game.undo().ok_or(SessionError::NothingToUndo)?),
+        };
+        self.draw_offered = false;
+        self.adjudicator.reset();
+        self.update_outcome();
+        Ok(mv)
+    }
+
+    /// State as sent to clients:
+    /// `id (u64 LE) | board | outcome | reason | flags | repetitions | ply count (u16 LE)`
+    pub fn to_binary(&self, id: u64) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(8 + BOARD_SIZE + 1 + 6);
+        bytes.extend_from_slice(&id.to_le_bytes());
+        bytes.extend_from_slice(&self.game.to_binary());
+        bytes.push(self.outcome as u8);
+        bytes.push(self.reason as u8);
+        bytes.push(self.draw_offered as u8);
+        bytes.push(self.game.repetition_count().min(u8::MAX as usize) as u8);
+        bytes.extend_from_slice(&(self.game.ply_count().min(u16::MAX as usize) as u16).to_le_bytes());
+        bytes
+    }
+
+    fn winner_against(&self, loser: Color) -> Outcome {
+        match loser {
+            Color::White => Outcome::BlackWon,
+            Color::Black => Outcome::WhiteWon,
+        }
+    }
+
+    fn end(&mut self, outcome: Outcome, reason: Reason) {
+        self.outcome = outcome;
+        self.reason = reason;
+        self.draw_offered = false;
+    }
+
+    /// Outcome read from the position alone
+    fn update_outcome(&mut self) {
+        let board = &self.game.board;
+        if board.is_game_over() {
+            let loser = if board.find_king(Color::White).is_none() { Color::White } else { Color::Black };
+            self.end(self.winner_against(loser), Reason::KingCaptured);
+        } else if self.game.legal_moves().is_empty() {
+            self.end(Outcome::Draw, Reason::NoMoves);
+        } else {
+            self.outcome = Outcome::Ongoing;
+            self.reason = Reason::None;
+        }
+    }
+}
+
+/// Sessions by ID, forgotten `ttl` after their last access
+pub struct SessionStore {
+    sessions: Mutex<HashMap<u64, Session>>,
+    ttl: Duration,
+}
+
+impl SessionStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Store a new session and return its random ID
+    pub fn insert(&self, session: Session) -> u64 {
+        let mut sessions = self.sessions.lock().unwrap();
+        let mut rng = rand::thread_rng();
+        let mut id = rng.gen();
+        while sessions.contains_key(&id) {
+            id = rng.gen();
+        }
+        sessions.insert(id, session);
+        id
+    }
+
+    /// Run `f` on a live session, refreshing its expiry. None when the ID is unknown
+    /// or the session expired.
+    pub fn with<R>(&self, id: u64, f: impl FnOnce(&mut Session) -> R) -> Option<R> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        if sessions.get(&id).is_some_and(|session| now - session.last_access > self.ttl) {
+            sessions.remove(&id);
+        }
+        let session = sessions.get_mut(&id)?;
+        session.last_access = now;
+        Some(f(session))
+    }
+
+    pub fn remove(&self, id: u64) -> bool {
+        self.sessions.lock().unwrap().remove(&id).is_some()
+    }
+
+    /// Drop every expired session and return how many were dropped
+    pub fn purge_expired(&self) -> usize {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Instant::now();
+        let before = sessions.len();
+        sessions.retain(|_, session| now - session.last_access <= self.ttl);
+        before - sessions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arx_engine::{Board, Position};
+
+    #[test]
+    fn test_session_play_undo_and_outcome() {
+        let mut session = Session::new(Game::new(), SessionOptions::default());
+        let start = session.game.board;
+        let mv = session.game.legal_moves()[0];
+        let illegal = Move { from: Position::new(4, 4), to: Position::new(4, 5), unstack: false };
+        assert_eq!(session.play(illegal), Err(SessionError::IllegalMove));
+        assert_eq!(session.undo(), Err(SessionError::NothingToUndo));
+        session.play(mv).unwrap();
+        assert_eq!(session.game.ply_count(), 1);
+
+        let state = session.to_binary(7);
+        assert_eq!(state.len(), 8 + BOARD_SIZE + 1 + 6);
+        assert_eq!(state[..8], 7u64.to_le_bytes());
+        assert_eq!(state[8 + BOARD_SIZE + 1..], [0, 0, 0, 1, 1, 0]);
+
+        assert_eq!(session.undo(), Ok(Some(mv)));
+        assert_eq!(session.game.board, start);
+
+        // The engine resigns on its first losing score
+        let adjudication = AdjudicationConfig { resign_threshold: Some(-500), resign_moves: 1, ..AdjudicationConfig::default() };
+        let mut session = Session::new(Game::new(), SessionOptions { level: None, adjudication });
+        session.play_engine(mv, Some(-100)).unwrap();
+        let reply = session.game.legal_moves()[0];
+        session.play_engine(reply, Some(-600)).unwrap();
+        assert_eq!((session.outcome, session.reason), (Outcome::WhiteWon, Reason::Resignation));
+        assert_eq!(session.play(reply), Err(SessionError::GameOver));
+        // Undoing the resignation keeps the one move played before it
+        assert_eq!(session.undo(), Ok(None));
+        assert!(!session.is_over());
+        assert_eq!(session.game.ply_count(), 1);
+        assert_eq!(session.undo(), Ok(Some(mv)));
+
+        // So does undoing an agreed draw, even before any move
+        let mut session = Session::new(Game::new(), SessionOptions::default());
+        session.agree_draw().unwrap();
+        assert_eq!(session.undo(), Ok(None));
+        assert_eq!(session.reason, Reason::None);
+        assert_eq!(session.undo(), Err(SessionError::NothingToUndo));
+
+        // Positions without a King are already decided
+        let mut board = Board::new();
+        board.set_piece(&Position::new(4, 0), None);
+        let session = Session::new(Game::from_board(board), SessionOptions::default());
+        assert_eq!(session.outcome, Outcome::WhiteWon);
+    }
+
+    #[test]
+    fn test_sessions_expire() {
+        let store = SessionStore::new(Duration::from_millis(50));
+        let id = store.insert(Session::new(Game::new(), SessionOptions::default()));
+        let other = store.insert(Session::new(Game::new(), SessionOptions::default()));
+        assert_eq!(store.with(id, |session| session.game.ply_count()), Some(0));
+        assert!(store.remove(other));
+        assert!(store.with(other, |_| ()).is_none());
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert_eq!(store.purge_expired(), 1);
+        assert!(store.with(id, |_| ()).is_none());
+    }
+}
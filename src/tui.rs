@@ -1,4 +1,6 @@
-use crate::{Color, Game, Piece, Position, BOARD_DIMENSION, cli_rendering::piece_to_char};
+use crate::{Color, Game, Move, Piece, Position, BOARD_DIMENSION, cli_rendering::piece_to_char};
+use crate::engine::adjudication::{AdjudicationConfig, Adjudicator, Verdict};
+use crate::engine::{strength::Strength, EngineConfig, MctsEngine};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
     execute,
@@ -19,7 +21,31 @@ pub enum GameState {
     SelectingPiece,
     SelectingTarget { from: Position },
     ConfirmUnstack { from: Position, to: Position, unstack: bool },
-    GameOver { winner: Color },
+    /// Finished game, without winner for a draw
+    GameOver { winner: Option<Color> },
+}
+
+/// Engine playing one side of a TUI game
+pub struct EngineOpponent {
+    engine: MctsEngine,
+    color: Color,
+    strength: Strength,
+    adjudicator: Adjudicator,
+}
+
+impl EngineOpponent {
+    /// Engine playing `color` at a difficulty level, on top of `config`
+    pub fn new(color: Color, strength: Strength, config: EngineConfig) -> Result<Self, String> {
+        let engine = MctsEngine::with_config(strength.apply(config))?;
+        let adjudicator = Adjudicator::new(AdjudicationConfig::default());
+        Ok(Self { engine, color, strength, adjudicator })
+    }
+
+    /// Let the engine resign and offer or accept draws under these rules
+    pub fn with_adjudication(mut self, config: AdjudicationConfig) -> Self {
+        self.adjudicator = Adjudicator::new(config);
+        self
+    }
 }
 
 pub struct App {
@@ -27,6 +53,18 @@ pub struct App {
     cursor_position: Position,
     game_state: GameState,
     highlighted_moves: Vec<Position>,
+    show_threats: bool,
+    opponent: Option<EngineOpponent>,
+    /// The engine offered a draw with its last move
+    draw_offered: bool,
+    /// Message about the engine's decisions, shown in the title
+    notice: Option<String>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl App {
@@ -39,7 +77,7 @@ impl App {
         let game_state = if game.board.is_game_over() {
             // Determine winner: if white to move but game is over, black won (and vice versa)
             let winner = if game.board.is_white_to_move() { Color::Black } else { Color::White };
-            GameState::GameOver { winner }
+            GameState::GameOver { winner: Some(winner) }
         } else {
             GameState::SelectingPiece
         };
@@ -49,9 +87,91 @@ impl App {
             cursor_position: Position::new(0, 0),
             game_state,
             highlighted_moves: Vec::new(),
+            show_threats: false,
+            opponent: None,
+            draw_offered: false,
+            notice: None,
+        }
+    }
+
+    /// Let the engine play one side
+    pub fn with_opponent(mut self, opponent: EngineOpponent) -> Self {
+        self.opponent = Some(opponent);
+        self
+    }
+
+    /// Whether the engine has to play now
+    pub fn engine_to_move(&self) -> bool {
+        let Some(opponent) = &self.opponent else {
+            return false;
+        };
+        self.game_state == GameState::SelectingPiece && self.game.board.color_to_move() == opponent.color
+    }
+
+    /// Let the engine play its move, or resign
+    pub fn play_engine_move(&mut self) -> Result<(), String> {
+        let board = self.game.to_binary();
+        let opponent = self.opponent.as_mut().ok_or("No engine opponent")?;
+        let (mv, score) = opponent.engine.find_best_move_scored(&board)?;
+        let verdict = score.map_or(Verdict::Continue, |score| opponent.adjudicator.record(score));
+        if verdict == Verdict::Resign {
+            self.game_state = GameState::GameOver { winner: Some(opponent.color.opposite()) };
+            self.highlighted_moves.clear();
+            self.notice = Some("Engine resigns".to_string());
+            return Ok(());
+        }
+
+        self.apply_move_and_update_state(Move::from_u16(mv))?;
+        if verdict == Verdict::OfferDraw && !matches!(self.game_state, GameState::GameOver { .. }) {
+            self.draw_offered = true;
+            self.notice = Some("Engine offers a draw".to_string());
+        }
+        Ok(())
+    }
+
+    /// Accept the draw offered by the engine
+    pub fn accept_draw(&mut self) {
+        if self.draw_offered && !matches!(self.game_state, GameState::GameOver { .. }) {
+            self.end_in_draw();
         }
     }
 
+    /// Offer a draw to the engine, which accepts or declines it right away
+    pub fn offer_draw(&mut self) {
+        if matches!(self.game_state, GameState::GameOver { .. }) {
+            return;
+        }
+        let Some(opponent) = &self.opponent else {
+            return;
+        };
+        if opponent.adjudicator.accepts_draw() {
+            self.end_in_draw();
+        } else {
+            self.notice = Some("Engine declines the draw".to_string());
+        }
+    }
+
+    fn end_in_draw(&mut self) {
+        self.game_state = GameState::GameOver { winner: None };
+        self.highlighted_moves.clear();
+        self.draw_offered = false;
+        self.notice = Some("Draw agreed".to_string());
+    }
+
+    /// Switch the engine to the next difficulty level
+    pub fn cycle_strength(&mut self) {
+        if let Some(opponent) = &mut self.opponent {
+            opponent.strength = opponent.strength.next();
+            let config = opponent.strength.apply(opponent.engine.config().clone());
+            opponent.engine.set_config(config);
+        }
+    }
+
+    /// Toggle the overlay showing squares attacked by the opponent and hanging pieces
+    pub fn toggle_threats(&mut self) {
+        self.show_threats = !self.show_threats;
+    }
+
     pub fn move_cursor(&mut self, dx: isize, dy: isize) {
         // Don't allow cursor movement when game is over
         if matches!(self.game_state, GameState::GameOver { .. }) {
@@ -67,9 +187,12 @@ impl App {
     /// Applies a move, updates game state and highlights, handling game over.
     fn apply_move_and_update_state(&mut self, game_move: crate::Move) -> Result<(), String> {
         self.game.apply_move(game_move)?;
+        // Playing on declines a pending draw offer
+        self.draw_offered = false;
+        self.notice = None;
         if self.game.board.is_game_over() {
             let winner = if self.game.board.is_white_to_move() { Color::Black } else { Color::White };
-            self.game_state = GameState::GameOver { winner };
+            self.game_state = GameState::GameOver { winner: Some(winner) };
             self.highlighted_moves.clear();
         } else {
             self.game_state = GameState::SelectingPiece;
@@ -191,9 +314,9 @@ impl App {
             output.push('+');
             output.push_str(&piece_to_char(&piece.bottom));
         } else {
-            output.push_str(" ");
+            output.push(' ');
             output.push_str(&piece_to_char(&piece.bottom));
-            output.push_str(" ");
+            output.push(' ');
         }
         
         output
@@ -201,6 +324,15 @@ impl App {
 }
 
 pub fn run_tui(game: Option<Game>) -> Result<Game, Box<dyn std::error::Error>> {
+    run_tui_app(App::from_game(game.unwrap_or_default()))
+}
+
+/// Run the TUI with the engine playing one side
+pub fn run_tui_against_engine(game: Option<Game>, opponent: EngineOpponent) -> Result<Game, Box<dyn std::error::Error>> {
+    run_tui_app(App::from_game(game.unwrap_or_default()).with_opponent(opponent))
+}
+
+fn run_tui_app(mut app: App) -> Result<Game, Box<dyn std::error::Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -209,13 +341,6 @@ pub fn run_tui(game: Option<Game>) -> Result<Game, Box<dyn std::error::Error>> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app
-    let mut app = if let Some(game) = game {
-        App::from_game(game)
-    } else {
-        App::new()
-    };
-
     let res = run_app(&mut terminal, &mut app);
 
     // Restore terminal
@@ -238,6 +363,15 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
     loop {
         terminal.draw(|f| ui(f, app))?;
 
+        if app.engine_to_move() {
+            if let Err(e) = app.play_engine_move() {
+                // Let the human play on rather than stalling the game
+                app.notice = Some(format!("Engine stopped: {}", e));
+                app.opponent = None;
+            }
+            continue;
+        }
+
         if let Event::Key(key) = event::read()? {
             if key.kind == KeyEventKind::Press {
                 match key.code {
@@ -255,6 +389,10 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> io::Result<
                             }
                         }
                     }
+                    KeyCode::Char('t') => app.toggle_threats(),
+                    KeyCode::Char('l') => app.cycle_strength(),
+                    KeyCode::Char('d') => app.offer_draw(),
+                    KeyCode::Char('a') => app.accept_draw(),
                     KeyCode::Up => app.move_cursor(0, -1),
                     KeyCode::Down => app.move_cursor(0, 1),
                     KeyCode::Left => app.move_cursor(-1, 0),
@@ -278,9 +416,15 @@ fn ui(f: &mut Frame, app: &App) {
 
     // Title
     let title = match app.game_state {
+        GameState::SelectingPiece if app.engine_to_move() => {
+            let level = app.opponent.as_ref().map(|opponent| opponent.strength).unwrap_or(Strength::Expert);
+            format!("Engine ({}) is thinking...", level)
+        }
         GameState::SelectingPiece => {
-            format!("{} to move - Select a piece", 
-                if app.game.board.is_white_to_move() { "White" } else { "Black" })
+            let board = &app.game.board;
+            format!("{} to move - Select a piece{}",
+                if board.is_white_to_move() { "White" } else { "Black" },
+                if board.king_in_danger(board.color_to_move()) { " - ⚠ King in danger!" } else { "" })
         }
         GameState::SelectingTarget { .. } => {
             "Select target position".to_string()
@@ -288,13 +432,18 @@ fn ui(f: &mut Frame, app: &App) {
         GameState::ConfirmUnstack { .. } => {
             "Confirm Unstack/Stack".to_string()
         }
-        GameState::GameOver { winner } => {
+        GameState::GameOver { winner: Some(winner) } => {
             format!("🎉 GAME OVER - {} WINS! 🎉", 
                 match winner {
                     Color::White => "WHITE",
                     Color::Black => "BLACK"
                 })
         }
+        GameState::GameOver { winner: None } => "GAME OVER - DRAW".to_string(),
+    };
+    let title = match &app.notice {
+        Some(notice) => format!("{} - {}", title, notice),
+        None => title,
     };
     
     let title_paragraph = Paragraph::new(title)
@@ -341,7 +490,7 @@ fn ui(f: &mut Frame, app: &App) {
             ]
         }
         _ => {
-            vec![
+            let mut lines = vec![
                 Line::from(vec![
                     Span::raw("Use "),
                     Span::styled("Arrow Keys", Style::default().add_modifier(Modifier::BOLD)),
@@ -352,10 +501,28 @@ fn ui(f: &mut Frame, app: &App) {
                 Line::from(vec![
                     Span::styled("Esc", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to cancel selection, "),
+                    Span::styled("T", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to toggle threats, "),
                     Span::styled("Q", Style::default().add_modifier(Modifier::BOLD)),
                     Span::raw(" to quit"),
                 ]),
-            ]
+            ];
+            if let Some(opponent) = &app.opponent {
+                let mut spans = vec![
+                    Span::raw(format!("Engine level: {} - ", opponent.strength)),
+                    Span::styled("L", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to change, "),
+                    Span::styled("D", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::raw(" to offer a draw"),
+                ];
+                if app.draw_offered {
+                    spans.push(Span::raw(", "));
+                    spans.push(Span::styled("A", Style::default().add_modifier(Modifier::BOLD)));
+                    spans.push(Span::raw(" to accept the engine's"));
+                }
+                lines.push(Line::from(spans));
+            }
+            lines
         }
     };
     
@@ -382,6 +549,15 @@ fn render_board(f: &mut Frame, app: &App, area: Rect) {
         .title("Board");
     f.render_widget(block, area);
     
+    // Threat overlay: squares attacked by the opponent and our undefended pieces
+    let game_over = matches!(app.game_state, GameState::GameOver { .. });
+    let (threatened, hanging) = if app.show_threats && !game_over {
+        let color = board.color_to_move();
+        (board.attacked_squares(color.opposite()), board.hanging_pieces(color))
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
     // Draw the board content manually using text with box drawing characters
     let mut board_lines = Vec::new();
     
@@ -412,7 +588,7 @@ fn render_board(f: &mut Frame, app: &App, area: Rect) {
             
             // Check if this position has a piece
             if let Some(piece) = board.get_piece(&position) {
-                cell_content = format!("{}", app.get_piece_display(piece));
+                cell_content = app.get_piece_display(piece);
                 
                 // Color the piece based on its color
                 cell_style = match piece.color {
@@ -429,6 +605,13 @@ fn render_board(f: &mut Frame, app: &App, area: Rect) {
             else if app.highlighted_moves.contains(&position) && !matches!(app.game_state, GameState::GameOver { .. }) {
                 cell_style = cell_style.bg(RatatuiColor::Green);
             }
+            // Highlight threats when the overlay is enabled
+            else if hanging.contains(&position) {
+                cell_style = cell_style.bg(RatatuiColor::Yellow);
+            }
+            else if threatened.contains(&position) {
+                cell_style = cell_style.bg(RatatuiColor::DarkGray);
+            }
             
             row_spans.push(Span::styled(cell_content, cell_style));
             row_spans.push(Span::raw("┃"));
@@ -447,13 +630,14 @@ fn render_board(f: &mut Frame, app: &App, area: Rect) {
     
     // Current player indicator or winner message
     let status_message = match app.game_state {
-        GameState::GameOver { winner } => {
+        GameState::GameOver { winner: Some(winner) } => {
             format!("            {} WINS THE GAME!", 
                 match winner {
                     Color::White => "WHITE",
                     Color::Black => "BLACK"
                 })
         }
+        GameState::GameOver { winner: None } => "             THE GAME IS DRAWN".to_string(),
         _ => {
             let current_player = if app.game.board.is_white_to_move() { "WHITE" } else { "BLACK" };
             format!("              {} TO MOVE", current_player)
@@ -0,0 +1,66 @@
+//! Zobrist hashing for Arx positions
+//!
+//! Keys are indexed by square and by the raw 7-bit piece code used in the binary
+//! board format, so the same hash can be computed from a `Board` or directly from
+//! the `[u8; 82]` representation used by the engine. Empty squares contribute
+//! nothing, which lets a move update the hash by XOR-ing only the changed squares.
+
+use crate::board::BOARD_SIZE;
+
+/// Number of distinct 7-bit piece codes
+const PIECE_CODES: usize = 128;
+
+/// Key XOR-ed into the hash when white is to move
+pub const WHITE_TO_MOVE_KEY: u64 = splitmix64(0x0A5C_0FFE_E000_0001).1;
+
+static PIECE_KEYS: [[u64; PIECE_CODES]; BOARD_SIZE] = generate_piece_keys();
+
+/// SplitMix64 step, returns the next state and the generated value
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, z ^ (z >> 31))
+}
+
+/// SplitMix64 value generated from `state`, used to derive independent seeds
+pub(crate) const fn mix64(state: u64) -> u64 {
+    splitmix64(state).1
+}
+
+const fn generate_piece_keys() -> [[u64; PIECE_CODES]; BOARD_SIZE] {
+    let mut keys = [[0u64; PIECE_CODES]; BOARD_SIZE];
+    let mut state = 0x4152_5845_4E47_494E; // "ARXENGIN"
+    let mut square = 0;
+    while square < BOARD_SIZE {
+        // Code 0 is the empty square and keeps a zero key
+        let mut code = 1;
+        while code < PIECE_CODES {
+            let (next_state, value) = splitmix64(state);
+            state = next_state;
+            keys[square][code] = value;
+            code += 1;
+        }
+        square += 1;
+    }
+    keys
+}
+
+/// Key for a piece code standing on a square (0 for an empty square)
+pub fn square_key(square: usize, code: u8) -> u64 {
+    PIECE_KEYS[square][(code & 0x7F) as usize]
+}
+
+/// Compute the hash of a board in binary format
+pub fn hash_binary(board: &[u8; BOARD_SIZE + 1]) -> u64 {
+    let mut hash = board
+        .iter()
+        .take(BOARD_SIZE)
+        .enumerate()
+        .fold(0u64, |hash, (square, &code)| hash ^ square_key(square, code));
+    if board[BOARD_SIZE] == 1 {
+        hash ^= WHITE_TO_MOVE_KEY;
+    }
+    hash
+}